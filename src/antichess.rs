@@ -0,0 +1,110 @@
+use crate::moves::{Move, captured_piece, valid_moves};
+use crate::piece::{Color, Piece};
+use crate::position::Position;
+
+// see: https://www.chessprogramming.org/Losing_Chess
+
+/// Returns every pseudo-legal move of `color`. Without check, these are all playable
+/// as far as king safety goes; castling is never generated in antichess.
+fn all_moves(color: Color, position: &Position) -> Vec<Move> {
+    let mut moves = Vec::new();
+    for piece_type in 0..6 {
+        let mut bb = position.bb_pieces[color.index()][piece_type].0;
+        while bb != 0 {
+            let from = bb.trailing_zeros() as u8;
+            bb &= bb - 1;
+            moves.extend(valid_moves(from, Piece::from_index(piece_type, color), position));
+        }
+    }
+    moves
+}
+
+/// Returns `true` if `color` can capture something, which makes capturing compulsory.
+pub fn has_capture(color: Color, position: &Position) -> bool {
+    all_moves(color, position).iter().any(|m| captured_piece(m, position).is_some())
+}
+
+/// Generates the legal moves for `color` in antichess.
+///
+/// The king is an ordinary piece, so there is no check to respect; but as soon
+/// as any capture is possible, only captures may be played.
+///
+/// # Arguments
+/// * `color` - The side to move.
+/// * `position` - The board state.
+///
+/// # Returns
+/// A vector of legal moves, empty if `color` has lost all its pieces or is stalemated.
+pub fn legal_moves(color: Color, position: &Position) -> Vec<Move> {
+    let mut moves = all_moves(color, position);
+    if moves.iter().any(|m| captured_piece(m, position).is_some()) {
+        moves.retain(|m| captured_piece(m, position).is_some());
+    }
+    moves
+}
+
+/// Statically evaluates an antichess position from the point of view of `color`.
+///
+/// Every piece is a burden to get rid of, so the score is the opponent's piece
+/// count minus `color`'s, at 100 centipawns a piece.
+pub fn evaluate(color: Color, position: &Position) -> i32 {
+    let count = |side: Color| position.bb_sides[side.index()].0.count_ones() as i32;
+    (count(color.opponent()) - count(color)) * 100
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{Game, GameResult};
+    use crate::make_move::make_move_quiet;
+    use crate::uci::{move_from_uci, move_to_uci};
+    use crate::variant::Variant;
+    use crate::variant::testing::{play, variant_game};
+
+    fn antichess_game(fen: &str) -> (Game, Color) {
+        variant_game(fen, Variant::Antichess)
+    }
+
+    fn uci_moves(color: Color, position: &Position) -> Vec<String> {
+        let mut moves: Vec<String> = legal_moves(color, position).iter().map(move_to_uci).collect();
+        moves.sort();
+        moves
+    }
+
+    #[test]
+    fn captures_are_compulsory() {
+        let (mut game, color) = antichess_game("4k3/8/8/3p4/4P3/8/8/4K3 w - - 0 1");
+        assert!(has_capture(color, &game.position));
+        assert_eq!(uci_moves(color, &game.position), ["e4d5"]);
+
+        // make_move_quiet refuses a quiet move even when given one built by hand
+        let quiet = valid_moves(4, Piece::King(Color::White), &game.position)[0];
+        assert!(make_move_quiet(quiet, &mut game).is_err());
+    }
+
+    #[test]
+    fn pawns_may_promote_to_a_king() {
+        let (game, color) = antichess_game("8/P7/8/8/8/8/8/7k w - - 0 1");
+        assert_eq!(uci_moves(color, &game.position), ["a7a8b", "a7a8k", "a7a8n", "a7a8q", "a7a8r"]);
+        let m = move_from_uci("a7a8k", color, &game.position).unwrap();
+        assert_eq!(m.piece, Piece::King(Color::White));
+    }
+
+    #[test]
+    fn losing_all_pieces_wins() {
+        let (mut game, color) = antichess_game("8/8/8/3p4/4P3/8/8/8 w - - 0 1");
+        play("e4d5", color, &mut game);
+        assert_eq!(game.result, GameResult::OutOfMoves(Color::White));
+        assert_eq!(game.result.winner(), Some(Color::Black));
+    }
+
+    #[test]
+    fn being_stalemated_wins() {
+        // after a3 the black pawn is blocked and Black has no move left
+        let (mut game, color) = antichess_game("8/8/8/8/p7/8/P7/8 w - - 0 1");
+        play("a2a3", color, &mut game);
+        assert!(legal_moves(Color::Black, &game.position).is_empty());
+        assert_eq!(game.result, GameResult::OutOfMoves(Color::White));
+        assert_eq!(game.result.winner(), Some(Color::Black));
+    }
+}
@@ -0,0 +1,122 @@
+use crate::make_move::is_checked;
+use crate::piece::Color;
+use crate::position::{Pieces, Position};
+
+// see: https://www.chessprogramming.org/Atomic_Chess
+
+/// Returns a bitboard of the (up to eight) squares adjacent to `square`.
+pub fn adjacent_squares(square: u8) -> u64 {
+    let file = (square % 8) as i8;
+    let rank = (square / 8) as i8;
+    let mut squares = 0u64;
+    for rank_step in -1..=1 {
+        for file_step in -1..=1 {
+            let (f, r) = (file + file_step, rank + rank_step);
+            if (file_step, rank_step) != (0, 0) && (0..8).contains(&f) && (0..8).contains(&r) {
+                squares |= 1u64 << (r * 8 + f);
+            }
+        }
+    }
+    squares
+}
+
+/// Returns `true` if both kings are on the board, next to each other.
+///
+/// Kings touching each other cannot be checked: capturing the enemy king
+/// would explode the capturer's own king too.
+pub fn kings_adjacent(position: &Position) -> bool {
+    let white = position.bb_pieces[0][Pieces::KING].0;
+    let black = position.bb_pieces[1][Pieces::KING].0;
+    white != 0 && black != 0 && adjacent_squares(white.trailing_zeros() as u8) & black != 0
+}
+
+/// Returns `true` if `color`'s king has been blown up.
+pub fn is_exploded(color: Color, position: &Position) -> bool {
+    position.bb_pieces[color.index()][Pieces::KING].0 == 0
+}
+
+/// Explodes a capture on `square`: the capturing piece standing there and every
+/// piece other than a pawn on the adjacent squares is removed, whatever its color.
+///
+/// # Arguments
+/// * `square` - The capture square (for en passant, the square the pawn moved to).
+/// * `position` - The board state after the capture, updated in place.
+pub(crate) fn explode(square: u8, position: &mut Position) {
+    let pawns = position.bb_pieces[0][Pieces::PAWN].0 | position.bb_pieces[1][Pieces::PAWN].0;
+    let blast = 1u64 << square | adjacent_squares(square) & !pawns;
+    for side in 0..2 {
+        position.bb_sides[side].0 &= !blast;
+        for piece_type in 0..6 {
+            position.bb_pieces[side][piece_type].0 &= !blast;
+        }
+    }
+}
+
+/// Returns `true` if a move by `color` that led to `after` is legal under atomic rules.
+///
+/// A move may never blow up the mover's own king. Exploding the enemy king wins
+/// at once, even if the mover's king is left attacked; otherwise the usual rule
+/// applies and the mover's king must not be in check.
+pub fn is_safe_after(color: Color, after: &Position) -> bool {
+    !is_exploded(color, after) && (is_exploded(color.opponent(), after) || !is_checked(color, after))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{Game, GameResult};
+    use crate::make_move::legal_moves;
+    use crate::uci::move_from_uci;
+    use crate::variant::Variant;
+    use crate::variant::testing::{play, variant_game};
+
+    fn atomic_game(fen: &str) -> (Game, Color) {
+        variant_game(fen, Variant::Atomic)
+    }
+
+    #[test]
+    fn explosion_removes_adjacent_pieces_but_pawns() {
+        // Rxd5 blows up the rook, the knight on d5, the bishop on c6 and the
+        // knight on e4; the pawns on c4 and e6 survive
+        let (mut game, color) = atomic_game("7k/8/2b1p3/3n4/2P1N3/8/8/3R3K w - - 0 1");
+        play("d1d5", color, &mut game);
+        let (c4, e6, h1, h8) = (26, 44, 7, 63);
+        assert_eq!(game.position.bb_sides[0].0, 1u64 << c4 | 1u64 << h1);
+        assert_eq!(game.position.bb_sides[1].0, 1u64 << e6 | 1u64 << h8);
+        assert_eq!(game.position.bb_pieces[0][Pieces::PAWN].0, 1u64 << c4);
+        assert_eq!(game.position.bb_pieces[1][Pieces::PAWN].0, 1u64 << e6);
+        assert_eq!(game.result, GameResult::Ongoing);
+    }
+
+    #[test]
+    fn king_cannot_capture() {
+        // Kxd2 would blow up the white king, so the checking pawn stays
+        let (game, color) = atomic_game("7k/8/8/8/8/8/3p4/4K3 w - - 0 1");
+        let moves = legal_moves(color, &game.position);
+        assert!(!moves.is_empty());
+        assert!(moves.iter().all(|m| m.to != 11));
+        assert!(move_from_uci("e1d2", color, &game.position).is_none());
+    }
+
+    #[test]
+    fn adjacent_kings_cannot_be_in_check() {
+        let (game, _) = atomic_game("8/8/8/8/8/3k4/3K3r/8 w - - 0 1");
+        assert!(kings_adjacent(&game.position));
+        assert!(!is_checked(Color::White, &game.position));
+
+        let (game, _) = atomic_game("8/8/8/8/3k4/8/3K3r/8 w - - 0 1");
+        assert!(!kings_adjacent(&game.position));
+        assert!(is_checked(Color::White, &game.position));
+    }
+
+    #[test]
+    fn exploding_the_king_wins() {
+        // the white king is in check, but blowing up the black one comes first
+        let (mut game, color) = atomic_game("4k2r/4n3/8/8/8/8/8/4Q2K w - - 0 1");
+        assert!(is_checked(Color::White, &game.position));
+        play("e1e7", color, &mut game);
+        assert!(is_exploded(Color::Black, &game.position));
+        assert_eq!(game.result, GameResult::KingExploded(Color::Black));
+        assert_eq!(game.result.winner(), Some(Color::White));
+    }
+}
@@ -0,0 +1,75 @@
+use std::env;
+use std::fs;
+use std::process;
+
+use chess::book_builder::{BookBuilder, BookOptions};
+use chess::pgn::load_pgn;
+
+const USAGE: &str = "usage: book <output.bin> <games.pgn>... [options]
+
+options:
+  --depth N          only record the first N plies of each game (default 24)
+  --min-games N      leave out moves played in fewer games (default 3)
+  --min-score P      leave out moves scoring below P percent for the side playing them (default 0)
+  --summary FILE     also write a human-readable listing of the book";
+
+/// Prints an error and the usage, and exits.
+fn fail(message: &str) -> ! {
+    eprintln!("{}\n\n{}", message, USAGE);
+    process::exit(2);
+}
+
+/// Builds a Polyglot book from PGN files.
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() < 3 {
+        fail("an output file and at least one PGN file are needed");
+    }
+    let mut options = BookOptions::default();
+    let mut summary_path = None;
+    let mut inputs = Vec::new();
+
+    let mut i = 2;
+    while i < args.len() {
+        let value = || args.get(i + 1).unwrap_or_else(|| fail(&format!("{} needs a value", args[i])));
+        let number = || value().parse::<f64>().unwrap_or_else(|_| fail(&format!("{} needs a number", args[i])));
+        match args[i].as_str() {
+            "--depth" => options.max_ply = number() as u32,
+            "--min-games" => options.min_games = number() as u32,
+            "--min-score" => options.min_score = number() / 100.0,
+            "--summary" => summary_path = Some(value().clone()),
+            flag if flag.starts_with("--") => fail(&format!("unknown option '{}'", flag)),
+            path => {
+                inputs.push(path.to_string());
+                i += 1;
+                continue;
+            }
+        }
+        i += 2;
+    }
+
+    let mut builder = BookBuilder::new(options);
+    let mut skipped = 0;
+    for path in &inputs {
+        let games = load_pgn(path).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            process::exit(1);
+        });
+        skipped += builder.add_games(&games);
+        println!("{}: {} games", path, games.len());
+    }
+
+    let book = builder.build();
+    if let Err(e) = fs::write(&args[1], book.to_bytes()) {
+        eprintln!("Cannot write {}: {}", args[1], e);
+        process::exit(1);
+    }
+    println!("wrote {} entries from {} games to {} ({} games skipped)", book.len(), builder.games, args[1], skipped);
+
+    if let Some(path) = summary_path
+        && let Err(e) = fs::write(&path, builder.summary())
+    {
+        eprintln!("Cannot write {}: {}", path, e);
+        process::exit(1);
+    }
+}
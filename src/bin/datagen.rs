@@ -0,0 +1,72 @@
+use std::env;
+use std::fs::File;
+use std::io::BufWriter;
+use std::process;
+use std::sync::Mutex;
+use std::thread;
+
+use chess::datagen::{DataGenOptions, generate};
+
+/// Self-play training data generation.
+///
+/// Usage: `datagen <output file> <games> [nodes per move] [seed] [threads]`
+///
+/// Thread `i` plays its share of the games with seed `seed + i` into its own
+/// buffer; the buffers are appended to the output file in thread order.
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() < 3 {
+        eprintln!("usage: {} <output file> <games> [nodes per move] [seed] [threads]", args[0]);
+        process::exit(2);
+    }
+    let number = |index: usize, default: u64| -> u64 {
+        args.get(index).map_or(default, |text| {
+            text.parse().unwrap_or_else(|_| {
+                eprintln!("invalid number '{}'", text);
+                process::exit(2);
+            })
+        })
+    };
+    let games = number(2, 0) as usize;
+    let options = DataGenOptions { node_limit: number(3, DataGenOptions::default().node_limit), ..DataGenOptions::default() };
+    let seed = number(4, 1);
+    let threads = number(5, 1).max(1) as usize;
+
+    let done = Mutex::new(0usize);
+    let buffers: Vec<Vec<u8>> = thread::scope(|scope| {
+        let handles: Vec<_> = (0..threads)
+            .map(|i| {
+                let share = games / threads + usize::from(i < games % threads);
+                let done = &done;
+                scope.spawn(move || {
+                    let mut buffer = Vec::new();
+                    generate(share, seed + i as u64, &options, &mut buffer, |_, _| {
+                        let mut done = done.lock().expect("progress lock");
+                        *done += 1;
+                        if done.is_multiple_of(10) {
+                            println!("{} / {} games", done, games);
+                        }
+                    })
+                    .expect("writing to memory cannot fail");
+                    buffer
+                })
+            })
+            .collect();
+        handles.into_iter().map(|h| h.join().expect("generator thread panicked")).collect()
+    });
+
+    let file = File::create(&args[1]).unwrap_or_else(|e| {
+        eprintln!("Cannot create {}: {}", args[1], e);
+        process::exit(1);
+    });
+    let mut writer = BufWriter::new(file);
+    let mut bytes = 0;
+    for buffer in &buffers {
+        if let Err(e) = std::io::Write::write_all(&mut writer, buffer) {
+            eprintln!("Cannot write {}: {}", args[1], e);
+            process::exit(1);
+        }
+        bytes += buffer.len();
+    }
+    println!("wrote {} records to {}", bytes / chess::datagen::RECORD_SIZE, args[1]);
+}
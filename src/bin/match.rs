@@ -0,0 +1,159 @@
+use std::env;
+use std::process;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use chess::engine::SearchLimits;
+use chess::match_runner::{Clock, MatchConfig, MatchStats, PlayerFactory, Sprt, SprtStatus, UciPlayer, load_openings, run_match};
+use chess::mcts::MctsSearcher;
+use chess::player::{EnginePlayer, GreedyCapturePlayer, MaterialPlayer, Player, RandomPlayer};
+use chess::search::Searcher;
+use chess::syzygy::Syzygy;
+use chess::tablebase::Tablebase;
+use chess::time_manager::TimeControl;
+
+const USAGE: &str = "usage: match <engine 1> <engine 2> [options]
+
+engines: a UCI executable (with arguments, quoted), or one of the built-in players
+         builtin (alpha-beta), mcts, material, greedy, random
+
+options:
+  --games N            number of games, played in color-swapped pairs (default 100)
+  --openings FILE      FEN/EPD start positions, one per line (default: start position)
+  --depth N            search depth per move
+  --nodes N            node limit per move
+  --movetime MS        time per move in milliseconds
+  --tc MS+INC          clock per game in milliseconds, e.g. 10000+100
+  --concurrency N      games played at the same time (default 1)
+  --sprt ELO0 ELO1     stop as soon as the SPRT (alpha = beta = 0.05) has a result
+  --maxplies N         draw games after N plies (default 400)
+  --tablebase DIR      adjudicate positions covered by the tables in DIR (see tbgen)
+  --syzygy PATH        adjudicate positions covered by the Syzygy WDL files in PATH
+  --option1 NAME=VALUE UCI option for engine 1 (repeatable)
+  --option2 NAME=VALUE UCI option for engine 2 (repeatable)";
+
+/// Prints an error and the usage, and exits.
+fn fail(message: &str) -> ! {
+    eprintln!("{}\n\n{}", message, USAGE);
+    process::exit(2);
+}
+
+fn parse<T: std::str::FromStr>(text: Option<&String>, flag: &str) -> T {
+    text.and_then(|t| t.parse().ok()).unwrap_or_else(|| fail(&format!("{} needs a number", flag)))
+}
+
+/// Creates the player described by `spec`; a different `seed` per game thread keeps the random bots apart.
+fn make_player(spec: &str, options: &[(String, String)], limits: SearchLimits, seed: u64) -> Result<Box<dyn Player>, String> {
+    Ok(match spec {
+        "builtin" => Box::new(EnginePlayer::new("builtin", Box::new(Searcher::new()), limits)),
+        "mcts" => Box::new(EnginePlayer::new("mcts", Box::new(MctsSearcher::new()), limits)),
+        "material" => Box::new(MaterialPlayer::new(seed)),
+        "greedy" => Box::new(GreedyCapturePlayer::new(seed)),
+        "random" => Box::new(RandomPlayer::new(seed)),
+        command => Box::new(UciPlayer::start(command, options, limits)?),
+    })
+}
+
+/// Plays a match between two engines and reports the score, Elo difference and SPRT state.
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() < 3 {
+        fail("two engines are needed");
+    }
+    let mut config = MatchConfig::default();
+    let mut limits = SearchLimits::default();
+    let mut options: [Vec<(String, String)>; 2] = [Vec::new(), Vec::new()];
+
+    let mut i = 3;
+    while i < args.len() {
+        let flag = args[i].as_str();
+        let value = args.get(i + 1);
+        match flag {
+            "--games" => config.games = parse(value, flag),
+            "--openings" => {
+                let path = value.unwrap_or_else(|| fail("--openings needs a file"));
+                config.openings = load_openings(path).unwrap_or_else(|e| fail(&e));
+            }
+            "--depth" => limits.depth = Some(parse(value, flag)),
+            "--nodes" => limits.nodes = Some(parse(value, flag)),
+            "--movetime" => limits.time = TimeControl { movetime: Some(parse(value, flag)), ..TimeControl::default() },
+            "--tc" => {
+                let text = value.unwrap_or_else(|| fail("--tc needs MS+INC"));
+                let (base, increment) = text.split_once('+').unwrap_or((text, "0"));
+                config.clock = Some(Clock {
+                    base_ms: parse(Some(&base.to_string()), flag),
+                    increment_ms: parse(Some(&increment.to_string()), flag),
+                });
+            }
+            "--concurrency" => config.concurrency = parse(value, flag),
+            "--maxplies" => config.adjudication.max_plies = parse(value, flag),
+            "--tablebase" => {
+                let dir = value.unwrap_or_else(|| fail("--tablebase needs a directory"));
+                let tablebase = Arc::new(Tablebase::load_dir(dir).unwrap_or_else(|e| fail(&e)));
+                config.adjudication.tablebase =
+                    Some(Box::new(move |position, color| tablebase.probe(position, color).map(|value| value.wdl())));
+            }
+            "--syzygy" => {
+                let path = value.unwrap_or_else(|| fail("--syzygy needs a path"));
+                let syzygy = Arc::new(Syzygy::new(path).unwrap_or_else(|e| fail(&e)));
+                config.adjudication.tablebase = Some(Box::new(move |position, color| syzygy.probe_wdl(position, color).map(|wdl| wdl.wdl())));
+            }
+            "--sprt" => {
+                config.sprt = Some(Sprt::new(parse(value, flag), parse(args.get(i + 2), flag)));
+                i += 1;
+            }
+            "--option1" | "--option2" => {
+                let text = value.unwrap_or_else(|| fail(&format!("{} needs NAME=VALUE", flag)));
+                let (name, option_value) = text.split_once('=').unwrap_or_else(|| fail(&format!("{} needs NAME=VALUE", flag)));
+                options[usize::from(flag == "--option2")].push((name.to_string(), option_value.to_string()));
+            }
+            _ => fail(&format!("unknown option '{}'", flag)),
+        }
+        i += 2;
+    }
+    if limits == SearchLimits::default() && config.clock.is_none() {
+        limits.depth = Some(4);
+    }
+
+    let seeds = AtomicU64::new(1);
+    let next_seed = || seeds.fetch_add(1, Ordering::Relaxed);
+    let first: &PlayerFactory<'_> = &|| make_player(&args[1], &options[0], limits, next_seed());
+    let second: &PlayerFactory<'_> = &|| make_player(&args[2], &options[1], limits, next_seed());
+
+    let sprt = config.sprt;
+    let report = |stats: &MatchStats| {
+        println!(
+            "Score: {} - {} - {} [{:.3}] {}   Elo: {:.1} +/- {:.1}",
+            stats.wins,
+            stats.losses,
+            stats.draws,
+            stats.score(),
+            stats.games(),
+            stats.elo(),
+            stats.elo_error()
+        );
+        if let Some(sprt) = sprt {
+            let (lower, upper) = sprt.bounds();
+            println!("SPRT [{}, {}]: LLR {:.2} ({:.2}, {:.2})", sprt.elo0, sprt.elo1, sprt.llr(stats), lower, upper);
+        }
+    };
+
+    let stats = run_match(&config, first, second, |stats, game| {
+        println!("Game {}: {} vs {}: {} {{{:?}}}", game.index + 1, game.white, game.black, game.result_text(), game.termination);
+        report(stats);
+    })
+    .unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        process::exit(1);
+    });
+
+    println!("\nFinished: {} vs {}", args[1], args[2]);
+    report(&stats);
+    if let Some(sprt) = sprt {
+        match sprt.status(&stats) {
+            SprtStatus::Pass => println!("SPRT: H1 accepted"),
+            SprtStatus::Fail => println!("SPRT: H0 accepted"),
+            SprtStatus::Continue => println!("SPRT: no result yet"),
+        }
+    }
+}
@@ -0,0 +1,26 @@
+use std::env;
+use std::fs;
+use std::process;
+
+use chess::nnue::Network;
+
+const USAGE: &str = "usage: nnuegen [output.nnue]
+
+Writes the tiny network built from the classical evaluation tables, which is
+bundled as nets/tiny.nnue (the default output).";
+
+/// Regenerates the bundled network file.
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() > 2 {
+        eprintln!("{}", USAGE);
+        process::exit(2);
+    }
+    let path = args.get(1).map_or("nets/tiny.nnue", String::as_str);
+    let network = Network::classical();
+    if let Err(e) = fs::write(path, network.to_bytes()) {
+        eprintln!("Cannot write {}: {}", path, e);
+        process::exit(1);
+    }
+    println!("{}: {} hidden neurons", path, network.hidden_size());
+}
@@ -0,0 +1,37 @@
+use std::env;
+use std::process;
+use std::time::Instant;
+
+use chess::tablebase::{MAX_PIECES, Tablebase, endings};
+
+const USAGE: &str = "usage: tbgen <dir> [ending...]
+
+Generates endgame tables, e.g. KQK, KRKP or KBNK, together with the smaller
+tables they depend on, and writes them to <dir>. Without endings, all endings
+of up to 4 pieces are generated. Tables already in <dir> are reused.";
+
+/// Generates endgame tables by retrograde analysis.
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    let Some(dir) = args.get(1) else {
+        eprintln!("{}", USAGE);
+        process::exit(2);
+    };
+    let mut tablebase = Tablebase::load_dir(dir).unwrap_or_default();
+    let names: Vec<String> =
+        if args.len() > 2 { args[2..].to_vec() } else { (3..=MAX_PIECES).flat_map(endings).collect() };
+
+    for name in &names {
+        let start = Instant::now();
+        if let Err(e) = tablebase.generate(name) {
+            eprintln!("{}", e);
+            process::exit(2);
+        }
+        println!("{}: done in {:.1}s", name, start.elapsed().as_secs_f64());
+    }
+    if let Err(e) = tablebase.save_dir(dir) {
+        eprintln!("{}", e);
+        process::exit(1);
+    }
+    println!("{} tables in {}", tablebase.names().len(), dir);
+}
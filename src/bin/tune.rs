@@ -0,0 +1,50 @@
+use std::env;
+use std::process;
+
+use chess::eval::EvalParams;
+use chess::tuning::{TuningOptions, find_k, load_any, mean_error, tune};
+
+/// Texel tuning of the evaluation parameters.
+///
+/// Usage: `tune <positions file> <output file> [iterations] [start parameters file]`
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() < 3 {
+        eprintln!("usage: {} <positions file> <output file> [iterations] [start parameters file]", args[0]);
+        process::exit(2);
+    }
+
+    let positions = load_any(&args[1]).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        process::exit(1);
+    });
+    let mut options = TuningOptions::default();
+    if let Some(iterations) = args.get(3) {
+        options.iterations = iterations.parse().unwrap_or_else(|_| {
+            eprintln!("invalid iteration count '{}'", iterations);
+            process::exit(2);
+        });
+    }
+    let start = match args.get(4) {
+        Some(path) => EvalParams::load(path).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            process::exit(1);
+        }),
+        None => EvalParams::default(),
+    };
+
+    println!("{} positions", positions.len());
+    let k = find_k(&start, &positions);
+    println!("k = {:.4}, initial error {:.6}", k, mean_error(&start, &positions, k));
+
+    let tuned = tune(&start, &positions, k, &options, |iteration, error| {
+        println!("iteration {:5}: error {:.6}", iteration, error);
+    });
+    println!("final error {:.6}", mean_error(&tuned, &positions, k));
+
+    if let Err(e) = std::fs::write(&args[2], tuned.to_text()) {
+        eprintln!("Cannot write {}: {}", args[2], e);
+        process::exit(1);
+    }
+    println!("wrote {}", args[2]);
+}
@@ -0,0 +1,4 @@
+// UCI engine binary: `cargo run --release --bin uci`
+fn main() {
+    chess::uci::run();
+}
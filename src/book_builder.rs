@@ -0,0 +1,246 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use crate::fen::to_fen;
+use crate::piece::Color;
+use crate::pgn::{PgnGame, move_to_san};
+use crate::polyglot::{Book, BookEntry, encode_move, polyglot_key};
+use crate::variant::Variant;
+
+/// Settings for building a book.
+#[derive(Debug, Clone, Copy)]
+pub struct BookOptions {
+    /// Only moves played within the first `max_ply` plies of a game are recorded.
+    pub max_ply: u32,
+    /// Moves played in fewer games are left out of the book.
+    pub min_games: u32,
+    /// Moves scoring less than this for the side that played them (0.0–1.0) are left out.
+    pub min_score: f64,
+}
+
+impl Default for BookOptions {
+    fn default() -> Self {
+        BookOptions { max_ply: 24, min_games: 3, min_score: 0.0 }
+    }
+}
+
+/// Results of the games in which a move was played, from the point of view of the side that played it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MoveStats {
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+}
+
+impl MoveStats {
+    pub fn games(&self) -> u32 {
+        self.wins + self.draws + self.losses
+    }
+
+    /// Average score of the move (win = 1, draw = 0.5).
+    pub fn score(&self) -> f64 {
+        if self.games() == 0 {
+            return 0.0;
+        }
+        (self.wins as f64 + 0.5 * self.draws as f64) / self.games() as f64
+    }
+
+    /// Book weight as used by Polyglot: two points per win and one per draw.
+    pub fn points(&self) -> u64 {
+        2 * self.wins as u64 + self.draws as u64
+    }
+}
+
+/// A move seen in a position.
+#[derive(Debug, Clone)]
+struct MoveRecord {
+    raw_move: u16,
+    san: String,
+    stats: MoveStats,
+}
+
+/// A position seen in the games, with the moves played from it.
+#[derive(Debug, Clone)]
+struct PositionRecord {
+    fen: String,
+    /// Ply at which the position was first seen.
+    ply: u32,
+    moves: Vec<MoveRecord>,
+}
+
+/// Collects move statistics from PGN games and turns them into a Polyglot book.
+pub struct BookBuilder {
+    pub options: BookOptions,
+    positions: HashMap<u64, PositionRecord>,
+    /// Number of games added.
+    pub games: usize,
+}
+
+impl BookBuilder {
+    pub fn new(options: BookOptions) -> Self {
+        BookBuilder { options, positions: HashMap::new(), games: 0 }
+    }
+
+    /// Replays a game and counts its first [`BookOptions::max_ply`] moves.
+    ///
+    /// # Errors
+    /// Returns `Err(String)` if the game has no result, contains an illegal
+    /// move or is not standard chess; nothing is counted then.
+    pub fn add_game(&mut self, game: &PgnGame) -> Result<(), String> {
+        let white_score = game.white_score().ok_or("game has no result")?;
+        let variant = game.start_position()?.0.variant;
+        if variant != Variant::Standard {
+            return Err(format!("{} games cannot go into a Polyglot book", variant.pgn_name()));
+        }
+        let line = game.replay()?;
+        for (ply, (position, color, m)) in line.iter().take(self.options.max_ply as usize).enumerate() {
+            let record = self.positions.entry(polyglot_key(position, *color)).or_insert_with(|| PositionRecord {
+                fen: to_fen(position, *color),
+                ply: ply as u32,
+                moves: Vec::new(),
+            });
+            let raw_move = encode_move(m);
+            let index = match record.moves.iter().position(|r| r.raw_move == raw_move) {
+                Some(index) => index,
+                None => {
+                    record.moves.push(MoveRecord { raw_move, san: move_to_san(m, position), stats: MoveStats::default() });
+                    record.moves.len() - 1
+                }
+            };
+            let stats = &mut record.moves[index].stats;
+            let score = if *color == Color::White { white_score } else { 1.0 - white_score };
+            match score {
+                s if s > 0.75 => stats.wins += 1,
+                s if s < 0.25 => stats.losses += 1,
+                _ => stats.draws += 1,
+            }
+        }
+        self.games += 1;
+        Ok(())
+    }
+
+    /// Adds many games, skipping the ones [`BookBuilder::add_game`] rejects.
+    ///
+    /// # Returns
+    /// The number of skipped games.
+    pub fn add_games(&mut self, games: &[PgnGame]) -> usize {
+        games.iter().filter(|game| self.add_game(game).is_err()).count()
+    }
+
+    fn keeps(&self, stats: &MoveStats) -> bool {
+        stats.games() >= self.options.min_games && stats.score() >= self.options.min_score
+    }
+
+    /// Positions that have at least one move passing the filters, with those moves,
+    /// ordered by ply and then by popularity.
+    fn selected(&self) -> Vec<(u64, &PositionRecord, Vec<&MoveRecord>)> {
+        let mut selected: Vec<_> = self
+            .positions
+            .iter()
+            .filter_map(|(&key, record)| {
+                let mut moves: Vec<&MoveRecord> = record.moves.iter().filter(|r| self.keeps(&r.stats)).collect();
+                moves.sort_by_key(|r| std::cmp::Reverse(r.stats.games()));
+                (!moves.is_empty()).then_some((key, record, moves))
+            })
+            .collect();
+        let games = |moves: &[&MoveRecord]| moves.iter().map(|r| r.stats.games()).sum::<u32>();
+        selected.sort_by(|a, b| a.1.ply.cmp(&b.1.ply).then(games(&b.2).cmp(&games(&a.2))).then(a.0.cmp(&b.0)));
+        selected
+    }
+
+    /// Builds the book from the moves that pass the filters.
+    ///
+    /// Weights are [`MoveStats::points`], scaled down per position if they would
+    /// not fit into 16 bits.
+    pub fn build(&self) -> Book {
+        let mut entries = Vec::new();
+        for (key, _, moves) in self.selected() {
+            let max_points = moves.iter().map(|r| r.stats.points()).max().unwrap_or(0);
+            for record in moves {
+                let mut weight = record.stats.points();
+                if max_points > u16::MAX as u64 {
+                    weight = weight * u16::MAX as u64 / max_points;
+                }
+                entries.push(BookEntry { key, raw_move: record.raw_move, weight: weight as u16, learn: 0 });
+            }
+        }
+        Book::new(entries)
+    }
+
+    /// Human-readable listing of the book: every position as FEN, followed by its
+    /// moves with game count, wins/draws/losses and score of the side to move.
+    pub fn summary(&self) -> String {
+        let selected = self.selected();
+        let move_count: usize = selected.iter().map(|(_, _, moves)| moves.len()).sum();
+        let mut text = String::new();
+        let _ = writeln!(text, "# {} positions, {} moves from {} games", selected.len(), move_count, self.games);
+        for (_, record, moves) in selected {
+            let _ = writeln!(text, "\n{}", record.fen);
+            for r in moves {
+                let s = &r.stats;
+                let _ = writeln!(
+                    text,
+                    "  {:<8} {:>6} games  +{} ={} -{}  {:.1}%",
+                    r.san,
+                    s.games(),
+                    s.wins,
+                    s.draws,
+                    s.losses,
+                    100.0 * s.score()
+                );
+            }
+        }
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fen::{START_FEN, parse_fen};
+    use crate::pgn::parse_pgn;
+    use crate::uci::move_to_uci;
+
+    const GAMES: &str = "1. e4 e5 2. Nf3 1-0\n\n1. e4 c5 1/2-1/2\n\n1. d4 d5 0-1\n\n1. e4 e5 0-1\n\n1. Nf3 *\n";
+
+    fn book_moves(book: &Book, fen: &str) -> Vec<(String, u16)> {
+        let (position, color) = parse_fen(fen).unwrap();
+        book.moves(&position, color).iter().map(|(m, weight)| (move_to_uci(m), *weight)).collect()
+    }
+
+    #[test]
+    fn pgn_to_polyglot() {
+        let mut builder = BookBuilder::new(BookOptions { max_ply: 2, min_games: 1, min_score: 0.0 });
+        // the game without a result is skipped
+        assert_eq!(builder.add_games(&parse_pgn(GAMES)), 1);
+        assert_eq!(builder.games, 4);
+        let book = builder.build();
+
+        // e4: one win, one draw and one loss; d4: one loss
+        let start = 0x463b_9618_1691_fc9c;
+        assert_eq!(book.entries_for(start).len(), 2);
+        assert_eq!(book_moves(&book, START_FEN), [("e2e4".to_string(), 3), ("d2d4".to_string(), 0)]);
+        // after 1. e4: e5 won once and lost once for Black, c5 drew
+        let after_e4 = "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1";
+        assert_eq!(book.entries_for(0x823c_9b50_fd11_4196).len(), 2);
+        assert_eq!(book_moves(&book, after_e4), [("e7e5".to_string(), 2), ("c7c5".to_string(), 1)]);
+        // max_ply keeps 2. Nf3 out
+        assert_eq!(book.len(), 5);
+        assert_eq!(Book::from_bytes(&book.to_bytes()).unwrap().entries(), book.entries());
+    }
+
+    #[test]
+    fn filters() {
+        let games = parse_pgn(GAMES);
+        let mut builder = BookBuilder::new(BookOptions { max_ply: 2, min_games: 2, min_score: 0.0 });
+        builder.add_games(&games);
+        let book = builder.build();
+        assert_eq!(book_moves(&book, START_FEN), [("e2e4".to_string(), 3)]);
+        assert_eq!(book.len(), 2);
+
+        let mut builder = BookBuilder::new(BookOptions { max_ply: 2, min_games: 1, min_score: 0.5 });
+        builder.add_games(&games);
+        let book = builder.build();
+        assert_eq!(book_moves(&book, START_FEN), [("e2e4".to_string(), 3)]);
+    }
+}
@@ -0,0 +1,137 @@
+use crate::fen::parse_fen;
+use crate::piece::Color;
+use crate::position::Position;
+
+// see: https://www.chessprogramming.org/Chess960
+// and: https://en.wikipedia.org/wiki/Fischer_random_chess_numbering_scheme
+
+/// Number of Chess960 starting positions.
+pub const POSITION_COUNT: u16 = 960;
+
+/// Index of the standard starting position in the Scharnagl numbering.
+pub const STANDARD_INDEX: u16 = 518;
+
+/// Knight placements on the five squares left after the bishops and the queen.
+const KNIGHTS: [(usize, usize); 10] = [(0, 1), (0, 2), (0, 3), (0, 4), (1, 2), (1, 3), (1, 4), (2, 3), (2, 4), (3, 4)];
+
+/// Returns White's back rank (files a to h) for a starting position index,
+/// e.g. `"RNBQKBNR"` for index 518.
+///
+/// # Errors
+/// Returns `Err(String)` if `index` is not below [`POSITION_COUNT`].
+pub fn back_rank(index: u16) -> Result<String, String> {
+    if index >= POSITION_COUNT {
+        return Err(format!("Chess960 position index must be below {}, got {}", POSITION_COUNT, index));
+    }
+    let mut rank = [' '; 8];
+    let mut n = index as usize;
+    // bishops on a light (b, d, f, h) and a dark (a, c, e, g) square
+    rank[2 * (n % 4) + 1] = 'B';
+    n /= 4;
+    rank[2 * (n % 4)] = 'B';
+    n /= 4;
+
+    // the queen, then the knights, on the n-th empty square
+    let place = |rank: &mut [char; 8], nth: usize, piece: char| {
+        let file = (0..8).filter(|&file| rank[file] == ' ').nth(nth).expect("enough empty files");
+        rank[file] = piece;
+    };
+    place(&mut rank, n % 6, 'Q');
+    n /= 6;
+    let (first, second) = KNIGHTS[n];
+    // placing the right knight first leaves the left one's count of empty squares unchanged
+    place(&mut rank, second, 'N');
+    place(&mut rank, first, 'N');
+
+    // the king between the rooks on the three squares left
+    for piece in ['R', 'K', 'R'] {
+        place(&mut rank, 0, piece);
+    }
+    Ok(rank.iter().collect())
+}
+
+/// Returns the X-FEN of a Chess960 starting position, e.g.
+/// `"bbqnnrkr/pppppppp/8/8/8/8/PPPPPPPP/BBQNNRKR w KQkq - 0 1"` for index 0.
+///
+/// # Errors
+/// Returns `Err(String)` if `index` is not below [`POSITION_COUNT`].
+pub fn start_fen(index: u16) -> Result<String, String> {
+    let white = back_rank(index)?;
+    Ok(format!("{}/pppppppp/8/8/8/8/PPPPPPPP/{} w KQkq - 0 1", white.to_ascii_lowercase(), white))
+}
+
+/// Returns a Chess960 starting position and the side to move (White).
+///
+/// Castling uses Chess960 rules and encoding (see
+/// [`CastlingRights::chess960`](crate::piece::CastlingRights::chess960)), also for
+/// the standard arrangement at [`STANDARD_INDEX`].
+///
+/// # Errors
+/// Returns `Err(String)` if `index` is not below [`POSITION_COUNT`].
+pub fn start_position(index: u16) -> Result<(Position, Color), String> {
+    let (mut position, color) = parse_fen(&start_fen(index)?)?;
+    position.castling_rights.chess960 = true;
+    Ok((position, color))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::make_move::perft;
+
+    fn perft_index(index: u16, depth: u32) -> u64 {
+        let (position, color) = start_position(index).unwrap();
+        perft(color, &position, depth)
+    }
+
+    fn perft_fen(fen: &str, depth: u32) -> u64 {
+        let (position, color) = parse_fen(fen).unwrap();
+        assert!(position.castling_rights.chess960);
+        perft(color, &position, depth)
+    }
+
+    #[test]
+    fn back_ranks() {
+        assert_eq!(back_rank(0).unwrap(), "BBQNNRKR");
+        assert_eq!(back_rank(STANDARD_INDEX).unwrap(), "RNBQKBNR");
+        assert_eq!(back_rank(709).unwrap(), "RKBBQNNR");
+        assert_eq!(back_rank(959).unwrap(), "RKRNNQBB");
+        assert!(back_rank(POSITION_COUNT).is_err());
+    }
+
+    #[test]
+    fn perft_start_positions() {
+        assert_eq!(perft_index(STANDARD_INDEX, 3), 8_902);
+        // king on the g-file, a rook on each side next to it
+        assert_eq!(perft_index(0, 3), 9_006);
+        // king on the b-file
+        assert_eq!(perft_index(709, 3), 8_964);
+        assert_eq!(perft_index(959, 3), 9_006);
+    }
+
+    #[test]
+    fn perft_castling_king_on_b_file() {
+        // kingside castling carries the king past both rooks; queenside the other rook is in the way
+        assert_eq!(perft_fen("rkr5/pppppppp/8/8/8/8/PPPPPPPP/RKR5 w KQkq - 0 1", 3), 10_720);
+        assert_eq!(perft_fen("rk5r/pppppppp/8/8/8/8/PPPPPPPP/RK5R w KQkq - 0 1", 3), 13_518);
+    }
+
+    #[test]
+    fn perft_castling_king_on_g_file() {
+        // kingside castling leaves the king on its square and only moves the rook
+        assert_eq!(perft_fen("5rkr/pppppppp/8/8/8/8/PPPPPPPP/5RKR w KQkq - 0 1", 3), 10_698);
+    }
+
+    #[test]
+    fn perft_castling_king_on_c_file() {
+        // queenside castling leaves the king on its square, the rook jumps over it
+        assert_eq!(perft_fen("1rk2r2/pppppppp/8/8/8/8/PPPPPPPP/1RK2R2 w FBfb - 0 1", 3), 13_494);
+    }
+
+    #[test]
+    fn perft_reference_positions() {
+        // see: https://www.chessprogramming.org/Chess960_Perft_Results
+        assert_eq!(perft_fen("bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9", 3), 12_189);
+        assert_eq!(perft_fen("2nnrbkr/p1qppppp/8/1ppb4/6PP/3PP3/PPP2P2/BQNNRBKR w HEhe - 1 9", 3), 18_002);
+    }
+}
@@ -0,0 +1,234 @@
+use crate::eval::PIECE_VALUES;
+use crate::make_move::is_checked;
+use crate::moves::{Move, valid_moves};
+use crate::piece::{Color, Piece};
+use crate::position::{Pieces, Position};
+
+// see: https://www.chessprogramming.org/Crazyhouse
+
+/// Squares a pawn may be dropped on: ranks 2 to 7.
+const PAWN_DROP_SQUARES: u64 = 0x00FF_FFFF_FFFF_FF00;
+
+/// Returns how many pieces of the given type `color` holds in its pocket.
+///
+/// # Arguments
+/// * `color` - The pocket's owner.
+/// * `piece_type` - A piece type from [`Pieces::PAWN`] to [`Pieces::QUEEN`].
+/// * `position` - The board state.
+pub fn pocket_count(color: Color, piece_type: usize, position: &Position) -> u8 {
+    position.pockets[color.index()][piece_type]
+}
+
+/// Returns the material value of `color`'s pocket minus that of the opponent's, in centipawns.
+pub fn pocket_material(color: Color, position: &Position) -> i32 {
+    let value = |side: usize| -> i32 {
+        (0..5).map(|piece_type| position.pockets[side][piece_type] as i32 * PIECE_VALUES[piece_type]).sum()
+    };
+    value(color.index()) - value(color.opponent().index())
+}
+
+/// Generates *pseudo-legal* drops for `color`: every pocket piece on every empty
+/// square, except pawns on the first and last rank.
+///
+/// # Arguments
+/// * `color` - The side to move.
+/// * `position` - The board state.
+///
+/// # Returns
+/// A vector of drop moves (empty outside Crazyhouse, where pockets stay empty).
+pub fn drop_moves(color: Color, position: &Position) -> Vec<Move> {
+    let empty = !(position.bb_sides[0].0 | position.bb_sides[1].0);
+    drops_on(color, position, empty)
+}
+
+/// Generates the legal drops for `color`.
+///
+/// A drop only adds a piece, so it never exposes its own king: out of check
+/// every drop is legal. In check, a drop must block the checking slider, so
+/// only empty squares on the king's lines are tried, and each of them once for
+/// all piece types (a knight or pawn check, or a double check, leaves none).
+///
+/// # Arguments
+/// * `color` - The side to move.
+/// * `position` - The board state.
+///
+/// # Returns
+/// A vector of legal drop moves.
+pub fn legal_drops(color: Color, position: &Position) -> Vec<Move> {
+    let side = color.index();
+    if position.pockets[side].iter().all(|&count| count == 0) {
+        return Vec::new();
+    }
+    let empty = !(position.bb_sides[0].0 | position.bb_sides[1].0);
+    if !is_checked(color, position) {
+        return drops_on(color, position, empty);
+    }
+
+    let king_bb = position.bb_pieces[side][Pieces::KING].0;
+    if king_bb == 0 {
+        return Vec::new();
+    }
+    let king_square = king_bb.trailing_zeros() as u8;
+    // empty squares a queen on the king's square would reach: the only candidate blocks
+    let lines = valid_moves(king_square, Piece::Queen(color), position)
+        .iter()
+        .fold(0u64, |lines, m| lines | 1u64 << m.to)
+        & empty;
+
+    let mut blocks = 0u64;
+    let mut bb = lines;
+    while bb != 0 {
+        let square = bb.trailing_zeros() as u8;
+        bb &= bb - 1;
+        let mut test_pos = *position;
+        test_pos.bb_sides[side].0 |= 1u64 << square;
+        if !is_checked(color, &test_pos) {
+            blocks |= 1u64 << square;
+        }
+    }
+    drops_on(color, position, blocks)
+}
+
+/// Drops of every pocket piece of `color` on the squares in `targets`.
+fn drops_on(color: Color, position: &Position, targets: u64) -> Vec<Move> {
+    let mut moves = Vec::new();
+    for piece_type in Pieces::PAWN..Pieces::KING {
+        if position.pockets[color.index()][piece_type] == 0 {
+            continue;
+        }
+        let piece = Piece::from_index(piece_type, color);
+        let mut bb = if piece_type == Pieces::PAWN { targets & PAWN_DROP_SQUARES } else { targets };
+        while bb != 0 {
+            let to = bb.trailing_zeros() as u8;
+            bb &= bb - 1;
+            moves.push(Move { from: to, to, piece, promoted_from_pawn: false, drop: true, duck: None });
+        }
+    }
+    moves
+}
+
+/// Applies a drop: takes the piece from the pocket and places it on the board.
+pub(crate) fn apply_drop(m: Move, position: &mut Position) {
+    let side = m.piece.color().index();
+    let piece_type = m.piece.index();
+    let pocket = &mut position.pockets[side][piece_type];
+    *pocket = pocket.saturating_sub(1);
+    position.bb_sides[side].0 |= 1u64 << m.to;
+    position.bb_pieces[side][piece_type].0 |= 1u64 << m.to;
+    position.en_passant = None;
+}
+
+/// Puts a piece captured on `square` into the capturer's pocket, as a pawn if
+/// it was promoted.
+///
+/// # Arguments
+/// * `capturer` - The side index of the capturing side.
+/// * `piece_type` - The type of the captured piece.
+/// * `square` - The square it was captured on.
+/// * `position` - The board state to update.
+pub(crate) fn pocket_capture(capturer: usize, piece_type: usize, square: u8, position: &mut Position) {
+    let mask = 1u64 << square;
+    let piece_type = if position.promoted.0 & mask != 0 { Pieces::PAWN } else { piece_type };
+    position.promoted.0 &= !mask;
+    // a king is only captured in variants without pockets
+    if piece_type != Pieces::KING {
+        position.pockets[capturer][piece_type] += 1;
+    }
+}
+
+/// Moves the promoted marker along with a moved piece, and sets it for a promotion.
+pub(crate) fn track_promoted(m: Move, position: &mut Position) {
+    let from = 1u64 << m.from;
+    if m.promoted_from_pawn || position.promoted.0 & from != 0 {
+        position.promoted.0 &= !from;
+        position.promoted.0 |= 1u64 << m.to;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fen::{parse_fen, to_fen};
+    use crate::game::Game;
+    use crate::make_move::make_move_quiet;
+    use crate::pgn::{move_from_san, move_to_san};
+    use crate::uci::{move_from_uci, move_to_uci};
+    use crate::variant::Variant;
+    use crate::variant::testing::{play, variant_game};
+
+    fn round_trip(fen: &str) -> String {
+        let (position, color) = parse_fen(fen).unwrap();
+        assert_eq!(position.variant, Variant::Crazyhouse);
+        to_fen(&position, color)
+    }
+
+    #[test]
+    fn pockets_round_trip() {
+        let fen = "rnb1kbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNB1KBNR[QNPqp] w KQkq - 0 1";
+        assert_eq!(round_trip(fen), fen);
+        let (position, _) = parse_fen(fen).unwrap();
+        assert_eq!(pocket_count(Color::White, Pieces::QUEEN, &position), 1);
+        assert_eq!(pocket_count(Color::White, Pieces::KNIGHT, &position), 1);
+        assert_eq!(pocket_count(Color::Black, Pieces::PAWN, &position), 1);
+        assert_eq!(pocket_count(Color::Black, Pieces::KNIGHT, &position), 0);
+
+        // the pockets as a ninth rank are written in brackets
+        let slashed = "rnb1kbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNB1KBNR/qpQNP w KQkq - 0 1";
+        assert_eq!(round_trip(slashed), fen);
+        assert_eq!(round_trip("4k3/8/8/8/8/8/8/4K3[] w - - 0 1"), "4k3/8/8/8/8/8/8/4K3[] w - - 0 1");
+        assert!(parse_fen("4k3/8/8/8/8/8/8/4K3[K] w - - 0 1").is_err());
+    }
+
+    #[test]
+    fn promoted_markers_round_trip() {
+        let fen = "4k3/8/8/8/8/8/8/Rq~2K3[] w - - 0 1";
+        assert_eq!(round_trip(fen), fen);
+        let (position, _) = parse_fen(fen).unwrap();
+        assert_eq!(position.promoted.0, 1 << 1);
+        assert!(parse_fen("4k3/8/8/8/8/8/8/~R3K3[] w - - 0 1").is_err());
+    }
+
+    #[test]
+    fn drops_round_trip_in_san_and_uci() {
+        let (position, color) = parse_fen("4k3/8/8/8/8/8/8/4K3[NP] w - - 0 1").unwrap();
+        let m = move_from_san("N@f3", color, &position).unwrap();
+        assert!(m.drop);
+        assert_eq!(m.piece, Piece::Knight(Color::White));
+        assert_eq!(m.to, 21);
+        assert_eq!(move_to_san(&m, &position), "N@f3");
+        assert_eq!(move_to_uci(&m), "N@f3");
+        assert_eq!(move_from_uci("N@f3", color, &position), Some(m));
+
+        let pawn = move_from_san("@e4", color, &position).unwrap();
+        assert_eq!(move_to_san(&pawn, &position), "P@e4");
+        assert!(move_from_san("P@e8", color, &position).is_none());
+        assert!(move_from_san("Q@d4", color, &position).is_none());
+
+        let mut game = Game::new(position);
+        make_move_quiet(m, &mut game).unwrap();
+        assert_eq!(to_fen(&game.position, Color::Black), "4k3/8/8/8/8/5N2/8/4K3[P] b - - 0 1");
+    }
+
+    #[test]
+    fn captured_promoted_piece_goes_to_the_pocket_as_a_pawn() {
+        let (mut game, color) = variant_game("4k3/8/8/8/8/8/8/Rq~2K3[] w - - 0 1", Variant::Crazyhouse);
+        play("a1b1", color, &mut game);
+        assert_eq!(game.position.pockets[Color::White.index()], [1, 0, 0, 0, 0]);
+        assert_eq!(game.position.promoted.0, 0);
+        assert_eq!(to_fen(&game.position, Color::Black), "4k3/8/8/8/8/8/8/1R2K3[P] b - - 0 1");
+    }
+
+    #[test]
+    fn promotions_are_marked() {
+        let (mut game, color) = variant_game("4k3/1P6/8/8/8/8/8/4K3[] w - - 0 1", Variant::Crazyhouse);
+        play("b7b8q", color, &mut game);
+        assert_eq!(to_fen(&game.position, Color::Black), "1Q~2k3/8/8/8/8/8/8/4K3[] b - - 0 1");
+
+        // the marker follows the piece, and is gone once it is captured
+        play("e8d7", Color::Black, &mut game);
+        play("b8c8", Color::White, &mut game);
+        assert_eq!(to_fen(&game.position, Color::Black), "2Q~5/3k4/8/8/8/8/8/4K3[] b - - 0 1");
+        play("d7c8", Color::Black, &mut game);
+        assert_eq!(to_fen(&game.position, Color::White), "2k5/8/8/8/8/8/8/4K3[p] w - - 0 1");
+    }
+}
@@ -0,0 +1,388 @@
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+
+use crate::bitboard::BitBoard;
+use crate::fen::{START_FEN, parse_fen};
+use crate::game::GameResult;
+use crate::make_move::{is_checked, is_insufficient_material, legal_moves};
+use crate::move_ordering::MAX_PLY;
+use crate::moves::captured_piece;
+use crate::piece::{CastlingRights, Color, Piece};
+use crate::position::{Position, get_piece_at};
+use crate::rng::Rng;
+use crate::search::{Searcher, is_mate_score, play};
+use crate::variant::Variant;
+use crate::zobrist::zobrist_key;
+
+/// Size of one encoded [`TrainingRecord`] in bytes.
+pub const RECORD_SIZE: usize = 32;
+
+/*
+Record layout (32 bytes, integers little endian). A file is a plain sequence of
+records without a header, so files can be concatenated.
+
+  0..8   occupancy bitboard (u64)
+  8..24  one 4-bit piece code per occupied square, in ascending square order,
+         low nibble first; code = color * 6 + piece type (white pawn = 0 ... black king = 11)
+  24     flags: bit 0 black to move, bits 1..5 castling rights K, Q, k, q
+  25     en passant square, 255 if none
+  26..28 search score (i16), from the side to move's point of view
+  28     game result: 0 = black won, 1 = draw, 2 = white won
+  29..31 ply of the position in its game (u16)
+  31     reserved, 0
+*/
+
+/// One training sample: a position, its search score and the final result of its game.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TrainingRecord {
+    pub position: Position,
+    /// The side to move.
+    pub color: Color,
+    /// Search score in centipawns, from the point of view of `color`.
+    pub score: i16,
+    /// How the game ended. Draws of any kind are stored as [`GameResult::Draw`].
+    pub result: GameResult,
+    /// Number of plies played in the game before this position.
+    pub ply: u16,
+}
+
+impl TrainingRecord {
+    /// The game result from White's point of view: `1.0` win, `0.5` draw, `0.0` loss.
+    pub fn white_result(&self) -> f64 {
+        match self.result.winner() {
+            Some(Color::White) => 1.0,
+            Some(Color::Black) => 0.0,
+            None => 0.5,
+        }
+    }
+
+    /// Encodes the record in the documented 32-byte format.
+    pub fn encode(&self) -> [u8; RECORD_SIZE] {
+        let mut bytes = [0u8; RECORD_SIZE];
+        let occupied = self.position.bb_sides[0].0 | self.position.bb_sides[1].0;
+        bytes[0..8].copy_from_slice(&occupied.to_le_bytes());
+
+        let mut bb = occupied;
+        let mut i = 0;
+        while bb != 0 {
+            let square = bb.trailing_zeros() as u8;
+            bb &= bb - 1;
+            let piece = get_piece_at(&self.position, square).expect("occupied square has a piece");
+            let code = (piece.color().index() * 6 + piece.index()) as u8;
+            bytes[8 + i / 2] |= code << (4 * (i % 2));
+            i += 1;
+        }
+
+        let cr = &self.position.castling_rights;
+        let mut flags = (self.color == Color::Black) as u8;
+        flags |= ((!cr.white_king_moved && !cr.white_kingside_rook_moved) as u8) << 1;
+        flags |= ((!cr.white_king_moved && !cr.white_queenside_rook_moved) as u8) << 2;
+        flags |= ((!cr.black_king_moved && !cr.black_kingside_rook_moved) as u8) << 3;
+        flags |= ((!cr.black_king_moved && !cr.black_queenside_rook_moved) as u8) << 4;
+        bytes[24] = flags;
+        bytes[25] = self.position.en_passant.unwrap_or(255);
+        bytes[26..28].copy_from_slice(&self.score.to_le_bytes());
+        bytes[28] = (self.white_result() * 2.0) as u8;
+        bytes[29..31].copy_from_slice(&self.ply.to_le_bytes());
+        bytes
+    }
+
+    /// Decodes a record written by [`TrainingRecord::encode`].
+    ///
+    /// # Errors
+    /// Returns `Err(String)` if the record holds more than 32 pieces or invalid codes.
+    pub fn decode(bytes: &[u8; RECORD_SIZE]) -> Result<Self, String> {
+        let occupied = u64::from_le_bytes(bytes[0..8].try_into().expect("8 bytes"));
+        if occupied.count_ones() > 32 {
+            return Err("Record has more than 32 pieces".to_string());
+        }
+        let mut position = Position {
+            bb_sides: [BitBoard(0), BitBoard(0)],
+            bb_pieces: [[BitBoard(0); 6]; 2],
+            castling_rights: CastlingRights::new(),
+            en_passant: None,
+            variant: Variant::Standard,
+            pockets: [[0; 5]; 2],
+            promoted: BitBoard(0),
+            checks: [0; 2],
+            duck: BitBoard(0),
+        };
+
+        let mut bb = occupied;
+        let mut i = 0;
+        while bb != 0 {
+            let square = bb.trailing_zeros();
+            bb &= bb - 1;
+            let code = ((bytes[8 + i / 2] >> (4 * (i % 2))) & 15) as usize;
+            if code >= 12 {
+                return Err(format!("Invalid piece code {} in record", code));
+            }
+            let (side, piece_type) = (code / 6, code % 6);
+            position.bb_sides[side].0 |= 1 << square;
+            position.bb_pieces[side][piece_type].0 |= 1 << square;
+            i += 1;
+        }
+
+        let flags = bytes[24];
+        let cr = &mut position.castling_rights;
+        cr.white_kingside_rook_moved = flags & 2 == 0;
+        cr.white_queenside_rook_moved = flags & 4 == 0;
+        cr.white_king_moved = cr.white_kingside_rook_moved && cr.white_queenside_rook_moved;
+        cr.black_kingside_rook_moved = flags & 8 == 0;
+        cr.black_queenside_rook_moved = flags & 16 == 0;
+        cr.black_king_moved = cr.black_kingside_rook_moved && cr.black_queenside_rook_moved;
+        position.en_passant = (bytes[25] < 64).then_some(bytes[25]);
+
+        let result = match bytes[28] {
+            0 => GameResult::Checkmate(Color::White),
+            1 => GameResult::Draw,
+            2 => GameResult::Checkmate(Color::Black),
+            other => return Err(format!("Invalid result {} in record", other)),
+        };
+        Ok(TrainingRecord {
+            position,
+            color: if flags & 1 == 1 { Color::Black } else { Color::White },
+            score: i16::from_le_bytes([bytes[26], bytes[27]]),
+            result,
+            ply: u16::from_le_bytes([bytes[29], bytes[30]]),
+        })
+    }
+}
+
+/// Reads [`TrainingRecord`]s one by one from a byte stream.
+pub struct RecordReader<R: Read> {
+    reader: R,
+}
+
+impl<R: Read> RecordReader<R> {
+    pub fn new(reader: R) -> Self {
+        RecordReader { reader }
+    }
+}
+
+impl RecordReader<BufReader<File>> {
+    /// Opens a record file for reading.
+    pub fn open(path: &str) -> Result<Self, String> {
+        let file = File::open(path).map_err(|e| format!("Cannot open {}: {}", path, e))?;
+        Ok(RecordReader::new(BufReader::new(file)))
+    }
+}
+
+impl<R: Read> Iterator for RecordReader<R> {
+    type Item = Result<TrainingRecord, String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut bytes = [0u8; RECORD_SIZE];
+        match self.reader.read_exact(&mut bytes) {
+            Ok(()) => Some(TrainingRecord::decode(&bytes)),
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => None,
+            Err(e) => Some(Err(e.to_string())),
+        }
+    }
+}
+
+/// Reads all records of a file.
+pub fn read_records(path: &str) -> Result<Vec<TrainingRecord>, String> {
+    RecordReader::open(path)?.collect()
+}
+
+/// Writes all records to a file, replacing it.
+pub fn write_records(path: &str, records: &[TrainingRecord]) -> Result<(), String> {
+    let file = File::create(path).map_err(|e| format!("Cannot create {}: {}", path, e))?;
+    let mut writer = BufWriter::new(file);
+    for record in records {
+        writer.write_all(&record.encode()).map_err(|e| e.to_string())?;
+    }
+    writer.flush().map_err(|e| e.to_string())
+}
+
+/// Settings for self-play data generation.
+#[derive(Debug, Clone, Copy)]
+pub struct DataGenOptions {
+    /// Number of uniformly random moves played from the start position before searching.
+    pub random_plies: u32,
+    /// Node limit of the search for every move.
+    pub node_limit: u64,
+    /// Games still running after this many plies are adjudicated as draws.
+    pub max_plies: u32,
+}
+
+impl Default for DataGenOptions {
+    fn default() -> Self {
+        DataGenOptions { random_plies: 8, node_limit: 5000, max_plies: 400 }
+    }
+}
+
+/// Plays one self-play game and returns its training records.
+///
+/// The game starts with [`DataGenOptions::random_plies`] random moves (an opening
+/// that ends the game is retried). Every further move is chosen by a search limited
+/// to [`DataGenOptions::node_limit`] nodes. The game ends with checkmate, stalemate,
+/// threefold repetition, the fifty-move rule, insufficient material or after
+/// [`DataGenOptions::max_plies`] plies.
+///
+/// Positions are recorded only when they are quiet: the side to move is not in
+/// check, the search's best move is not a capture or promotion, and the score is
+/// not a mate score.
+///
+/// # Arguments
+/// * `searcher` - The searcher to use; its transposition table is cleared first.
+/// * `rng` - Source of the random opening moves.
+/// * `options` - Game settings.
+pub fn play_game(searcher: &mut Searcher, rng: &mut Rng, options: &DataGenOptions) -> Vec<TrainingRecord> {
+    searcher.tt.clear();
+    searcher.ordering.clear();
+    searcher.node_limit = Some(options.node_limit);
+
+    let (mut position, mut color) = random_opening(rng, options.random_plies);
+    let mut ply = options.random_plies as u16;
+    let mut halfmove_clock = 0;
+    let mut history = vec![zobrist_key(&position, color)];
+    let mut records = Vec::new();
+
+    let result = loop {
+        let moves = legal_moves(color, &position);
+        if moves.is_empty() {
+            break if is_checked(color, &position) { GameResult::Checkmate(color) } else { GameResult::Stalemate };
+        }
+        let key = history[history.len() - 1];
+        let repetitions = history.iter().filter(|&&k| k == key).count();
+        if repetitions >= 3 || halfmove_clock >= 100 || is_insufficient_material(&position) || ply as u32 >= options.max_plies {
+            break GameResult::Draw;
+        }
+
+        let search = searcher.search(&position, color, MAX_PLY as u32 / 2);
+        let m = search.best_move.unwrap_or(moves[0]);
+        let tactical = m.promoted_from_pawn || captured_piece(&m, &position).is_some();
+        if !tactical && !is_checked(color, &position) && !is_mate_score(search.score) {
+            records.push(TrainingRecord {
+                position,
+                color,
+                score: search.score.clamp(i16::MIN as i32, i16::MAX as i32) as i16,
+                result: GameResult::Ongoing,
+                ply,
+            });
+        }
+
+        let irreversible = matches!(m.piece, Piece::Pawn(_)) || m.promoted_from_pawn || captured_piece(&m, &position).is_some();
+        position = play(m, &position);
+        color = color.opponent();
+        ply += 1;
+        if irreversible {
+            halfmove_clock = 0;
+            history.clear();
+        } else {
+            halfmove_clock += 1;
+        }
+        history.push(zobrist_key(&position, color));
+    };
+
+    let result = if result == GameResult::Stalemate { GameResult::Draw } else { result };
+    for record in &mut records {
+        record.result = result;
+    }
+    records
+}
+
+/// Plays `plies` random legal moves from the start position, retrying if the game ends.
+fn random_opening(rng: &mut Rng, plies: u32) -> (Position, Color) {
+    'retry: loop {
+        let (mut position, mut color) = parse_fen(START_FEN).expect("start FEN is valid");
+        for _ in 0..plies {
+            let moves = legal_moves(color, &position);
+            if moves.is_empty() {
+                continue 'retry;
+            }
+            position = play(moves[rng.below(moves.len())], &position);
+            color = color.opponent();
+        }
+        if !legal_moves(color, &position).is_empty() {
+            return (position, color);
+        }
+    }
+}
+
+/// Plays `games` self-play games and writes their records to `writer`.
+///
+/// # Arguments
+/// * `games` - Number of games to play.
+/// * `seed` - Seed of the random openings; the same seed gives the same games.
+/// * `options` - Game settings.
+/// * `writer` - Destination of the encoded records.
+/// * `progress` - Called after every game with the number of games and records so far.
+///
+/// # Returns
+/// The number of records written.
+pub fn generate<W, F>(games: usize, seed: u64, options: &DataGenOptions, writer: &mut W, mut progress: F) -> Result<usize, String>
+where
+    W: Write,
+    F: FnMut(usize, usize),
+{
+    let mut searcher = Searcher::new();
+    let mut rng = Rng::new(seed);
+    let mut written = 0;
+    for game in 1..=games {
+        for record in play_game(&mut searcher, &mut rng, options) {
+            writer.write_all(&record.encode()).map_err(|e| e.to_string())?;
+            written += 1;
+        }
+        progress(game, written);
+    }
+    writer.flush().map_err(|e| e.to_string())?;
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fen::to_fen;
+
+    fn record(fen: &str, score: i16, result: GameResult, ply: u16) -> TrainingRecord {
+        let (position, color) = parse_fen(fen).unwrap();
+        TrainingRecord { position, color, score, result, ply }
+    }
+
+    #[test]
+    fn record_round_trip() {
+        let records = [
+            record(START_FEN, 25, GameResult::Draw, 0),
+            // some castling rights, en passant, Black to move and a negative score
+            record("r3k2r/pppp1ppp/8/8/4Pp2/8/PPPP1PPP/R3K2R b Kq e3 0 1", -317, GameResult::Checkmate(Color::Black), 513),
+            record("8/8/4k3/8/8/3K4/8/8 w - - 0 1", i16::MIN, GameResult::Checkmate(Color::White), u16::MAX),
+        ];
+        for original in records {
+            let bytes = original.encode();
+            assert_eq!(bytes[31], 0);
+            let decoded = TrainingRecord::decode(&bytes).unwrap();
+            assert_eq!(to_fen(&decoded.position, decoded.color), to_fen(&original.position, original.color));
+            assert_eq!(decoded.color, original.color);
+            assert_eq!(decoded.score, original.score);
+            assert_eq!(decoded.result, original.result);
+            assert_eq!(decoded.ply, original.ply);
+            assert_eq!(decoded.encode(), bytes);
+        }
+    }
+
+    #[test]
+    fn record_fields() {
+        let r = record("r3k2r/pppp1ppp/8/8/4Pp2/8/PPPP1PPP/R3K2R b Kq e3 0 1", -317, GameResult::Checkmate(Color::Black), 513);
+        let bytes = r.encode();
+        assert_eq!(bytes[24], 1 | 2 | 16);
+        assert_eq!(bytes[25], 20);
+        assert_eq!(i16::from_le_bytes([bytes[26], bytes[27]]), -317);
+        // Black is checkmated: White won
+        assert_eq!(bytes[28], 2);
+        assert_eq!(u16::from_le_bytes([bytes[29], bytes[30]]), 513);
+        assert_eq!(r.white_result(), 1.0);
+        // draws of any kind are stored as draws
+        let stalemate = record(START_FEN, 0, GameResult::Stalemate, 0);
+        assert_eq!(TrainingRecord::decode(&stalemate.encode()).unwrap().result, GameResult::Draw);
+
+        let mut bad = bytes;
+        bad[28] = 3;
+        assert!(TrainingRecord::decode(&bad).is_err());
+        let mut bad = bytes;
+        bad[8] = 0xff;
+        assert!(TrainingRecord::decode(&bad).is_err());
+    }
+}
@@ -0,0 +1,137 @@
+use crate::bitboard::BitBoard;
+use crate::helper::{index_to_square, square_to_index};
+use crate::moves::Move;
+use crate::piece::Color;
+use crate::position::{Pieces, Position};
+
+// see: https://duckchess.com/
+
+/// Returns the square of the duck, if it is on the board.
+pub fn duck_square(position: &Position) -> Option<u8> {
+    (position.duck.0 != 0).then(|| position.duck.0.trailing_zeros() as u8)
+}
+
+/// Returns `true` if `color`'s king has been captured.
+pub fn is_king_captured(color: Color, position: &Position) -> bool {
+    position.bb_pieces[color.index()][Pieces::KING].0 == 0
+}
+
+/// Returns the squares the duck may be placed on after `after` was reached by
+/// the piece part of a move: every empty square except the one it stands on,
+/// since the duck has to move.
+pub fn placement_squares(after: &Position) -> u64 {
+    !(after.bb_sides[0].0 | after.bb_sides[1].0 | after.duck.0)
+}
+
+/// Completes the piece part `m` of a move with every possible duck placement.
+///
+/// # Arguments
+/// * `m` - A pseudo-legal move without a duck square.
+/// * `after` - The position after `m` was applied (with the duck still on its old square).
+///
+/// # Returns
+/// One move per empty square, with `duck` set to it.
+pub fn with_placements(m: Move, after: &Position) -> Vec<Move> {
+    let mut moves = Vec::new();
+    let mut bb = placement_squares(after);
+    while bb != 0 {
+        let square = bb.trailing_zeros() as u8;
+        bb &= bb - 1;
+        moves.push(Move { duck: Some(square), ..m });
+    }
+    moves
+}
+
+/// Returns `true` if the duck square of `m` is a valid placement after its piece part.
+///
+/// # Arguments
+/// * `m` - A duck chess move.
+/// * `after` - The position after the piece part of `m` was applied.
+pub fn is_valid_placement(m: &Move, after: &Position) -> bool {
+    m.duck.is_some_and(|square| placement_squares(after) & 1u64 << square != 0)
+}
+
+/// Places the duck on `square`, taking it off its old square.
+pub(crate) fn place_duck(square: u8, position: &mut Position) {
+    position.duck = BitBoard(1u64 << square);
+}
+
+/// Splits the duck placement off a move in UCI or SAN notation, e.g. `e2e4,d5`.
+///
+/// # Returns
+/// The piece part and the duck square (`None` if there is no `,`), or `None`
+/// if the duck square is malformed.
+pub fn split_placement(text: &str) -> Option<(&str, Option<u8>)> {
+    match text.split_once(',') {
+        Some((piece_part, square)) => Some((piece_part, Some(square_to_index(square)?))),
+        None => Some((text, None)),
+    }
+}
+
+/// Formats the duck placement suffix of `m`, e.g. `,d5`, or an empty string if it has none.
+pub fn placement_suffix(m: &Move) -> String {
+    m.duck.map_or_else(String::new, |square| format!(",{}", index_to_square(square).to_ascii_lowercase()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fen::{parse_fen, to_fen};
+    use crate::game::{Game, GameResult};
+    use crate::make_move::is_checked;
+    use crate::moves::valid_moves;
+    use crate::piece::Piece;
+    use crate::uci::move_from_uci;
+    use crate::variant::Variant;
+    use crate::variant::testing::{play, variant_game};
+
+    #[test]
+    fn duck_blocks_sliders_and_pawns() {
+        let (position, _) = parse_fen("4k3/8/8/*7/8/8/8/R3K3 w - - 0 1").unwrap();
+        let mut targets: Vec<u8> = valid_moves(0, Piece::Rook(Color::White), &position)
+            .iter()
+            .filter(|m| m.to % 8 == 0)
+            .map(|m| m.to)
+            .collect();
+        targets.sort();
+        assert_eq!(targets, [8, 16, 24]);
+
+        let (position, _) = parse_fen("4k3/8/8/8/8/4*3/4P3/4K3 w - - 0 1").unwrap();
+        assert!(valid_moves(12, Piece::Pawn(Color::White), &position).is_empty());
+    }
+
+    #[test]
+    fn fen_round_trip() {
+        let fen = "4k3/8/8/8/3*4/8/8/4K3 w - - 0 1";
+        let (position, color) = parse_fen(fen).unwrap();
+        assert_eq!(position.variant, Variant::Duck);
+        assert_eq!(duck_square(&position), Some(27));
+        assert_eq!(to_fen(&position, color), fen);
+
+        let mut game = Game::new(position);
+        play("e1e2,h8", color, &mut game);
+        assert_eq!(to_fen(&game.position, Color::Black), "4k2*/8/8/8/8/8/4K3/8 b - - 0 1");
+    }
+
+    #[test]
+    fn duck_must_move() {
+        let (position, color) = parse_fen("4k3/8/8/8/3*4/8/8/4K3 w - - 0 1").unwrap();
+        assert!(move_from_uci("e1e2,d4", color, &position).is_none());
+        assert!(move_from_uci("e1e2", color, &position).is_none());
+        assert!(move_from_uci("e1e2,d5", color, &position).is_some());
+    }
+
+    #[test]
+    fn king_may_be_left_in_check_and_captured() {
+        let (mut game, color) = variant_game("4k3/8/8/8/8/8/7P/r2*K3 w - - 0 1", Variant::Duck);
+        // the duck on d1 was shielding the king
+        play("h2h3,d4", color, &mut game);
+        assert!(!is_checked(Color::White, &game.position));
+        assert_eq!(game.result, GameResult::Ongoing);
+
+        play("a1e1,d5", Color::Black, &mut game);
+        assert!(is_king_captured(Color::White, &game.position));
+        assert_eq!(game.result, GameResult::KingCaptured(Color::White));
+        assert_eq!(game.result.winner(), Some(Color::Black));
+    }
+}
@@ -0,0 +1,68 @@
+use crate::move_ordering::MAX_PLY;
+use crate::moves::Move;
+use crate::piece::Color;
+use crate::position::Position;
+use crate::search::{PvLine, SearchResult, Searcher};
+use crate::time_manager::{TimeControl, TimeManager};
+
+/// Limits of one search, as given by the UCI `go` command.
+///
+/// Without any limit the search runs until it is stopped from outside.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SearchLimits {
+    /// Maximum depth in plies.
+    pub depth: Option<u32>,
+    /// Maximum number of nodes (alpha-beta) or playouts (MCTS).
+    pub nodes: Option<u64>,
+    /// Clock state; used if it holds time for the side to move.
+    pub time: TimeControl,
+}
+
+/// Common interface of the searchers, so front ends can drive any of them.
+pub trait Engine: Send {
+    /// Searches `position` within `limits` and returns the move to play.
+    fn go(&mut self, position: &Position, color: Color, limits: &SearchLimits) -> SearchResult;
+
+    /// Ranked lines of the last search, best first.
+    fn lines(&self) -> &[PvLine];
+
+    /// The opponent's expected reply to the last search's best move.
+    fn ponder_move(&self) -> Option<Move> {
+        self.lines().first().and_then(|line| line.pv.get(1).copied())
+    }
+
+    /// Forgets everything learned about the previous game.
+    fn new_game(&mut self);
+}
+
+impl Engine for Searcher {
+    /// Searches to `limits.depth` if given, otherwise with the clock if `limits.time`
+    /// has time for `color`, otherwise until stopped. `limits.nodes` caps the node count.
+    fn go(&mut self, position: &Position, color: Color, limits: &SearchLimits) -> SearchResult {
+        let node_limit = self.node_limit;
+        if limits.nodes.is_some() {
+            self.node_limit = limits.nodes;
+        }
+        let timed = limits.depth.is_none() && TimeManager::new(&limits.time, color, self.move_overhead_ms).is_some();
+        let result = if timed {
+            self.search_timed(position, color, &limits.time)
+        } else {
+            self.search(position, color, limits.depth.unwrap_or(MAX_PLY as u32 / 2))
+        };
+        self.node_limit = node_limit;
+        result
+    }
+
+    fn lines(&self) -> &[PvLine] {
+        Searcher::lines(self)
+    }
+
+    fn ponder_move(&self) -> Option<Move> {
+        Searcher::ponder_move(self)
+    }
+
+    fn new_game(&mut self) {
+        self.tt.clear();
+        self.ordering.clear();
+    }
+}
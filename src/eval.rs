@@ -0,0 +1,258 @@
+use crate::antichess;
+use crate::king_of_the_hill;
+use crate::piece::{Color, Piece};
+use crate::position::{Pieces, Position, Sides};
+use crate::racing_kings;
+use crate::three_check;
+use crate::variant::Variant;
+
+// see: https://www.chessprogramming.org/Simplified_Evaluation_Function
+
+/// Material value of each piece type in centipawns.
+///
+/// Indexed like [`Pieces`](crate::position::Pieces), i.e. pawn -> king.
+/// The king gets a large value so that exchange evaluation never trades it.
+pub const PIECE_VALUES: [i32; 6] = [100, 320, 330, 500, 900, 20_000];
+
+/// Piece-square tables in centipawns, indexed like [`PIECE_VALUES`].
+///
+/// Each table is written from White's point of view the way a board is drawn:
+/// the first row is rank 8, the last row is rank 1.
+/// Use [`pst_index`] to look up a square.
+#[rustfmt::skip]
+pub const PIECE_SQUARE_TABLES: [[i32; 64]; 6] = [
+    // pawn
+    [
+          0,   0,   0,   0,   0,   0,   0,   0,
+         50,  50,  50,  50,  50,  50,  50,  50,
+         10,  10,  20,  30,  30,  20,  10,  10,
+          5,   5,  10,  25,  25,  10,   5,   5,
+          0,   0,   0,  20,  20,   0,   0,   0,
+          5,  -5, -10,   0,   0, -10,  -5,   5,
+          5,  10,  10, -20, -20,  10,  10,   5,
+          0,   0,   0,   0,   0,   0,   0,   0,
+    ],
+    // knight
+    [
+        -50, -40, -30, -30, -30, -30, -40, -50,
+        -40, -20,   0,   0,   0,   0, -20, -40,
+        -30,   0,  10,  15,  15,  10,   0, -30,
+        -30,   5,  15,  20,  20,  15,   5, -30,
+        -30,   0,  15,  20,  20,  15,   0, -30,
+        -30,   5,  10,  15,  15,  10,   5, -30,
+        -40, -20,   0,   5,   5,   0, -20, -40,
+        -50, -40, -30, -30, -30, -30, -40, -50,
+    ],
+    // bishop
+    [
+        -20, -10, -10, -10, -10, -10, -10, -20,
+        -10,   0,   0,   0,   0,   0,   0, -10,
+        -10,   0,   5,  10,  10,   5,   0, -10,
+        -10,   5,   5,  10,  10,   5,   5, -10,
+        -10,   0,  10,  10,  10,  10,   0, -10,
+        -10,  10,  10,  10,  10,  10,  10, -10,
+        -10,   5,   0,   0,   0,   0,   5, -10,
+        -20, -10, -10, -10, -10, -10, -10, -20,
+    ],
+    // rook
+    [
+          0,   0,   0,   0,   0,   0,   0,   0,
+          5,  10,  10,  10,  10,  10,  10,   5,
+         -5,   0,   0,   0,   0,   0,   0,  -5,
+         -5,   0,   0,   0,   0,   0,   0,  -5,
+         -5,   0,   0,   0,   0,   0,   0,  -5,
+         -5,   0,   0,   0,   0,   0,   0,  -5,
+         -5,   0,   0,   0,   0,   0,   0,  -5,
+          0,   0,   0,   5,   5,   0,   0,   0,
+    ],
+    // queen
+    [
+        -20, -10, -10,  -5,  -5, -10, -10, -20,
+        -10,   0,   0,   0,   0,   0,   0, -10,
+        -10,   0,   5,   5,   5,   5,   0, -10,
+         -5,   0,   5,   5,   5,   5,   0,  -5,
+          0,   0,   5,   5,   5,   5,   0,  -5,
+        -10,   5,   5,   5,   5,   5,   0, -10,
+        -10,   0,   5,   0,   0,   0,   0, -10,
+        -20, -10, -10,  -5,  -5, -10, -10, -20,
+    ],
+    // king (middle game)
+    [
+        -30, -40, -40, -50, -50, -40, -40, -30,
+        -30, -40, -40, -50, -50, -40, -40, -30,
+        -30, -40, -40, -50, -50, -40, -40, -30,
+        -30, -40, -40, -50, -50, -40, -40, -30,
+        -20, -30, -30, -40, -40, -30, -30, -20,
+        -10, -20, -20, -20, -20, -20, -20, -10,
+         20,  20,   0,   0,   0,   0,  20,  20,
+         20,  30,  10,   0,   0,  10,  30,  20,
+    ],
+];
+
+/// Returns the material value of a piece in centipawns.
+pub fn piece_value(piece: Piece) -> i32 {
+    PIECE_VALUES[piece.index()]
+}
+
+/// Maps a board square (0 = A1, 63 = H8) to its index in a [`PIECE_SQUARE_TABLES`] table.
+///
+/// Black's squares are mirrored vertically so both sides share the same tables.
+pub fn pst_index(square: u8, color: Color) -> usize {
+    match color {
+        Color::White => (square ^ 56) as usize,
+        Color::Black => square as usize,
+    }
+}
+
+/// Names of the piece types in the order of [`PIECE_VALUES`], used in parameter files.
+const PIECE_NAMES: [&str; 6] = ["pawn", "knight", "bishop", "rook", "queen", "king"];
+
+/// A full set of evaluation parameters: material values and piece-square tables.
+///
+/// The built-in parameters are [`DEFAULT_PARAMS`]; tuned ones (see [`crate::tuning`])
+/// can be written with [`EvalParams::to_text`] and loaded with [`EvalParams::load`].
+///
+/// The text format lists integers separated by whitespace; lines starting with `#`
+/// are comments. It starts with the 6 piece values (pawn -> king), followed by the 6
+/// piece-square tables, each as 64 numbers laid out like [`PIECE_SQUARE_TABLES`]
+/// (rank 8 first).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EvalParams {
+    pub piece_values: [i32; 6],
+    pub piece_square_tables: [[i32; 64]; 6],
+}
+
+/// The built-in evaluation parameters.
+pub static DEFAULT_PARAMS: EvalParams = EvalParams {
+    piece_values: PIECE_VALUES,
+    piece_square_tables: PIECE_SQUARE_TABLES,
+};
+
+impl Default for EvalParams {
+    fn default() -> Self {
+        DEFAULT_PARAMS.clone()
+    }
+}
+
+impl EvalParams {
+    /// Number of parameters: 6 piece values plus 6 tables of 64 squares.
+    pub const COUNT: usize = 6 + 6 * 64;
+
+    /// Returns all parameters as one flat vector: piece values first, then the tables.
+    pub fn to_vector(&self) -> Vec<i32> {
+        let mut values = self.piece_values.to_vec();
+        for table in &self.piece_square_tables {
+            values.extend_from_slice(table);
+        }
+        values
+    }
+
+    /// Builds parameters from a flat vector laid out like [`EvalParams::to_vector`].
+    ///
+    /// # Errors
+    /// Returns `Err(String)` if `values` does not hold exactly [`EvalParams::COUNT`] numbers.
+    pub fn from_vector(values: &[i32]) -> Result<Self, String> {
+        if values.len() != Self::COUNT {
+            return Err(format!("Expected {} evaluation parameters, got {}", Self::COUNT, values.len()));
+        }
+        let mut params = EvalParams { piece_values: [0; 6], piece_square_tables: [[0; 64]; 6] };
+        params.piece_values.copy_from_slice(&values[..6]);
+        for (i, table) in params.piece_square_tables.iter_mut().enumerate() {
+            table.copy_from_slice(&values[6 + i * 64..6 + (i + 1) * 64]);
+        }
+        Ok(params)
+    }
+
+    /// Parses parameters in the text format described on [`EvalParams`].
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let values = text
+            .lines()
+            .filter(|line| !line.trim_start().starts_with('#'))
+            .flat_map(str::split_whitespace)
+            .map(|token| token.parse::<i32>().map_err(|_| format!("Invalid number '{}' in evaluation parameters", token)))
+            .collect::<Result<Vec<i32>, String>>()?;
+        Self::from_vector(&values)
+    }
+
+    /// Loads parameters from a text file, see [`EvalParams::parse`].
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| format!("Cannot read {}: {}", path, e))?;
+        Self::parse(&text)
+    }
+
+    /// Writes the parameters in the text format described on [`EvalParams`].
+    pub fn to_text(&self) -> String {
+        let mut text = String::from("# piece values: pawn knight bishop rook queen king\n");
+        let values: Vec<String> = self.piece_values.iter().map(|v| v.to_string()).collect();
+        text.push_str(&values.join(" "));
+        text.push('\n');
+        for (name, table) in PIECE_NAMES.iter().zip(&self.piece_square_tables) {
+            text.push_str(&format!("# {} (rank 8 first)\n", name));
+            for row in table.chunks(8) {
+                let row: Vec<String> = row.iter().map(|v| format!("{:4}", v)).collect();
+                text.push_str(&row.join(" "));
+                text.push('\n');
+            }
+        }
+        text
+    }
+}
+
+/// Statically evaluates a position from the point of view of `color`.
+///
+/// The score is the sum of material and piece-square bonuses of `color`'s pieces,
+/// minus the same for the opponent, plus the [`variant_bonus`]. Positive scores
+/// favour `color`. Antichess positions are scored by [`antichess::evaluate`] instead.
+///
+/// # Arguments
+/// * `color` - The side the score is relative to (usually the side to move).
+/// * `position` - The board state.
+///
+/// # Returns
+/// The evaluation in centipawns.
+pub fn evaluate(color: Color, position: &Position) -> i32 {
+    evaluate_with(&DEFAULT_PARAMS, color, position)
+}
+
+/// Like [`evaluate`], but with the given parameters instead of the built-in ones.
+pub fn evaluate_with(params: &EvalParams, color: Color, position: &Position) -> i32 {
+    if position.variant == Variant::Antichess {
+        return antichess::evaluate(color, position);
+    }
+    let variant_score = variant_bonus(color, position);
+    let mut score = [0i32; 2];
+
+    for side in [Sides::WHITE, Sides::BLACK] {
+        let side_color = if side == Sides::WHITE { Color::White } else { Color::Black };
+        for piece_type in 0..6 {
+            let mut bb = position.bb_pieces[side][piece_type].0;
+            while bb != 0 {
+                let square = bb.trailing_zeros() as u8;
+                bb &= bb - 1;
+                score[side] += params.piece_square_tables[piece_type][pst_index(square, side_color)];
+                // the kings' values cancel out, and must not count where a side has none (Horde)
+                if piece_type != Pieces::KING {
+                    score[side] += params.piece_values[piece_type];
+                }
+            }
+        }
+        // Crazyhouse pockets (empty in other variants)
+        for piece_type in 0..5 {
+            score[side] += position.pockets[side][piece_type] as i32 * params.piece_values[piece_type];
+        }
+    }
+
+    score[color.index()] - score[color.opponent().index()] + variant_score
+}
+
+/// Evaluation terms of the variant rules, from the point of view of `color`:
+/// checks given in three-check, the kings' distance to the hill in King of the Hill
+/// and their advance in Racing Kings.
+pub fn variant_bonus(color: Color, position: &Position) -> i32 {
+    match position.variant {
+        Variant::ThreeCheck => three_check::evaluate(color, position),
+        Variant::KingOfTheHill => king_of_the_hill::evaluate(color, position),
+        Variant::RacingKings => racing_kings::evaluate(color, position),
+        _ => 0,
+    }
+}
@@ -0,0 +1,375 @@
+use crate::bitboard::BitBoard;
+use crate::helper::{index_to_square, square_to_index};
+use crate::piece::{CastlingRights, Color, Piece};
+use crate::position::{Pieces, Position, get_piece_at};
+use crate::variant::Variant;
+
+// see: https://www.chessprogramming.org/Forsyth-Edwards_Notation
+
+/// FEN of the standard starting position.
+pub const START_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+/// Returns the FEN letter of a piece (uppercase for White, lowercase for Black).
+pub fn piece_to_char(piece: Piece) -> char {
+    let c = ['p', 'n', 'b', 'r', 'q', 'k'][piece.index()];
+    match piece.color() {
+        Color::White => c.to_ascii_uppercase(),
+        Color::Black => c,
+    }
+}
+
+/// Parses a FEN piece letter (uppercase for White, lowercase for Black).
+pub fn char_to_piece(c: char) -> Option<Piece> {
+    let color = if c.is_ascii_uppercase() { Color::White } else { Color::Black };
+    let piece_type = "pnbrqk".find(c.to_ascii_lowercase())?;
+    Some(Piece::from_index(piece_type, color))
+}
+
+/// Parses a FEN string into a position and the side to move.
+///
+/// The halfmove clock and fullmove number are optional and ignored, as neither
+/// [`Position`] nor the search track them. The castling field may use `KQkq`,
+/// Shredder-FEN rook files (`HAha`) or a mix of both (X-FEN); castling rights
+/// without a rook to castle with are dropped. A Crazyhouse pocket after the
+/// board (`.../RNBQKBNR[Qp]` or `.../RNBQKBNR/Qp`) makes the position a
+/// Crazyhouse position, and `~` after a piece marks it as promoted. A `+N+M`
+/// field with the checks given by White and Black makes it a three-check position,
+/// and a `*` on the board (the duck) a duck chess position.
+///
+/// # Arguments
+/// * `fen` - e.g. `"rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"`.
+///
+/// # Errors
+/// Returns `Err(String)` describing the first malformed field.
+///
+/// # Returns
+/// `Ok((position, side_to_move))`.
+pub fn parse_fen(fen: &str) -> Result<(Position, Color), String> {
+    let mut fields: Vec<&str> = fen.split_whitespace().collect();
+    // three-check counters: a `+N+M` field, usually after the fullmove number
+    let checks = match fields.iter().position(|field| field.starts_with('+')) {
+        Some(i) => Some(parse_checks(fields.remove(i))?),
+        None => None,
+    };
+    if fields.len() < 4 {
+        return Err(format!("FEN needs at least 4 fields, got {}", fields.len()));
+    }
+
+    let mut position = Position {
+        bb_sides: [BitBoard(0), BitBoard(0)],
+        bb_pieces: [[BitBoard(0); 6]; 2],
+        castling_rights: CastlingRights::new(),
+        en_passant: None,
+        variant: Variant::Standard,
+        pockets: [[0; 5]; 2],
+        promoted: BitBoard(0),
+        checks: checks.unwrap_or([0; 2]),
+        duck: BitBoard(0),
+    };
+    if checks.is_some() {
+        position.variant = Variant::ThreeCheck;
+    }
+
+    // Crazyhouse pockets: `board[QNp]`, or a ninth "rank" as in `board/QNp`
+    let (board, pocket) = match fields[0].split_once('[') {
+        Some((board, rest)) => {
+            let pocket = rest.strip_suffix(']').ok_or_else(|| format!("Unterminated pocket '[{}' in FEN", rest))?;
+            (board, Some(pocket))
+        }
+        None if fields[0].matches('/').count() == 8 => {
+            let (board, pocket) = fields[0].rsplit_once('/').expect("board has a '/'");
+            (board, Some(pocket))
+        }
+        None => (fields[0], None),
+    };
+    if let Some(pocket) = pocket {
+        position.variant = Variant::Crazyhouse;
+        for c in pocket.chars() {
+            let piece = char_to_piece(c)
+                .filter(|piece| !matches!(piece, Piece::King(_)))
+                .ok_or_else(|| format!("Invalid pocket piece '{}' in FEN", c))?;
+            position.pockets[piece.color().index()][piece.index()] += 1;
+        }
+    }
+
+    // piece placement, rank 8 first
+    let ranks: Vec<&str> = board.split('/').collect();
+    if ranks.len() != 8 {
+        return Err(format!("FEN board needs 8 ranks, got {}", ranks.len()));
+    }
+    for (i, rank_str) in ranks.iter().enumerate() {
+        let rank = 7 - i as u8;
+        let mut file = 0u8;
+        let mut previous = None;
+        for c in rank_str.chars() {
+            // Crazyhouse: `~` marks the piece before it as promoted
+            if c == '~' {
+                let square = previous.take().ok_or_else(|| format!("Misplaced '~' in FEN rank {}", rank + 1))?;
+                position.promoted.0 |= 1u64 << square;
+                continue;
+            }
+            previous = None;
+            if let Some(skip) = c.to_digit(10) {
+                file += skip as u8;
+                continue;
+            }
+            // duck chess: `*` is the duck
+            if c == '*' && file < 8 {
+                position.duck.0 = 1u64 << (rank * 8 + file);
+                position.variant = Variant::Duck;
+                file += 1;
+                continue;
+            }
+            let piece = char_to_piece(c).ok_or_else(|| format!("Invalid piece '{}' in FEN", c))?;
+            if file >= 8 {
+                return Err(format!("Too many squares in FEN rank {}", rank + 1));
+            }
+            let mask = 1u64 << (rank * 8 + file);
+            let side = piece.color().index();
+            position.bb_pieces[side][piece.index()].0 |= mask;
+            position.bb_sides[side].0 |= mask;
+            previous = Some(rank * 8 + file);
+            file += 1;
+        }
+        if file != 8 {
+            return Err(format!("FEN rank {} does not have 8 squares", rank + 1));
+        }
+    }
+
+    let color = match fields[1] {
+        "w" => Color::White,
+        "b" => Color::Black,
+        other => return Err(format!("Invalid side to move '{}' in FEN", other)),
+    };
+
+    parse_castling(fields[2], &mut position)?;
+
+    position.en_passant = match fields[3] {
+        "-" => None,
+        square => Some(
+            square_to_index(square).ok_or_else(|| format!("Invalid en passant square '{}' in FEN", square))?,
+        ),
+    };
+
+    Ok((position, color))
+}
+
+/// Writes a position and the side to move as a FEN string.
+///
+/// The halfmove clock and fullmove number are not tracked and written as `0 1`.
+/// Chess960 castling rights are written as X-FEN: `KQkq` for the outermost rook,
+/// the rook's file letter otherwise. Crazyhouse positions get their pockets in
+/// brackets after the board, and promoted pieces a `~`. Three-check positions
+/// end with the checks given by White and Black, as in `0 1 +1+0`, and the duck
+/// is written as `*`.
+pub fn to_fen(position: &Position, color: Color) -> String {
+    let mut board = String::new();
+    for rank in (0..8).rev() {
+        let mut empty = 0;
+        for file in 0..8 {
+            match get_piece_at(position, rank * 8 + file) {
+                Some(piece) => {
+                    if empty > 0 {
+                        board.push_str(&empty.to_string());
+                        empty = 0;
+                    }
+                    board.push(piece_to_char(piece));
+                    if position.promoted.0 & 1u64 << (rank * 8 + file) != 0 {
+                        board.push('~');
+                    }
+                }
+                None if position.duck.0 & 1u64 << (rank * 8 + file) != 0 => {
+                    if empty > 0 {
+                        board.push_str(&empty.to_string());
+                        empty = 0;
+                    }
+                    board.push('*');
+                }
+                None => empty += 1,
+            }
+        }
+        if empty > 0 {
+            board.push_str(&empty.to_string());
+        }
+        if rank > 0 {
+            board.push('/');
+        }
+    }
+    if position.variant == Variant::Crazyhouse {
+        board.push('[');
+        for color in [Color::White, Color::Black] {
+            for piece_type in (Pieces::PAWN..Pieces::KING).rev() {
+                let letter = piece_to_char(Piece::from_index(piece_type, color));
+                board.extend((0..position.pockets[color.index()][piece_type]).map(|_| letter));
+            }
+        }
+        board.push(']');
+    }
+
+    let side = if color == Color::White { "w" } else { "b" };
+
+    let castling = castling_field(position);
+
+    let en_passant = position
+        .en_passant
+        .map_or("-".to_string(), |sq| index_to_square(sq).to_ascii_lowercase());
+
+    let mut fen = format!("{} {} {} {} 0 1", board, side, castling, en_passant);
+    if position.variant == Variant::ThreeCheck {
+        fen.push_str(&format!(" +{}+{}", position.checks[0], position.checks[1]));
+    }
+    fen
+}
+
+/// Parses a three-check `+N+M` field: the checks given by White and Black.
+fn parse_checks(field: &str) -> Result<[u8; 2], String> {
+    let invalid = || format!("Invalid check counter '{}' in FEN", field);
+    let (white, black) = field.strip_prefix('+').and_then(|rest| rest.split_once('+')).ok_or_else(invalid)?;
+    let count = |text: &str| text.parse::<u8>().ok().filter(|&n| n <= 3).ok_or_else(invalid);
+    Ok([count(white)?, count(black)?])
+}
+
+/// Returns the king's file and a mask of the files holding rooks on `color`'s back rank.
+fn back_rank(position: &Position, color: Color) -> (Option<u8>, u8) {
+    let shift = match color {
+        Color::White => 0,
+        Color::Black => 56,
+    };
+    let pieces = &position.bb_pieces[color.index()];
+    let kings = (pieces[Pieces::KING].0 >> shift) as u8;
+    let king_file = (kings != 0).then(|| kings.trailing_zeros() as u8);
+    (king_file, (pieces[Pieces::ROOK].0 >> shift) as u8)
+}
+
+/// Returns the file of the outermost rook on the given side of the king, if any.
+fn outermost_rook(king_file: u8, rooks: u8, kingside: bool) -> Option<u8> {
+    if kingside {
+        let right = rooks & !((2u16 << king_file) - 1) as u8;
+        (right != 0).then(|| 7 - right.leading_zeros() as u8)
+    } else {
+        let left = rooks & ((1u16 << king_file) - 1) as u8;
+        (left != 0).then(|| left.trailing_zeros() as u8)
+    }
+}
+
+/// Parses the castling field of a FEN into `position.castling_rights`.
+///
+/// # Errors
+/// Returns `Err(String)` if the field holds anything but `-`, `KQkq` and rook files.
+fn parse_castling(castling: &str, position: &mut Position) -> Result<(), String> {
+    if castling != "-" && castling.chars().any(|c| !"KQkqABCDEFGHabcdefgh".contains(c)) {
+        return Err(format!("Invalid castling field '{}' in FEN", castling));
+    }
+    let mut rights = CastlingRights::new();
+    for color in [Color::White, Color::Black] {
+        rights.revoke(color, true);
+        rights.revoke(color, false);
+    }
+    let mut chess960 = false;
+    for c in castling.chars().filter(|&c| c != '-') {
+        let color = if c.is_ascii_uppercase() { Color::White } else { Color::Black };
+        let (Some(king_file), rooks) = back_rank(position, color) else { continue };
+        let (kingside, rook_file) = match c.to_ascii_lowercase() {
+            'k' => (true, outermost_rook(king_file, rooks, true)),
+            'q' => (false, outermost_rook(king_file, rooks, false)),
+            file => {
+                let file = file as u8 - b'a';
+                chess960 = true;
+                (file > king_file, (rooks & 1 << file != 0).then_some(file))
+            }
+        };
+        let Some(rook_file) = rook_file else { continue };
+        let side = color.index();
+        if kingside {
+            rights.kingside_rook_files[side] = rook_file;
+        } else {
+            rights.queenside_rook_files[side] = rook_file;
+        }
+        chess960 |= king_file != 4 || rook_file != if kingside { 7 } else { 0 };
+        match (color, kingside) {
+            (Color::White, true) => rights.white_kingside_rook_moved = false,
+            (Color::White, false) => rights.white_queenside_rook_moved = false,
+            (Color::Black, true) => rights.black_kingside_rook_moved = false,
+            (Color::Black, false) => rights.black_queenside_rook_moved = false,
+        }
+    }
+    rights.white_king_moved = rights.white_kingside_rook_moved && rights.white_queenside_rook_moved;
+    rights.black_king_moved = rights.black_kingside_rook_moved && rights.black_queenside_rook_moved;
+    rights.chess960 = chess960;
+    position.castling_rights = rights;
+    Ok(())
+}
+
+/// Writes the castling field of a FEN (`-` if neither side may castle).
+fn castling_field(position: &Position) -> String {
+    let rights = &position.castling_rights;
+    let mut castling = String::new();
+    for color in [Color::White, Color::Black] {
+        let (king_file, rooks) = back_rank(position, color);
+        for kingside in [true, false] {
+            if !rights.has_right(color, kingside) {
+                continue;
+            }
+            let rook_file = rights.rook_square(color, kingside) % 8;
+            let outermost = king_file.and_then(|king_file| outermost_rook(king_file, rooks, kingside));
+            let letter = if !rights.chess960 || outermost == Some(rook_file) {
+                if kingside { 'k' } else { 'q' }
+            } else {
+                (b'a' + rook_file) as char
+            };
+            castling.push(if color == Color::White { letter.to_ascii_uppercase() } else { letter });
+        }
+    }
+    if castling.is_empty() {
+        castling.push('-');
+    }
+    castling
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(fen: &str) -> String {
+        let (position, color) = parse_fen(fen).unwrap();
+        to_fen(&position, color)
+    }
+
+    #[test]
+    fn start_position_round_trip() {
+        assert_eq!(round_trip(START_FEN), START_FEN);
+        let (position, _) = parse_fen(START_FEN).unwrap();
+        assert!(!position.castling_rights.chess960);
+    }
+
+    #[test]
+    fn x_fen_round_trip() {
+        // the outermost rooks are written as KQkq
+        let fen = "rk5r/pppppppp/8/8/8/8/PPPPPPPP/RK5R w KQkq - 0 1";
+        assert_eq!(round_trip(fen), fen);
+        let (position, _) = parse_fen(fen).unwrap();
+        assert!(position.castling_rights.chess960);
+        assert_eq!(position.castling_rights.rook_square(Color::White, true), 7);
+        assert_eq!(position.castling_rights.rook_square(Color::Black, false), 56);
+
+        // an inner rook needs its file letter
+        let fen = "1rk2r1r/pppppppp/8/8/8/8/PPPPPPPP/1RK2R1R w Ff - 0 1";
+        assert_eq!(round_trip(fen), fen);
+        let (position, _) = parse_fen(fen).unwrap();
+        assert_eq!(position.castling_rights.rook_square(Color::White, true), 5);
+        assert!(!position.castling_rights.has_right(Color::White, false));
+    }
+
+    #[test]
+    fn shredder_fen_is_written_as_x_fen() {
+        let shredder = "2nnrbkr/p1qppppp/8/1ppb4/6PP/3PP3/PPP2P2/BQNNRBKR w HEhe - 0 1";
+        let x_fen = "2nnrbkr/p1qppppp/8/1ppb4/6PP/3PP3/PPP2P2/BQNNRBKR w KQkq - 0 1";
+        assert_eq!(round_trip(shredder), x_fen);
+        assert_eq!(parse_fen(shredder).unwrap(), parse_fen(x_fen).unwrap());
+    }
+
+    #[test]
+    fn invalid_castling_field() {
+        assert!(parse_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQx - 0 1").is_err());
+    }
+}
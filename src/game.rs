@@ -1,5 +1,6 @@
 use crate::piece::{Piece, Color};
 use crate::position::{Position, get_piece_at};
+use crate::variant::Variant;
 
 /// Represents the current state of a game.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -8,6 +9,43 @@ pub enum GameResult {
     /// One player has been checkmated. Stores the color of the losing side.
     Checkmate(Color),  
     Stalemate,
+    /// Any other draw: repetition, fifty-move rule, insufficient material or adjudication.
+    Draw,
+    /// One player resigned (or was adjudicated lost). Stores the color of the losing side.
+    Resigned(Color),
+    /// Atomic chess: a king was blown up by a capture next to it. Stores the color of the losing side.
+    KingExploded(Color),
+    /// Antichess and duck chess: the opponent has no legal moves left (in antichess
+    /// after losing all its pieces), which wins. Stores the color of the losing side.
+    OutOfMoves(Color),
+    /// Three-check: a side received its third check. Stores the color of the losing side.
+    ThirdCheck(Color),
+    /// King of the Hill: the opponent's king reached the center. Stores the color of the losing side.
+    KingOfTheHill(Color),
+    /// Horde: all of a side's pieces were captured. Stores the color of the losing side.
+    AllCaptured(Color),
+    /// Racing Kings: the opponent's king won the race to the eighth rank. Stores the color of the losing side.
+    RaceLost(Color),
+    /// Duck chess: a king was captured. Stores the color of the losing side.
+    KingCaptured(Color),
+}
+
+impl GameResult {
+    /// Returns the winner, or `None` for draws and ongoing games.
+    pub fn winner(&self) -> Option<Color> {
+        match self {
+            GameResult::Checkmate(loser)
+            | GameResult::Resigned(loser)
+            | GameResult::KingExploded(loser)
+            | GameResult::ThirdCheck(loser)
+            | GameResult::KingOfTheHill(loser)
+            | GameResult::AllCaptured(loser)
+            | GameResult::RaceLost(loser)
+            | GameResult::KingCaptured(loser)
+            | GameResult::OutOfMoves(loser) => Some(loser.opponent()),
+            _ => None,
+        }
+    }
 }
 
 /// Stores the game state, including the board position, turn counter,
@@ -27,6 +65,17 @@ impl Game {
     pub fn new(position: Position) -> Self {
         Game { position, turn: 1, selected: None, result: GameResult::Ongoing, }
     }
+
+    /// Creates a game played under the rules of `variant` from `position`.
+    pub fn with_variant(mut position: Position, variant: Variant) -> Self {
+        position.variant = variant;
+        Game::new(position)
+    }
+
+    /// Returns the rule set the game is played under.
+    pub fn variant(&self) -> Variant {
+        self.position.variant
+    }
     
     /// Increments the turn counter by one.
     ///
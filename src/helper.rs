@@ -2,6 +2,7 @@ use crate::piece::CastlingRights;
 use crate::position::Position;
 use crate::bitboard::BitBoard;
 use crate::position::{Pieces, Sides};
+use crate::variant::Variant;
 
 /// Initializes a chessboard to the standard starting position.
 ///
@@ -19,6 +20,11 @@ pub fn initialize_board() -> Position {
         bb_pieces: [[BitBoard(0); 6]; 2], 
         castling_rights: CastlingRights::new(),
         en_passant: None,
+        variant: Variant::Standard,
+        pockets: [[0; 5]; 2],
+        promoted: BitBoard(0),
+        checks: [0; 2],
+        duck: BitBoard(0),
     };
 
     // White pieces
@@ -0,0 +1,70 @@
+use crate::piece::Color;
+use crate::position::Position;
+
+// see: https://lichess.org/variant/horde
+
+/// The Horde start position: 36 white pawns and no white king against the usual black army.
+pub const START_FEN: &str = "rnbqkbnr/pppppppp/8/1PP2PP1/PPPPPPPP/PPPPPPPP/PPPPPPPP/PPPPPPPP w kq - 0 1";
+
+/// Returns `true` if a pawn of `color` on `from` may push two squares in Horde.
+///
+/// White pawns on the first rank may double-push like those on the second,
+/// but cannot be taken en passant afterwards.
+pub fn is_first_rank_pawn(color: Color, from: u8) -> bool {
+    color == Color::White && from < 8
+}
+
+/// Returns `true` if `color` has no pieces left: Black wins by capturing the whole horde.
+pub fn is_wiped_out(color: Color, position: &Position) -> bool {
+    position.bb_sides[color.index()].0 == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fen::parse_fen;
+    use crate::game::{Game, GameResult};
+    use crate::make_move::legal_moves;
+    use crate::position::Pieces;
+    use crate::uci::{move_from_uci, move_to_uci};
+    use crate::variant::Variant;
+    use crate::variant::testing::{play, variant_game};
+
+    fn horde_game(fen: &str) -> (Game, Color) {
+        variant_game(fen, Variant::Horde)
+    }
+
+    #[test]
+    fn start_position() {
+        let (position, _) = parse_fen(START_FEN).unwrap();
+        assert_eq!(position.bb_pieces[0][Pieces::PAWN].0.count_ones(), 36);
+        assert_eq!(position.bb_sides[0].0.count_ones(), 36);
+    }
+
+    #[test]
+    fn first_rank_pawns_double_push() {
+        let fen = "4k3/8/8/8/8/1p6/8/P7 w - - 0 1";
+        let (mut game, color) = horde_game(fen);
+        let mut moves: Vec<String> = legal_moves(color, &game.position).iter().map(move_to_uci).collect();
+        moves.sort();
+        assert_eq!(moves, ["a1a2", "a1a3"]);
+
+        // ...but cannot be taken en passant afterwards
+        play("a1a3", color, &mut game);
+        assert_eq!(game.position.en_passant, None);
+        assert!(move_from_uci("b3a2", Color::Black, &game.position).is_none());
+
+        // only in Horde
+        let (position, color) = parse_fen(fen).unwrap();
+        assert!(move_from_uci("a1a3", color, &position).is_none());
+    }
+
+    #[test]
+    fn capturing_the_whole_horde_wins() {
+        let (mut game, color) = horde_game("4k3/8/8/8/8/8/1q6/P7 b - - 0 1");
+        play("b2a1", color, &mut game);
+        assert!(is_wiped_out(Color::White, &game.position));
+        assert_eq!(game.result, GameResult::AllCaptured(Color::White));
+        assert_eq!(game.result.winner(), Some(Color::Black));
+    }
+}
@@ -0,0 +1,67 @@
+use crate::piece::Color;
+use crate::position::{Pieces, Position};
+
+// see: https://lichess.org/variant/kingOfTheHill
+
+/// The hill: d4, e4, d5 and e5. A king reaching one of them wins.
+pub const HILL: u64 = 0x0000_0018_1800_0000;
+
+/// Evaluation bonus in centipawns by the king's distance to the hill (`0` = on it).
+pub const HILL_BONUS: [i32; 4] = [0, 120, 50, 15];
+
+/// Returns `true` if `color`'s king stands on the hill.
+pub fn is_on_hill(color: Color, position: &Position) -> bool {
+    position.bb_pieces[color.index()][Pieces::KING].0 & HILL != 0
+}
+
+/// Returns the number of king steps from `square` to the nearest hill square.
+pub fn hill_distance(square: u8) -> u8 {
+    let file = square % 8;
+    let rank = square / 8;
+    let file_distance = 3u8.saturating_sub(file).max(file.saturating_sub(4));
+    let rank_distance = 3u8.saturating_sub(rank).max(rank.saturating_sub(4));
+    file_distance.max(rank_distance)
+}
+
+/// Evaluation term rewarding a king close to the hill, from the point of view of `color`.
+pub fn evaluate(color: Color, position: &Position) -> i32 {
+    let bonus = |side: Color| {
+        let king = position.bb_pieces[side.index()][Pieces::KING].0;
+        if king == 0 {
+            return 0;
+        }
+        HILL_BONUS.get(hill_distance(king.trailing_zeros() as u8) as usize).copied().unwrap_or(0)
+    };
+    bonus(color) - bonus(color.opponent())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::GameResult;
+    use crate::helper::square_to_index;
+    use crate::variant::Variant;
+    use crate::variant::testing::{play, variant_game};
+
+    #[test]
+    fn hill_distances() {
+        for square in ["d4", "e4", "d5", "e5"] {
+            assert_eq!(hill_distance(square_to_index(square).unwrap()), 0);
+        }
+        assert_eq!(hill_distance(0), 3);
+        assert_eq!(hill_distance(63), 3);
+        assert_eq!(hill_distance(square_to_index("f6").unwrap()), 1);
+    }
+
+    #[test]
+    fn king_reaching_the_hill_wins() {
+        let (mut game, color) = variant_game("7k/8/8/8/8/3K4/8/8 w - - 0 1", Variant::KingOfTheHill);
+        play("d3c4", color, &mut game);
+        assert_eq!(game.result, GameResult::Ongoing);
+        play("h8g8", Color::Black, &mut game);
+        play("c4d5", color, &mut game);
+        assert!(is_on_hill(Color::White, &game.position));
+        assert_eq!(game.result, GameResult::KingOfTheHill(Color::Black));
+        assert_eq!(game.result.winner(), Some(Color::White));
+    }
+}
@@ -6,10 +6,47 @@ pub mod moves;
 pub mod special_moves;
 pub mod make_move;
 pub mod helper;
+pub mod fen;
+pub mod eval;
+pub mod nnue;
+pub mod tuning;
+pub mod datagen;
+pub mod rng;
+pub mod skill;
+pub mod engine;
+pub mod mcts;
+pub mod player;
+pub mod match_runner;
+pub mod polyglot;
+pub mod pgn;
+pub mod tablebase;
+pub mod syzygy;
+pub mod book_builder;
+pub mod see;
+pub mod search;
+pub mod zobrist;
+pub mod tt;
+pub mod move_ordering;
+pub mod time_manager;
+pub mod chess960;
+pub mod variant;
+pub mod crazyhouse;
+pub mod atomic;
+pub mod antichess;
+pub mod three_check;
+pub mod king_of_the_hill;
+pub mod horde;
+pub mod racing_kings;
+pub mod duck;
+pub mod uci;
 
 pub use bitboard::BitBoard;
 pub use position::{Position, Sides};
 pub use game::Game; 
+pub use fen::{parse_fen, to_fen};
 pub use moves::{valid_moves, Move};
 pub use make_move::make_move;
+pub use search::{search, PvLine, SearchOptions, SearchResult, Searcher};
+pub use see::see;
+pub use time_manager::{TimeControl, TimeManager};
 pub use helper::{initialize_board, index_to_square, square_to_index,print_debug_board};
\ No newline at end of file
@@ -1,7 +1,17 @@
+use crate::antichess;
+use crate::atomic;
+use crate::crazyhouse;
+use crate::duck;
 use crate::game::{Game, GameResult};
-use crate::moves::{Move, valid_moves};
+use crate::horde;
+use crate::king_of_the_hill;
+use crate::moves::{Move, valid_moves, captured_piece};
 use crate::piece::{Color, Piece};
-use crate::position::{Pieces, Position, Sides};
+use crate::position::{Pieces, Position, Sides, get_piece_at};
+use crate::racing_kings;
+use crate::special_moves::castling_squares;
+use crate::three_check;
+use crate::variant::Variant;
 
 // see: https://www.chessprogramming.org/Bitboard_Serialization
 
@@ -25,86 +35,137 @@ use crate::position::{Pieces, Position, Sides};
 /// # Returns
 /// `Ok(())` if the move was applied successfully.
 pub fn make_move(m: Move, game: &mut Game) -> Result<(), String> {
+    make_move_quiet(m, game)?;
+
+    // check if opponent king is in check
+    let enemy_color = m.piece.color().opponent();
+    if is_checked(enemy_color, &game.position) {
+        println!("{:?} king is in check", enemy_color);
+    }
+    match game.result {
+        GameResult::Checkmate(color) => println!("{:?} is checkmated.", color),
+        GameResult::Stalemate => println!("Stalemate! It's a draw."),
+        GameResult::KingExploded(color) => println!("{:?} king exploded.", color),
+        GameResult::OutOfMoves(color) => println!("{:?} loses: the opponent has no moves left.", color),
+        GameResult::ThirdCheck(color) => println!("{:?} received a third check.", color),
+        GameResult::KingOfTheHill(color) => println!("{:?} lost the hill.", color),
+        GameResult::AllCaptured(color) => println!("{:?} has no pieces left.", color),
+        GameResult::RaceLost(color) => println!("{:?} lost the race.", color),
+        GameResult::KingCaptured(color) => println!("{:?} king was captured.", color),
+        _ => {}
+    }
+    //println!("En Passant: {:?}", position.en_passant);
+    Ok(())
+}
+
+/// Like [`make_move`], but without printing anything.
+///
+/// Used where many games are played automatically, e.g. by bots.
+pub fn make_move_quiet(m: Move, game: &mut Game) -> Result<(), String> {
     let position = &mut game.position;
-    let valid = valid_moves(m.from, m.piece, position);
-    if !valid.contains(&m) {
-        return Err("Illegal move (not in generated valid moves)".to_string());
+    if m.drop {
+        if !crazyhouse::legal_drops(m.piece.color(), position).iter().any(|d| d.is_same(&m)) {
+            return Err("Illegal drop".to_string());
+        }
+    } else {
+        // promotions are generated from the pawn, not the promoted piece
+        let moved_piece = if m.promoted_from_pawn { Piece::Pawn(m.piece.color()) } else { m.piece };
+        let valid = valid_moves(m.from, moved_piece, position);
+        // the duck is placed after the piece has moved and checked separately
+        let piece_part = Move { duck: None, ..m };
+        if !valid.iter().any(|v| v.is_same(&piece_part)) {
+            return Err("Illegal move (not in generated valid moves)".to_string());
+        }
+        if !is_duck_valid(&m, position) {
+            return Err("Illegal move: the duck must move to an empty square".to_string());
+        }
+        if position.variant == Variant::Antichess
+            && captured_piece(&m, position).is_none()
+            && antichess::has_capture(m.piece.color(), position)
+        {
+            return Err("Illegal move: captures are compulsory".to_string());
+        }
     }
 
     // simulate on a clone to check if this move leaves current player's king in check
     let mut test_pos = position.clone();
     apply_move_unchecked(m, &mut test_pos);
-    if is_checked(m.piece.color(), &test_pos) {
+    if !is_safe_after(m.piece.color(), &test_pos) {
         return Err("Illegal move: would leave your king in check".to_string());
     }
+    if !is_castling_safe(&m, position) {
+        return Err("Illegal move: cannot castle out of or through check".to_string());
+    }
 
     // commit to real position
     apply_move_unchecked(m, position);
     update_castling_rights(m, position);
 
-    // check if opponent king is in check
-    let enemy_color = match m.piece.color() {
-        Color::White => Color::Black,
-        Color::Black => Color::White,
-    };
-
-    if is_checked(enemy_color, position) {
-        println!("{:?} king is in check", enemy_color);
-    }
-    if is_checkmated(enemy_color, &position) {
-        println!("{:?} is checkmated.", enemy_color);
-        game.result = GameResult::Checkmate(enemy_color);
+    let enemy_color = m.piece.color().opponent();
+    if let Some(result) = variant_result(enemy_color, position) {
+        game.result = result;
         return Ok(());
-    } else if is_stalemated(enemy_color, &position) {
-        println!("Stalemate! It's a draw.");
-        game.result = GameResult::Stalemate;
+    }
+    if legal_moves(enemy_color, position).is_empty() {
+        game.result = no_moves_result(enemy_color, position);
         return Ok(());
     }
-    //println!("En Passant: {:?}", position.en_passant);
     game.turn_tracker();
     Ok(())
 }
 
 /// Updates castling rights in the given position after a move.
 ///
-/// Castling rights are revoked when a king or rook moves from its initial square.
+/// Castling rights are revoked when a king moves, or when a rook moves from or
+/// is captured on its starting square.
 ///
 /// # Arguments
 /// * `m` - The move to check.
 /// * `position` - The mutable board state to update.
-fn update_castling_rights(m: Move, position: &mut Position) {
+pub(crate) fn update_castling_rights(m: Move, position: &mut Position) {
+    let rights = &mut position.castling_rights;
     match m.piece {
-        Piece::King(Color::White) => position.castling_rights.white_king_moved = true,
-        Piece::King(Color::Black) => position.castling_rights.black_king_moved = true,
-        Piece::Rook(Color::White) => {
-            if m.from == 0 {
-                position.castling_rights.white_queenside_rook_moved = true;
-            } // can this cause a pooblem if it mvoes multiple times from the same spot? true + true = ...?
-            if m.from == 7 {
-                position.castling_rights.white_kingside_rook_moved = true;
-            }
-        }
-        Piece::Rook(Color::Black) => {
-            if m.from == 56 {
-                position.castling_rights.black_queenside_rook_moved = true;
-            }
-            if m.from == 63 {
-                position.castling_rights.black_kingside_rook_moved = true;
+        Piece::King(Color::White) => rights.white_king_moved = true,
+        Piece::King(Color::Black) => rights.black_king_moved = true,
+        _ => {}
+    }
+    for color in [Color::White, Color::Black] {
+        for kingside in [true, false] {
+            let square = rights.rook_square(color, kingside);
+            // the rook leaving its square, or being captured (or castled with) there
+            if m.from == square || m.to == square {
+                rights.revoke(color, kingside);
             }
         }
-        _ => {}
     }
 }
 
 /// Applies a move directly to the given position without legality checks.
 ///
 /// This function updates side and piece bitboards, handles captures,
-/// castling, promotions, and en passant.
+/// castling, promotions, and en passant. In atomic chess captures explode
+/// (see [`atomic::explode`]), in three-check a move giving check is
+/// counted in [`Position::checks`], and in duck chess the duck is placed last.
 ///
 /// # Arguments
 /// * `m` - The move to apply.
 /// * `position` - The mutable board state to update.
 pub fn apply_move_unchecked(m: Move, position: &mut Position) {
+    move_pieces(m, position);
+    // only three-check counts checks: is_checked is too slow to run on every move
+    if position.variant == Variant::ThreeCheck {
+        let color = m.piece.color();
+        if is_checked(color.opponent(), position) {
+            position.checks[color.index()] += 1;
+        }
+    }
+    if let Some(square) = m.duck {
+        duck::place_duck(square, position);
+    }
+}
+
+/// Moves the pieces for [`apply_move_unchecked`].
+fn move_pieces(m: Move, position: &mut Position) {
     let color = m.piece.color();
     let friendly_index = match color {
         Color::White => Sides::WHITE,
@@ -116,6 +177,28 @@ pub fn apply_move_unchecked(m: Move, position: &mut Position) {
         Sides::WHITE
     };
 
+    if m.drop {
+        crazyhouse::apply_drop(m, position);
+        return;
+    }
+    let has_pockets = position.variant == Variant::Crazyhouse;
+    let mut captured = false;
+
+    // Castling: king and rook go to their castling squares (in Chess960 the move
+    // targets the rook's square, which must not be treated as a normal landing square)
+    if let Some(castling) = castling_squares(&m, position) {
+        for (from, piece_index) in [(castling.king_from, Pieces::KING), (castling.rook_from, Pieces::ROOK)] {
+            position.bb_sides[friendly_index].0 &= !(1u64 << from);
+            position.bb_pieces[friendly_index][piece_index].0 &= !(1u64 << from);
+        }
+        for (to, piece_index) in [(castling.king_to, Pieces::KING), (castling.rook_to, Pieces::ROOK)] {
+            position.bb_sides[friendly_index].0 |= 1u64 << to;
+            position.bb_pieces[friendly_index][piece_index].0 |= 1u64 << to;
+        }
+        position.en_passant = None;
+        return;
+    }
+
     let from_mask: u64 = 1u64 << m.from;
     let to_mask: u64 = 1u64 << m.to;
 
@@ -137,40 +220,20 @@ pub fn apply_move_unchecked(m: Move, position: &mut Position) {
     }
     // if 'to' square contains an enemy, remove that enemy piece
     if (position.bb_sides[enemy_index].0 & to_mask) != 0 {
+        captured = true;
         // remove from enemy SIDE occupancy
         position.bb_sides[enemy_index].0 &= !to_mask;
         // find which enemy PIECE LAYER has the bit and clear it
         for i in 0..6 {
             if (position.bb_pieces[enemy_index][i].0 & to_mask) != 0 {
                 position.bb_pieces[enemy_index][i].0 &= !to_mask;
+                if has_pockets {
+                    crazyhouse::pocket_capture(friendly_index, i, m.to, position);
+                }
                 break;
             }
         }
     }
-    // Castling: king moves 2 squares horizontally
-    if let Piece::King(color) = m.piece {
-        if (m.from as i8 - m.to as i8).abs() == 2 {
-            let (rook_from, rook_to) = match (color, m.to) {
-                (Color::Black, 62) => (63, 61),
-                (Color::Black, 58) => (56, 59),
-                (Color::White, 6) => (7, 5), 
-                (Color::White, 2) => (0, 3),
-                _ => (0, 0),
-            };
-
-            let rook_mask_from = 1u64 << rook_from;
-            let rook_mask_to = 1u64 << rook_to;
-            // ("rook from {}, to {}", rook_from, rook_to);
-
-            // remove rook from original square
-            position.bb_sides[friendly_index].0 &= !rook_mask_from;
-            position.bb_pieces[friendly_index][Pieces::ROOK].0 &= !rook_mask_from;
-
-            // place rook on new square
-            position.bb_sides[friendly_index].0 |= rook_mask_to;
-            position.bb_pieces[friendly_index][Pieces::ROOK].0 |= rook_mask_to;
-        }
-    }
     if let Piece::Pawn(pawn_color) = m.piece {
         let dir = match pawn_color {
             Color::White => 8,
@@ -182,17 +245,25 @@ pub fn apply_move_unchecked(m: Move, position: &mut Position) {
             if m.to == ep_square {
                 let captured_pawn_square = (ep_square as i8 - dir) as u8;
                 let captured_mask = 1u64 << captured_pawn_square;
+                captured = true;
 
                 position.bb_sides[enemy_index].0 &= !captured_mask;
                 position.bb_pieces[enemy_index][Pieces::PAWN].0 &= !captured_mask;
+                if has_pockets {
+                    crazyhouse::pocket_capture(friendly_index, Pieces::PAWN, captured_pawn_square, position);
+                }
             }
         }
         position.en_passant = None;
-        if (m.from as i8 + 2 * dir) == m.to as i8 {
+        // Horde pawns double-pushing from the first rank cannot be taken en passant
+        if (m.from as i8 + 2 * dir) == m.to as i8 && !horde::is_first_rank_pawn(pawn_color, m.from) {
             let ep_square = (m.from as i8 + dir) as u8;
             position.en_passant = Some(ep_square);
             //println!("En passant square {}", ep_square);
         }
+    } else {
+        // the en passant square only lasts for one reply
+        position.en_passant = None;
     }
 
     let piece_index = if m.promoted_from_pawn {
@@ -202,6 +273,7 @@ pub fn apply_move_unchecked(m: Move, position: &mut Position) {
             Piece::Rook(_) => Pieces::ROOK,
             Piece::Bishop(_) => Pieces::BISHOP,
             Piece::Knight(_) => Pieces::KNIGHT,
+            Piece::King(_) => Pieces::KING,
             _ => Pieces::PAWN,
         }
     } else {
@@ -217,12 +289,159 @@ pub fn apply_move_unchecked(m: Move, position: &mut Position) {
 
     position.bb_sides[friendly_index].0 |= to_mask;
     position.bb_pieces[friendly_index][piece_index].0 |= to_mask;
+    if has_pockets {
+        crazyhouse::track_promoted(m, position);
+    }
+    if captured && position.variant == Variant::Atomic {
+        atomic::explode(m.to, position);
+    }
+}
+
+/// Returns `true` if `m` is a legal move for `color` in `position`.
+///
+/// Unlike [`make_move`] this does not touch any game state, which makes it
+/// suitable for validating moves that come from elsewhere, such as a
+/// transposition table or a killer slot of a sibling node.
+///
+/// # Arguments
+/// * `m` - The move to check.
+/// * `color` - The side to move.
+/// * `position` - The board state.
+pub fn is_legal(m: Move, color: Color, position: &Position) -> bool {
+    if m.piece.color() != color {
+        return false;
+    }
+    if m.drop {
+        return crazyhouse::legal_drops(color, position).iter().any(|d| d.is_same(&m));
+    }
+    let moved_piece = if m.promoted_from_pawn { Piece::Pawn(color) } else { m.piece };
+    if get_piece_at(position, m.from) != Some(moved_piece) {
+        return false;
+    }
+    let piece_part = Move { duck: None, ..m };
+    if !valid_moves(m.from, moved_piece, position).iter().any(|v| v.is_same(&piece_part)) || !is_duck_valid(&m, position) {
+        return false;
+    }
+    if position.variant == Variant::Antichess {
+        return captured_piece(&m, position).is_some() || !antichess::has_capture(color, position);
+    }
+    let mut test_pos = *position;
+    apply_move_unchecked(m, &mut test_pos);
+    is_safe_after(color, &test_pos) && is_castling_safe(&m, position)
+}
+
+/// Returns `false` for a duck chess move without a valid duck square, and for
+/// a move placing a duck in any other variant.
+fn is_duck_valid(m: &Move, position: &Position) -> bool {
+    if position.variant != Variant::Duck {
+        return m.duck.is_none();
+    }
+    let mut after = *position;
+    apply_move_unchecked(Move { duck: None, ..*m }, &mut after);
+    duck::is_valid_placement(m, &after)
+}
+
+/// Returns `true` if a move by `color` that led to `after` does not leave its king in check.
+///
+/// This is the legality test after applying a pseudo-legal move; variants with
+/// other ideas of king safety decide it themselves.
+fn is_safe_after(color: Color, after: &Position) -> bool {
+    match after.variant {
+        Variant::Atomic => atomic::is_safe_after(color, after),
+        // Racing Kings: giving check is as illegal as being in check
+        Variant::RacingKings => !is_checked(color, after) && !is_checked(color.opponent(), after),
+        // duck chess: kings may be left en prise, capturing one wins
+        Variant::Duck => true,
+        _ => !is_checked(color, after),
+    }
+}
+
+/// Returns the result if a variant rule has ended the game before `color` moves.
+///
+/// Checkmate and stalemate are not covered, see [`is_checkmated`] and [`is_stalemated`].
+///
+/// # Arguments
+/// * `color` - The side to move.
+/// * `position` - The board state.
+///
+/// # Returns
+/// `Some(GameResult)` if the game is over by a variant rule, e.g. an exploded
+/// king in atomic chess or a third check, otherwise `None`.
+pub fn variant_result(color: Color, position: &Position) -> Option<GameResult> {
+    match position.variant {
+        Variant::Atomic => {
+            if atomic::is_exploded(color, position) {
+                Some(GameResult::KingExploded(color))
+            } else if atomic::is_exploded(color.opponent(), position) {
+                Some(GameResult::KingExploded(color.opponent()))
+            } else {
+                None
+            }
+        }
+        Variant::Antichess if position.bb_sides[color.index()].0 == 0 => Some(GameResult::OutOfMoves(color.opponent())),
+        Variant::ThreeCheck if three_check::has_won(color.opponent(), position) => Some(GameResult::ThirdCheck(color)),
+        Variant::KingOfTheHill if king_of_the_hill::is_on_hill(color.opponent(), position) => {
+            Some(GameResult::KingOfTheHill(color))
+        }
+        Variant::Horde => [color, color.opponent()]
+            .into_iter()
+            .find(|&side| horde::is_wiped_out(side, position))
+            .map(GameResult::AllCaptured),
+        Variant::Duck => [color, color.opponent()]
+            .into_iter()
+            .find(|&side| duck::is_king_captured(side, position))
+            .map(GameResult::KingCaptured),
+        Variant::RacingKings => racing_kings::race_result(color, position).map(|winner| match winner {
+            Some(winner) => GameResult::RaceLost(winner.opponent()),
+            None => GameResult::Draw,
+        }),
+        _ => None,
+    }
+}
+
+/// Returns the result of the game when `color` is to move but has no legal moves.
+///
+/// In standard chess that is checkmate if `color` is in check and stalemate
+/// otherwise; in antichess and duck chess, running out of moves wins.
+///
+/// # Arguments
+/// * `color` - The side to move.
+/// * `position` - The board state.
+pub fn no_moves_result(color: Color, position: &Position) -> GameResult {
+    match position.variant {
+        Variant::Antichess | Variant::Duck => GameResult::OutOfMoves(color.opponent()),
+        _ if is_checked(color, position) => GameResult::Checkmate(color),
+        _ => GameResult::Stalemate,
+    }
+}
+
+/// Returns `false` for a castling move out of check or across an attacked square.
+///
+/// The king's destination is not checked here; like for any other move, that is
+/// done by applying the move and testing [`is_checked`].
+///
+/// # Arguments
+/// * `m` - A pseudo-legal move.
+/// * `position` - The position before the move.
+fn is_castling_safe(m: &Move, position: &Position) -> bool {
+    let Some(castling) = castling_squares(m, position) else { return true };
+    let color = m.piece.color();
+    let side = color.index();
+    let (low, high) = (castling.king_from.min(castling.king_to), castling.king_from.max(castling.king_to));
+    // the king's own square is always tested: in Chess960 it may already stand on its destination
+    (low..=high).filter(|&square| square == castling.king_from || square != castling.king_to).all(|square| {
+        let mut test_pos = *position;
+        test_pos.bb_pieces[side][Pieces::KING].0 = 1u64 << square;
+        test_pos.bb_sides[side].0 = (test_pos.bb_sides[side].0 & !(1u64 << castling.king_from)) | 1u64 << square;
+        !is_checked(color, &test_pos)
+    })
 }
 
 /// Returns `true` if the given color’s king is in check.
 ///
 /// A king is considered checked if any opposing piece
-/// has a legal attack on its square.
+/// has a legal attack on its square. In atomic chess a king next to the
+/// enemy king is never in check, and in antichess and duck chess there is no check at all.
 ///
 /// # Arguments
 /// * `color` - The side to check for.
@@ -236,11 +455,17 @@ pub fn is_checked(color: Color, position: &Position) -> bool {
         Color::White => (Sides::WHITE, Sides::BLACK),
         Color::Black => (Sides::BLACK, Sides::WHITE),
     };
+    if matches!(position.variant, Variant::Antichess | Variant::Duck) {
+        return false;
+    }
     let king_bb = position.bb_pieces[friendly_index][Pieces::KING].0;
     if king_bb == 0 {
         return false;
         // panic!("No king found for {:?} in position!", color);        add panic in final version
     }
+    if position.variant == Variant::Atomic && atomic::kings_adjacent(position) {
+        return false;
+    }
     let king_sq = king_bb.trailing_zeros() as u8;
     let enemy_color = match color {
         Color::White => Color::Black,
@@ -277,7 +502,12 @@ pub fn is_checked(color: Color, position: &Position) -> bool {
 /// Generates all legal moves for the given color.
 ///
 /// Pseudo-legal moves are generated with [`valid_moves`],
-/// then filtered to exclude moves that leave the king in check.
+/// then filtered to exclude moves that leave the king in check
+/// (in atomic chess: that explode the own king, see [`atomic::is_safe_after`];
+/// in Racing Kings: that give check as well).
+/// In Crazyhouse the legal drops are added (see [`crazyhouse::legal_drops`]),
+/// in antichess only captures are legal whenever there are any (see [`antichess::legal_moves`]),
+/// and in duck chess every move comes once per duck square (see [`duck::with_placements`]).
 ///
 /// # Arguments
 /// * `color` - The side to generate moves for.
@@ -286,6 +516,77 @@ pub fn is_checked(color: Color, position: &Position) -> bool {
 /// # Returns
 /// A vector of all legal moves available to `color`.
 pub fn legal_moves(color: Color, position: &Position) -> Vec<Move> {
+    legal_moves_where(color, position, |_| true)
+}
+
+/// Generates all legal captures for the given color.
+///
+/// Includes en passant captures and every promotion (capturing or not),
+/// since both change the material balance. This is the move set searched
+/// by quiescence search.
+///
+/// # Arguments
+/// * `color` - The side to generate moves for.
+/// * `position` - The board state.
+///
+/// # Returns
+/// A vector of legal captures and promotions available to `color`.
+pub fn capture_moves(color: Color, position: &Position) -> Vec<Move> {
+    legal_moves_where(color, position, |m| {
+        m.promoted_from_pawn || captured_piece(m, position).is_some()
+    })
+}
+
+/// Generates all legal quiet moves for the given color.
+///
+/// The complement of [`capture_moves`]: neither captures nor promotions.
+///
+/// # Arguments
+/// * `color` - The side to generate moves for.
+/// * `position` - The board state.
+///
+/// # Returns
+/// A vector of legal quiet moves available to `color`.
+pub fn quiet_moves(color: Color, position: &Position) -> Vec<Move> {
+    legal_moves_where(color, position, |m| {
+        !m.promoted_from_pawn && captured_piece(m, position).is_none()
+    })
+}
+
+/// Generates all legal quiet moves that give check.
+///
+/// Captures and promotions are excluded, as they are already covered by
+/// [`capture_moves`]. Together the two form the capture-and-check move set
+/// optionally used at the first ply of quiescence search.
+///
+/// # Arguments
+/// * `color` - The side to generate moves for.
+/// * `position` - The board state.
+///
+/// # Returns
+/// A vector of quiet checking moves available to `color`.
+pub fn checking_moves(color: Color, position: &Position) -> Vec<Move> {
+    quiet_moves(color, position)
+        .into_iter()
+        .filter(|m| {
+            let mut test_pos = *position;
+            apply_move_unchecked(*m, &mut test_pos);
+            is_checked(color.opponent(), &test_pos)
+        })
+        .collect()
+}
+
+/// Generates the legal moves for `color` that satisfy `keep`.
+///
+/// `keep` runs on the pseudo-legal moves before the (expensive) king safety
+/// check, so narrow move sets such as captures skip most of that work.
+fn legal_moves_where<F>(color: Color, position: &Position, keep: F) -> Vec<Move>
+where
+    F: Fn(&Move) -> bool,
+{
+    if position.variant == Variant::Antichess {
+        return antichess::legal_moves(color, position).into_iter().filter(|m| keep(m)).collect();
+    }
     let mut result = Vec::new();
 
     // find all friendly pieces
@@ -313,15 +614,23 @@ pub fn legal_moves(color: Color, position: &Position) -> Vec<Move> {
             let pseudo_moves = valid_moves(from, piece, position);
 
             // filter out moves that leave king in check
-            for m in pseudo_moves {
+            for m in pseudo_moves.into_iter().filter(|m| keep(m)) {
                 let mut test_pos = position.clone();
                 apply_move_unchecked(m, &mut test_pos);
-                if !is_checked(color, &test_pos) {
+                if !is_safe_after(color, &test_pos) || !is_castling_safe(&m, position) {
+                    continue;
+                }
+                if position.variant == Variant::Duck {
+                    result.extend(duck::with_placements(m, &test_pos));
+                } else {
                     result.push(m);
                 }
             }
         }
     }
+    if position.variant == Variant::Crazyhouse {
+        result.extend(crazyhouse::legal_drops(color, position).into_iter().filter(|m| keep(m)));
+    }
     result
 }
 
@@ -348,3 +657,138 @@ pub fn is_checkmated(color: Color, position: &Position) -> bool {
 pub fn is_stalemated(color: Color, position: &Position) -> bool {
     !is_checked(color, position) && legal_moves(color, position).is_empty()
 }
+
+/// Returns `true` if neither side has enough material to checkmate.
+///
+/// Covers king vs king and king plus a single bishop or knight vs king. Only
+/// applies to standard and atomic chess: in the other variants captured pieces
+/// come back as drops, or the game is won by other means than mate.
+///
+/// # Arguments
+/// * `position` - The board state.
+pub fn is_insufficient_material(position: &Position) -> bool {
+    if !matches!(position.variant, Variant::Standard | Variant::Atomic) {
+        return false;
+    }
+    let mut minors = 0;
+    for side in [Sides::WHITE, Sides::BLACK] {
+        let pieces = &position.bb_pieces[side];
+        if pieces[Pieces::PAWN].0 | pieces[Pieces::ROOK].0 | pieces[Pieces::QUEEN].0 != 0 {
+            return false;
+        }
+        minors += (pieces[Pieces::KNIGHT].0 | pieces[Pieces::BISHOP].0).count_ones();
+    }
+    minors <= 1
+}
+
+/// Counts the leaf nodes of the legal move tree of `color` to the given depth.
+///
+/// The standard check of a move generator: the counts of well-known positions
+/// are published, see <https://www.chessprogramming.org/Perft_Results>.
+///
+/// # Arguments
+/// * `color` - The side to move.
+/// * `position` - The board state.
+/// * `depth` - The number of plies to play out.
+pub fn perft(color: Color, position: &Position, depth: u32) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+    let moves = legal_moves(color, position);
+    if depth == 1 {
+        return moves.len() as u64;
+    }
+    moves
+        .into_iter()
+        .map(|m| {
+            let mut child = *position;
+            apply_move_unchecked(m, &mut child);
+            update_castling_rights(m, &mut child);
+            perft(color.opponent(), &child, depth - 1)
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fen::{START_FEN, parse_fen};
+
+    fn perft_fen(fen: &str, depth: u32) -> u64 {
+        let (position, color) = parse_fen(fen).unwrap();
+        perft(color, &position, depth)
+    }
+
+    #[test]
+    fn perft_start_position() {
+        assert_eq!(perft_fen(START_FEN, 1), 20);
+        assert_eq!(perft_fen(START_FEN, 2), 400);
+        assert_eq!(perft_fen(START_FEN, 3), 8_902);
+    }
+
+    #[test]
+    fn perft_kiwipete() {
+        let fen = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+        assert_eq!(perft_fen(fen, 1), 48);
+        assert_eq!(perft_fen(fen, 2), 2_039);
+    }
+
+    #[test]
+    fn perft_position_3() {
+        let fen = "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1";
+        assert_eq!(perft_fen(fen, 1), 14);
+        assert_eq!(perft_fen(fen, 2), 191);
+        assert_eq!(perft_fen(fen, 3), 2_812);
+        assert_eq!(perft_fen(fen, 4), 43_238);
+    }
+
+    #[test]
+    fn perft_position_4() {
+        let fen = "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1";
+        assert_eq!(perft_fen(fen, 1), 6);
+        assert_eq!(perft_fen(fen, 2), 264);
+        assert_eq!(perft_fen(fen, 3), 9_467);
+    }
+
+    #[test]
+    fn perft_position_5() {
+        let fen = "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8";
+        assert_eq!(perft_fen(fen, 1), 44);
+        assert_eq!(perft_fen(fen, 2), 1_486);
+        assert_eq!(perft_fen(fen, 3), 62_379);
+    }
+
+    #[test]
+    fn perft_position_6() {
+        let fen = "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10";
+        assert_eq!(perft_fen(fen, 1), 46);
+        assert_eq!(perft_fen(fen, 2), 2_079);
+    }
+
+    #[test]
+    fn en_passant_is_listed_once() {
+        let fen = "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3";
+        let (position, color) = parse_fen(fen).unwrap();
+        let en_passant: Vec<Move> = legal_moves(color, &position).into_iter().filter(|m| m.from == 36 && m.to == 45).collect();
+        assert_eq!(en_passant.len(), 1);
+        assert_eq!(perft(color, &position, 1), 31);
+        // the square only lasts for one reply
+        let knight = legal_moves(color, &position).into_iter().find(|m| matches!(m.piece, Piece::Knight(_))).unwrap();
+        let mut after = position;
+        apply_move_unchecked(knight, &mut after);
+        assert_eq!(after.en_passant, None);
+    }
+
+    #[test]
+    fn sliders_reach_the_corners_without_wrapping() {
+        // a bishop on a1 sees the whole long diagonal, h8 included
+        let (position, color) = parse_fen("1k6/8/8/8/8/8/8/B6K w - - 0 1").unwrap();
+        let bishop: Vec<u8> = legal_moves(color, &position).iter().filter(|m| m.from == 0).map(|m| m.to).collect();
+        assert_eq!(bishop, [9, 18, 27, 36, 45, 54, 63]);
+        // a rook on h1 does not wrap onto a2
+        let (position, color) = parse_fen("k7/8/8/8/8/8/8/K6R w - - 0 1").unwrap();
+        let rook: Vec<u8> = legal_moves(color, &position).iter().filter(|m| m.from == 7).map(|m| m.to).collect();
+        assert!(!rook.contains(&8), "{:?}", rook);
+        assert_eq!(rook.len(), 7 + 6);
+    }
+}
@@ -0,0 +1,807 @@
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::engine::SearchLimits;
+use crate::fen::{START_FEN, parse_fen, to_fen};
+use crate::game::{Game, GameResult};
+use crate::make_move::{is_insufficient_material, legal_moves, make_move_quiet, no_moves_result, variant_result};
+use crate::moves::{Move, captured_piece};
+use crate::piece::{Color, Piece};
+use crate::player::Player;
+use crate::position::Position;
+use crate::search::MATE_SCORE;
+use crate::time_manager::TimeControl;
+use crate::uci::{move_from_uci, move_to_uci};
+use crate::zobrist::zobrist_key;
+
+// see: https://www.chessprogramming.org/Match_Statistics
+// and: https://www.chessprogramming.org/Sequential_Probability_Ratio_Test
+
+/// Reads an opening file: one FEN or EPD position per line.
+///
+/// Empty lines and lines starting with `#` are skipped. Only the first four
+/// fields of each line are used, so EPD operations and move counters are ignored.
+///
+/// # Errors
+/// Returns `Err(String)` if the file cannot be read, contains an invalid
+/// position, or contains no positions at all.
+pub fn load_openings(path: &str) -> Result<Vec<String>, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("Cannot read {}: {}", path, e))?;
+    parse_openings(&text)
+}
+
+/// Parses the contents of an opening file; see [`load_openings`].
+pub fn parse_openings(text: &str) -> Result<Vec<String>, String> {
+    let mut openings = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields: Vec<&str> = line.split_whitespace().take(4).collect();
+        let fen = format!("{} 0 1", fields.join(" "));
+        parse_fen(&fen).map_err(|e| format!("line {}: {}", number + 1, e))?;
+        openings.push(fen);
+    }
+    if openings.is_empty() {
+        return Err("Opening file contains no positions".to_string());
+    }
+    Ok(openings)
+}
+
+/// Why a match game ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Termination {
+    Checkmate,
+    Stalemate,
+    Repetition,
+    FiftyMoves,
+    InsufficientMaterial,
+    Resignation,
+    DrawAgreed,
+    IllegalMove,
+    TimeForfeit,
+    /// The tablebase probe of [`Adjudication::tablebase`] knew the result.
+    Tablebase,
+    /// Both players agreed the game was decided, see [`Adjudication::resign_score`].
+    ScoreWin,
+    /// Both players agreed the game was drawn, see [`Adjudication::draw_score`].
+    ScoreDraw,
+    /// [`Adjudication::max_plies`] was reached.
+    MoveLimit,
+    /// A variant rule ended the game, e.g. an exploded king in atomic chess.
+    Variant,
+}
+
+/// Tablebase lookup used for adjudication.
+///
+/// Given a position and the side to move, returns `Some(1)` if the side to move
+/// wins, `Some(0)` for a draw and `Some(-1)` if it loses, or `None` if the
+/// position is not covered.
+pub type TablebaseProbe = dyn Fn(&Position, Color) -> Option<i32> + Send + Sync;
+
+/// Rules for ending match games early.
+///
+/// Score adjudication uses [`Player::last_score`], so it only applies to players
+/// that report scores (engines).
+pub struct Adjudication {
+    /// Games still running after this many plies are drawn.
+    pub max_plies: u32,
+    /// A game is won once both players' scores have favored the same side by at
+    /// least this much for [`Adjudication::resign_moves`] moves each; `None` disables it.
+    pub resign_score: Option<i32>,
+    pub resign_moves: usize,
+    /// A game is drawn once both players' scores have stayed within `±draw_score`
+    /// for [`Adjudication::draw_moves`] moves each, after [`Adjudication::draw_min_ply`] plies.
+    /// `None` disables it.
+    pub draw_score: Option<i32>,
+    pub draw_moves: usize,
+    pub draw_min_ply: u32,
+    /// Probed before every move; a known result ends the game.
+    pub tablebase: Option<Box<TablebaseProbe>>,
+}
+
+impl Default for Adjudication {
+    fn default() -> Self {
+        Adjudication {
+            max_plies: 400,
+            resign_score: Some(1000),
+            resign_moves: 3,
+            draw_score: Some(10),
+            draw_moves: 8,
+            draw_min_ply: 80,
+            tablebase: None,
+        }
+    }
+}
+
+/// Clock of a timed match: every player starts with `base_ms` and gains
+/// `increment_ms` after each move. Running out of time loses the game.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Clock {
+    pub base_ms: u64,
+    pub increment_ms: u64,
+}
+
+/// Settings of a match between two players.
+pub struct MatchConfig {
+    /// Number of games; an odd number is rounded up so every opening is played with both colors.
+    pub games: usize,
+    /// Start positions as FEN; game pair `i` uses opening `i % openings.len()`.
+    pub openings: Vec<String>,
+    pub adjudication: Adjudication,
+    /// Clock for both players, or `None` to let the players' own limits decide.
+    pub clock: Option<Clock>,
+    /// Number of games played at the same time.
+    pub concurrency: usize,
+    /// Stops the match early once the test has a result.
+    pub sprt: Option<Sprt>,
+}
+
+impl Default for MatchConfig {
+    fn default() -> Self {
+        MatchConfig {
+            games: 100,
+            openings: vec![START_FEN.to_string()],
+            adjudication: Adjudication::default(),
+            clock: None,
+            concurrency: 1,
+            sprt: None,
+        }
+    }
+}
+
+/// One finished match game.
+#[derive(Debug, Clone)]
+pub struct GameRecord {
+    /// Index of the game in the match.
+    pub index: usize,
+    pub opening: String,
+    pub white: String,
+    pub black: String,
+    /// Moves in UCI notation, starting from `opening`.
+    pub moves: Vec<String>,
+    pub result: GameResult,
+    pub termination: Termination,
+}
+
+impl GameRecord {
+    /// The result as in PGN: `1-0`, `0-1` or `1/2-1/2`.
+    pub fn result_text(&self) -> &'static str {
+        match self.result.winner() {
+            Some(Color::White) => "1-0",
+            Some(Color::Black) => "0-1",
+            None => "1/2-1/2",
+        }
+    }
+}
+
+/// Plays one game from `opening`, applying the adjudication rules and clock of `config`.
+///
+/// Unlike [`crate::player::play_game`], this also detects threefold repetition
+/// and the fifty-move rule.
+///
+/// # Arguments
+/// * `white`, `black` - The players; [`Player::new_game`] is called on both first.
+/// * `opening` - FEN of the start position.
+/// * `config` - Adjudication rules and clock.
+///
+/// # Returns
+/// The game record; the result is never [`GameResult::Ongoing`] or [`GameResult::Stalemate`]
+/// (stalemate is reported as [`GameResult::Draw`] with [`Termination::Stalemate`]).
+pub fn play_match_game<'a>(white: &mut (dyn Player + 'a), black: &mut (dyn Player + 'a), opening: &str, config: &MatchConfig) -> GameRecord {
+    white.new_game();
+    black.new_game();
+    let (position, color) = parse_fen(opening).unwrap_or_else(|_| parse_fen(START_FEN).expect("start FEN is valid"));
+    let mut game = Game::new(position);
+    if color == Color::Black {
+        game.turn_tracker();
+    }
+
+    let rules = &config.adjudication;
+    let mut record = GameRecord {
+        index: 0,
+        opening: opening.to_string(),
+        white: white.name(),
+        black: black.name(),
+        moves: Vec::new(),
+        result: GameResult::Ongoing,
+        termination: Termination::MoveLimit,
+    };
+    let mut clocks = config.clock.map(|c| [c.base_ms; 2]);
+    let mut history = vec![zobrist_key(&game.position, color)];
+    let mut halfmove_clock = 0;
+    // scores of the moves played, from White's point of view
+    let mut scores: Vec<Option<i32>> = Vec::new();
+
+    let (result, termination) = loop {
+        let color = game.player_tracker();
+        let ply = record.moves.len() as u32;
+        if game.is_over() {
+            let termination = match game.result {
+                GameResult::Stalemate => Termination::Stalemate,
+                GameResult::Checkmate(_) => Termination::Checkmate,
+                _ => Termination::Variant,
+            };
+            break (game.result, termination);
+        }
+        if let Some(result) = variant_result(color, &game.position) {
+            break (result, Termination::Variant);
+        }
+        if legal_moves(color, &game.position).is_empty() {
+            break match no_moves_result(color, &game.position) {
+                GameResult::Checkmate(loser) => (GameResult::Checkmate(loser), Termination::Checkmate),
+                GameResult::Stalemate => (GameResult::Draw, Termination::Stalemate),
+                result => (result, Termination::Variant),
+            };
+        }
+        if is_insufficient_material(&game.position) {
+            break (GameResult::Draw, Termination::InsufficientMaterial);
+        }
+        let key = history[history.len() - 1];
+        if history.iter().filter(|&&k| k == key).count() >= 3 {
+            break (GameResult::Draw, Termination::Repetition);
+        }
+        if halfmove_clock >= 100 {
+            break (GameResult::Draw, Termination::FiftyMoves);
+        }
+        if let Some(result) = adjudicate(rules, &game.position, color, ply, &scores) {
+            break result;
+        }
+
+        let (mover, opponent) = match color {
+            Color::White => (&mut *white, &mut *black),
+            Color::Black => (&mut *black, &mut *white),
+        };
+        if mover.should_resign(&game) {
+            break (GameResult::Resigned(color), Termination::Resignation);
+        }
+        if mover.offer_draw(&game) && opponent.accept_draw(&game) {
+            break (GameResult::Draw, Termination::DrawAgreed);
+        }
+
+        if let (Some(clock), Some(times)) = (config.clock, clocks) {
+            mover.set_clock(&TimeControl {
+                wtime: Some(times[Color::White.index()]),
+                btime: Some(times[Color::Black.index()]),
+                winc: Some(clock.increment_ms),
+                binc: Some(clock.increment_ms),
+                movestogo: None,
+                movetime: None,
+            });
+        }
+        let start = Instant::now();
+        let m = mover.choose_move(&game);
+        if let (Some(clock), Some(times)) = (config.clock, clocks.as_mut()) {
+            let used = start.elapsed().as_millis() as u64;
+            let left = &mut times[color.index()];
+            if used > *left {
+                break (GameResult::Resigned(color), Termination::TimeForfeit);
+            }
+            *left = *left - used + clock.increment_ms;
+        }
+        let score = mover.last_score().map(|s| if color == Color::White { s } else { -s });
+
+        let irreversible = matches!(m.piece, Piece::Pawn(_)) || m.promoted_from_pawn || captured_piece(&m, &game.position).is_some();
+        if make_move_quiet(m, &mut game).is_err() {
+            break (GameResult::Resigned(color), Termination::IllegalMove);
+        }
+        record.moves.push(move_to_uci(&m));
+        scores.push(score);
+        if irreversible {
+            halfmove_clock = 0;
+            history.clear();
+        } else {
+            halfmove_clock += 1;
+        }
+        history.push(zobrist_key(&game.position, game.player_tracker()));
+    };
+
+    record.result = if result == GameResult::Stalemate { GameResult::Draw } else { result };
+    record.termination = termination;
+    record
+}
+
+/// Applies the tablebase, score and move count rules before a move.
+fn adjudicate(rules: &Adjudication, position: &Position, color: Color, ply: u32, scores: &[Option<i32>]) -> Option<(GameResult, Termination)> {
+    if let Some(probe) = &rules.tablebase
+        && let Some(wdl) = probe(position, color)
+    {
+        let result = match wdl.signum() {
+            1 => GameResult::Resigned(color.opponent()),
+            -1 => GameResult::Resigned(color),
+            _ => GameResult::Draw,
+        };
+        return Some((result, Termination::Tablebase));
+    }
+
+    // the last `moves` moves of both players all satisfy `condition`
+    let recent_all = |moves: usize, condition: &dyn Fn(i32) -> bool| {
+        moves > 0 && scores.len() >= 2 * moves && scores[scores.len() - 2 * moves..].iter().all(|s| s.is_some_and(condition))
+    };
+    if let Some(threshold) = rules.resign_score {
+        if recent_all(rules.resign_moves, &|s| s >= threshold) {
+            return Some((GameResult::Resigned(Color::Black), Termination::ScoreWin));
+        }
+        if recent_all(rules.resign_moves, &|s| s <= -threshold) {
+            return Some((GameResult::Resigned(Color::White), Termination::ScoreWin));
+        }
+    }
+    if let Some(threshold) = rules.draw_score
+        && ply >= rules.draw_min_ply
+        && recent_all(rules.draw_moves, &|s| s.abs() <= threshold)
+    {
+        return Some((GameResult::Draw, Termination::ScoreDraw));
+    }
+    if ply >= rules.max_plies {
+        return Some((GameResult::Draw, Termination::MoveLimit));
+    }
+    None
+}
+
+/// Expected score of a player `elo` points stronger than its opponent.
+pub fn expected_score(elo: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-elo / 400.0))
+}
+
+/// Elo difference that corresponds to an expected score (the inverse of [`expected_score`]).
+pub fn score_to_elo(score: f64) -> f64 {
+    -400.0 * (1.0 / score - 1.0).log10()
+}
+
+/// Results of a match from the first player's point of view.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MatchStats {
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+}
+
+impl MatchStats {
+    /// Counts a finished game.
+    ///
+    /// # Arguments
+    /// * `result` - The game result.
+    /// * `first_is_white` - Whether the first player had White.
+    pub fn add(&mut self, result: GameResult, first_is_white: bool) {
+        let first = if first_is_white { Color::White } else { Color::Black };
+        match result.winner() {
+            Some(winner) if winner == first => self.wins += 1,
+            Some(_) => self.losses += 1,
+            None => self.draws += 1,
+        }
+    }
+
+    pub fn games(&self) -> u32 {
+        self.wins + self.draws + self.losses
+    }
+
+    /// Average score per game (win = 1, draw = 0.5), or 0.5 before the first game.
+    pub fn score(&self) -> f64 {
+        if self.games() == 0 {
+            return 0.5;
+        }
+        (self.wins as f64 + 0.5 * self.draws as f64) / self.games() as f64
+    }
+
+    /// Variance of a single game's score.
+    pub fn variance(&self) -> f64 {
+        let n = self.games() as f64;
+        if n == 0.0 {
+            return 0.0;
+        }
+        let s = self.score();
+        (self.wins as f64 * (1.0 - s).powi(2) + self.draws as f64 * (0.5 - s).powi(2) + self.losses as f64 * s.powi(2)) / n
+    }
+
+    /// Elo difference of the first player (positive if it is stronger).
+    ///
+    /// Infinite if one player won every decisive and drawn game.
+    pub fn elo(&self) -> f64 {
+        score_to_elo(self.score())
+    }
+
+    /// Half width of the 95% confidence interval of [`MatchStats::elo`].
+    pub fn elo_error(&self) -> f64 {
+        let n = self.games() as f64;
+        if n == 0.0 || !self.elo().is_finite() {
+            return f64::INFINITY;
+        }
+        let margin = 1.959964 * (self.variance() / n).sqrt();
+        let low = score_to_elo((self.score() - margin).max(f64::MIN_POSITIVE));
+        let high = score_to_elo((self.score() + margin).min(1.0 - f64::EPSILON));
+        (high - low) / 2.0
+    }
+}
+
+/// State of a sequential probability ratio test.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SprtStatus {
+    /// More games are needed.
+    Continue,
+    /// H0 accepted: the first player is not `elo1` stronger.
+    Fail,
+    /// H1 accepted: the first player is at least `elo1` stronger.
+    Pass,
+}
+
+/// Sequential probability ratio test of H0 "the Elo difference is `elo0`"
+/// against H1 "it is `elo1`", with error rates `alpha` (false pass) and `beta` (false fail).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sprt {
+    pub elo0: f64,
+    pub elo1: f64,
+    pub alpha: f64,
+    pub beta: f64,
+}
+
+impl Sprt {
+    /// Smallest per-game score variance used by [`Sprt::llr`].
+    pub const MIN_VARIANCE: f64 = 0.01;
+
+    /// Creates a test with the usual error rates of 5%.
+    pub fn new(elo0: f64, elo1: f64) -> Self {
+        Sprt { elo0, elo1, alpha: 0.05, beta: 0.05 }
+    }
+
+    /// Lower and upper bound of the log-likelihood ratio.
+    pub fn bounds(&self) -> (f64, f64) {
+        ((self.beta / (1.0 - self.alpha)).ln(), ((1.0 - self.beta) / self.alpha).ln())
+    }
+
+    /// Log-likelihood ratio of H1 against H0 for the results so far,
+    /// using the normal approximation of the game score distribution.
+    ///
+    /// A sample in which every game ended the same way has no spread, so the
+    /// variance is floored at [`Sprt::MIN_VARIANCE`]: a sweep then ends the
+    /// test after a handful of games instead of running on to the game limit.
+    pub fn llr(&self, stats: &MatchStats) -> f64 {
+        if stats.games() == 0 {
+            return 0.0;
+        }
+        let variance = stats.variance().max(Self::MIN_VARIANCE);
+        let s0 = expected_score(self.elo0);
+        let s1 = expected_score(self.elo1);
+        stats.games() as f64 * (s1 - s0) * (2.0 * stats.score() - s0 - s1) / (2.0 * variance)
+    }
+
+    pub fn status(&self, stats: &MatchStats) -> SprtStatus {
+        let llr = self.llr(stats);
+        let (lower, upper) = self.bounds();
+        if llr >= upper {
+            SprtStatus::Pass
+        } else if llr <= lower {
+            SprtStatus::Fail
+        } else {
+            SprtStatus::Continue
+        }
+    }
+}
+
+/// Creates a fresh player for one match thread.
+pub type PlayerFactory<'a> = dyn Fn() -> Result<Box<dyn Player>, String> + Sync + 'a;
+
+/// Plays a match between two players.
+///
+/// Games are played in color-swapped pairs: game `2i` has the first player as
+/// White and game `2i + 1` the second, both from opening `i % openings.len()`.
+/// Each of the [`MatchConfig::concurrency`] threads creates its own players.
+/// With [`MatchConfig::sprt`] set, no new games are started once the test has a result.
+///
+/// # Arguments
+/// * `config` - Match settings.
+/// * `first`, `second` - Player factories.
+/// * `progress` - Called after every game with the results so far and the game.
+///
+/// # Returns
+/// The results from the first player's point of view.
+///
+/// # Errors
+/// Returns `Err(String)` if a player cannot be created.
+pub fn run_match<F>(config: &MatchConfig, first: &PlayerFactory<'_>, second: &PlayerFactory<'_>, progress: F) -> Result<MatchStats, String>
+where
+    F: FnMut(&MatchStats, &GameRecord) + Send,
+{
+    if config.openings.is_empty() {
+        return Err("No openings".to_string());
+    }
+    let games = config.games.div_ceil(2) * 2;
+    let next_game = AtomicUsize::new(0);
+    let finished = AtomicBool::new(false);
+    let state = Mutex::new((MatchStats::default(), progress));
+
+    let worker = || -> Result<(), String> {
+        let mut first = first()?;
+        let mut second = second()?;
+        while !finished.load(Ordering::Relaxed) {
+            let index = next_game.fetch_add(1, Ordering::Relaxed);
+            if index >= games {
+                break;
+            }
+            let opening = &config.openings[(index / 2) % config.openings.len()];
+            let first_is_white = index.is_multiple_of(2);
+            let mut record = if first_is_white {
+                play_match_game(first.as_mut(), second.as_mut(), opening, config)
+            } else {
+                play_match_game(second.as_mut(), first.as_mut(), opening, config)
+            };
+            record.index = index;
+
+            let mut state = state.lock().expect("match state lock");
+            let (stats, progress) = &mut *state;
+            stats.add(record.result, first_is_white);
+            progress(stats, &record);
+            if config.sprt.is_some_and(|sprt| sprt.status(stats) != SprtStatus::Continue) {
+                finished.store(true, Ordering::Relaxed);
+            }
+        }
+        Ok(())
+    };
+
+    thread::scope(|scope| {
+        let handles: Vec<_> = (0..config.concurrency.max(1)).map(|_| scope.spawn(worker)).collect();
+        for handle in handles {
+            handle.join().expect("match thread panicked")?;
+        }
+        Ok::<(), String>(())
+    })?;
+    let (stats, _) = state.into_inner().expect("match state lock");
+    Ok(stats)
+}
+
+/// A UCI engine running as a child process.
+///
+/// Every move is searched from the current position as FEN, within the limits
+/// given at start (or the match clock). If the engine dies or answers with an
+/// illegal move, [`Player::choose_move`] returns the null move `a1a1`, which the
+/// game rejects as illegal, so the engine loses the game.
+pub struct UciPlayer {
+    name: String,
+    process: Child,
+    input: ChildStdin,
+    output: BufReader<ChildStdout>,
+    pub limits: SearchLimits,
+    last_score: Option<i32>,
+}
+
+impl UciPlayer {
+    /// Starts an engine and sets its options.
+    ///
+    /// # Arguments
+    /// * `command` - The executable, followed by its arguments (split on whitespace).
+    /// * `options` - `setoption` name/value pairs.
+    /// * `limits` - Limits of every search.
+    ///
+    /// # Errors
+    /// Returns `Err(String)` if the process cannot be started or does not complete the handshake.
+    pub fn start(command: &str, options: &[(String, String)], limits: SearchLimits) -> Result<Self, String> {
+        let mut parts = command.split_whitespace();
+        let program = parts.next().ok_or("Empty engine command")?;
+        let mut process = Command::new(program)
+            .args(parts)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| format!("Cannot start {}: {}", program, e))?;
+        let input = process.stdin.take().ok_or("No engine stdin")?;
+        let output = BufReader::new(process.stdout.take().ok_or("No engine stdout")?);
+        let mut player = UciPlayer { name: program.to_string(), process, input, output, limits, last_score: None };
+
+        player.send("uci")?;
+        loop {
+            let line = player.read_line()?;
+            if let Some(name) = line.strip_prefix("id name ") {
+                player.name = name.trim().to_string();
+            } else if line.trim() == "uciok" {
+                break;
+            }
+        }
+        for (name, value) in options {
+            player.send(&format!("setoption name {} value {}", name, value))?;
+        }
+        player.wait_ready()?;
+        Ok(player)
+    }
+
+    fn send(&mut self, line: &str) -> Result<(), String> {
+        writeln!(self.input, "{}", line).and_then(|_| self.input.flush()).map_err(|e| format!("{}: {}", self.name, e))
+    }
+
+    fn read_line(&mut self) -> Result<String, String> {
+        let mut line = String::new();
+        match self.output.read_line(&mut line) {
+            Ok(0) => Err(format!("{} closed its output", self.name)),
+            Ok(_) => Ok(line),
+            Err(e) => Err(format!("{}: {}", self.name, e)),
+        }
+    }
+
+    fn wait_ready(&mut self) -> Result<(), String> {
+        self.send("isready")?;
+        while self.read_line()?.trim() != "readyok" {}
+        Ok(())
+    }
+
+    /// Builds the `go` command for the current limits.
+    fn go_command(&self) -> String {
+        let mut command = "go".to_string();
+        let limits = &self.limits;
+        let fields = [
+            ("depth", limits.depth.map(u64::from)),
+            ("nodes", limits.nodes),
+            ("wtime", limits.time.wtime),
+            ("btime", limits.time.btime),
+            ("winc", limits.time.winc),
+            ("binc", limits.time.binc),
+            ("movestogo", limits.time.movestogo),
+            ("movetime", limits.time.movetime),
+        ];
+        for (name, value) in fields {
+            if let Some(value) = value {
+                command.push_str(&format!(" {} {}", name, value));
+            }
+        }
+        command
+    }
+
+    /// Sends the position, searches it and returns the engine's `bestmove`.
+    fn search(&mut self, game: &Game) -> Result<String, String> {
+        self.send(&format!("position fen {}", to_fen(&game.position, game.player_tracker())))?;
+        let go = self.go_command();
+        self.send(&go)?;
+        loop {
+            let line = self.read_line()?;
+            let tokens: Vec<&str> = line.split_whitespace().collect();
+            match tokens.first() {
+                Some(&"bestmove") => return tokens.get(1).map(|m| m.to_string()).ok_or("bestmove without move".to_string()),
+                Some(&"info") => {
+                    if let Some(score) = parse_info_score(&tokens) {
+                        self.last_score = Some(score);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Reads the score of an `info` line: `score cp <n>` or `score mate <moves>`.
+fn parse_info_score(tokens: &[&str]) -> Option<i32> {
+    let at = tokens.iter().position(|&t| t == "score")?;
+    let value: i32 = tokens.get(at + 2)?.parse().ok()?;
+    match *tokens.get(at + 1)? {
+        "cp" => Some(value),
+        "mate" if value > 0 => Some(MATE_SCORE - (2 * value - 1)),
+        "mate" => Some(-MATE_SCORE + 2 * -value),
+        _ => None,
+    }
+}
+
+impl Player for UciPlayer {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn choose_move(&mut self, game: &Game) -> Move {
+        let color = game.player_tracker();
+        let null_move = Move { from: 0, to: 0, piece: Piece::King(color), promoted_from_pawn: false, drop: false, duck: None };
+        match self.search(game) {
+            Ok(text) => move_from_uci(&text, color, &game.position).unwrap_or(null_move),
+            Err(_) => null_move,
+        }
+    }
+
+    fn new_game(&mut self) {
+        self.last_score = None;
+        let _ = self.send("ucinewgame").and_then(|_| self.wait_ready());
+    }
+
+    fn last_score(&self) -> Option<i32> {
+        self.last_score
+    }
+
+    fn set_clock(&mut self, time: &TimeControl) {
+        self.limits.time = *time;
+    }
+}
+
+impl Drop for UciPlayer {
+    /// Asks the engine to quit, and kills it if it is still running a second later.
+    fn drop(&mut self) {
+        let _ = self.send("quit");
+        for _ in 0..20 {
+            if let Ok(Some(_)) = self.process.try_wait() {
+                return;
+            }
+            thread::sleep(Duration::from_millis(50));
+        }
+        let _ = self.process.kill();
+        let _ = self.process.wait();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stats(wins: u32, draws: u32, losses: u32) -> MatchStats {
+        MatchStats { wins, draws, losses }
+    }
+
+    #[test]
+    fn sprt_bounds() {
+        let (lower, upper) = Sprt::new(0.0, 5.0).bounds();
+        assert!((lower + 2.944).abs() < 1e-3);
+        assert!((upper - 2.944).abs() < 1e-3);
+
+        let sprt = Sprt { alpha: 0.01, ..Sprt::new(0.0, 5.0) };
+        let (lower, upper) = sprt.bounds();
+        assert!((lower - (0.05f64 / 0.99).ln()).abs() < 1e-9);
+        assert!((upper - 95f64.ln()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn llr_sign_and_monotonicity() {
+        let sprt = Sprt::new(0.0, 10.0);
+        assert_eq!(sprt.llr(&stats(0, 0, 0)), 0.0);
+        assert!(sprt.llr(&stats(60, 20, 40)) > 0.0);
+        assert!(sprt.llr(&stats(40, 20, 60)) < 0.0);
+
+        let mut previous = f64::NEG_INFINITY;
+        for wins in 40..80 {
+            let llr = sprt.llr(&stats(wins, 20, 50));
+            assert!(llr > previous);
+            previous = llr;
+        }
+    }
+
+    #[test]
+    fn sweeps_end_the_test() {
+        let sprt = Sprt::new(0.0, 5.0);
+        assert_eq!(sprt.status(&stats(1, 0, 0)), SprtStatus::Continue);
+        assert_eq!(sprt.status(&stats(20, 0, 0)), SprtStatus::Pass);
+        assert_eq!(sprt.status(&stats(0, 0, 20)), SprtStatus::Fail);
+        // all draws: the first player is not 5 Elo stronger
+        assert!(sprt.llr(&stats(0, 100, 0)) < 0.0);
+        assert_eq!(sprt.status(&stats(0, 2000, 0)), SprtStatus::Fail);
+    }
+
+    #[test]
+    fn elo_and_error() {
+        assert_eq!(stats(10, 10, 10).elo(), 0.0);
+        assert!((stats(3, 0, 1).elo() - 190.85).abs() < 0.01);
+        assert!((stats(1, 0, 3).elo() + 190.85).abs() < 0.01);
+        assert_eq!(stats(5, 0, 0).elo(), f64::INFINITY);
+
+        let error = stats(10, 10, 10).elo_error();
+        assert!((error - 104.6).abs() < 0.5, "{}", error);
+        assert!(stats(100, 100, 100).elo_error() < error);
+        assert_eq!(stats(0, 0, 0).elo_error(), f64::INFINITY);
+        assert_eq!(stats(5, 0, 0).elo_error(), f64::INFINITY);
+    }
+
+    #[test]
+    fn opening_files() {
+        let text = "# openings\n\n\
+            rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1\n\
+            rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - bm Nf3; id \"open\";\n";
+        let openings = parse_openings(text).unwrap();
+        assert_eq!(
+            openings,
+            [
+                "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1",
+                "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 1",
+            ]
+        );
+
+        let error = parse_openings("8/8/8/8/8/8/8/4K2k w - -\nnot a fen w - -\n").unwrap_err();
+        assert!(error.starts_with("line 2:"), "{}", error);
+        assert!(parse_openings("# nothing here\n").is_err());
+    }
+}
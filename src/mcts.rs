@@ -0,0 +1,491 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Instant;
+
+use crate::engine::{Engine, SearchLimits};
+use crate::eval::evaluate;
+use crate::game::GameResult;
+use crate::make_move::{
+    apply_move_unchecked, is_insufficient_material, legal_moves, no_moves_result, update_castling_rights, variant_result,
+};
+use crate::moves::Move;
+use crate::piece::Color;
+use crate::position::Position;
+use crate::rng::Rng;
+use crate::search::{IterationCallback, IterationInfo, MATE_SCORE, PvLine, SearchResult};
+use crate::time_manager::{DEFAULT_MOVE_OVERHEAD_MS, TimeManager};
+
+// see: https://www.chessprogramming.org/Monte-Carlo_Tree_Search
+
+/// Default UCT exploration constant (about sqrt(2)).
+pub const DEFAULT_EXPLORATION: f64 = 1.41;
+/// Playouts per ply of a depth limit, as MCTS has no search depth of its own.
+pub const PLAYOUTS_PER_DEPTH: u64 = 1000;
+/// Playouts between two progress reports.
+const REPORT_INTERVAL: u64 = 1000;
+/// Scale of the logistic mapping between centipawns and winning chances.
+const SCORE_SCALE: f64 = 400.0;
+
+/// How a newly expanded node is evaluated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Playout {
+    /// Play uniformly random moves for up to this many plies, then evaluate statically.
+    Random(u32),
+    /// Play for up to this many plies, each time picking the move with the best
+    /// static evaluation (with a little randomness), then evaluate statically.
+    EvalGuided(u32),
+}
+
+/// One position in the search tree.
+#[derive(Debug, Clone)]
+struct Node {
+    position: Position,
+    /// The side to move.
+    color: Color,
+    /// The move that led here from the parent.
+    m: Option<Move>,
+    parent: Option<usize>,
+    children: Vec<usize>,
+    /// Legal moves that have no child node yet.
+    untried: Vec<Move>,
+    visits: u32,
+    /// Sum of playout results from the point of view of the side that moved into this node.
+    value: f64,
+    /// Result if the game is over here, from the point of view of the side to move.
+    terminal: Option<f64>,
+}
+
+/// The value of a finished game for `color`: `1.0` win, `0.5` draw, `0.0` loss.
+fn result_value(result: GameResult, color: Color) -> f64 {
+    match result.winner() {
+        Some(winner) if winner == color => 1.0,
+        Some(_) => 0.0,
+        None => 0.5,
+    }
+}
+
+impl Node {
+    fn new(position: Position, color: Color, m: Option<Move>, parent: Option<usize>) -> Self {
+        let untried = legal_moves(color, &position);
+        let terminal = if let Some(result) = variant_result(color, &position) {
+            Some(result_value(result, color))
+        } else if untried.is_empty() {
+            Some(result_value(no_moves_result(color, &position), color))
+        } else if is_insufficient_material(&position) {
+            Some(0.5)
+        } else {
+            None
+        };
+        Node { position, color, m, parent, children: Vec::new(), untried, visits: 0, value: 0.0, terminal }
+    }
+
+    /// Average result for the side that moved into this node.
+    fn mean(&self) -> f64 {
+        if self.visits == 0 { 0.5 } else { self.value / self.visits as f64 }
+    }
+}
+
+/// Monte Carlo Tree Search engine.
+///
+/// Each playout walks down the tree choosing children by UCT (upper confidence
+/// bound applied to trees), expands one new node, evaluates it with a
+/// [`Playout`] and adds the result to every node on the way back up. The move
+/// played is the root child with the most visits.
+///
+/// The tree is kept between searches: if the new position was reached from the
+/// previous root in one or two plies, that subtree becomes the new root.
+pub struct MctsSearcher {
+    /// Playouts run by the last search.
+    pub nodes: u64,
+    /// UCT exploration constant; larger values explore more.
+    pub exploration: f64,
+    pub playout: Playout,
+    /// Number of root moves to report as lines (MultiPV).
+    pub multi_pv: usize,
+    /// Set to `true` (e.g. from another thread) to stop the running search as soon as possible.
+    pub stop: Arc<AtomicBool>,
+    /// Time reserved per move for communication lag in timed searches, in milliseconds.
+    pub move_overhead_ms: u64,
+    /// Called every [`REPORT_INTERVAL`] playouts and at the end of the search.
+    pub on_iteration: Option<IterationCallback>,
+    tree: Vec<Node>,
+    rng: Rng,
+    lines: Vec<PvLine>,
+}
+
+impl Default for MctsSearcher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MctsSearcher {
+    /// Creates a searcher with eval-guided playouts of 8 plies and a clock-seeded random generator.
+    pub fn new() -> Self {
+        MctsSearcher {
+            nodes: 0,
+            exploration: DEFAULT_EXPLORATION,
+            playout: Playout::EvalGuided(8),
+            multi_pv: 1,
+            stop: Arc::new(AtomicBool::new(false)),
+            move_overhead_ms: DEFAULT_MOVE_OVERHEAD_MS,
+            on_iteration: None,
+            tree: Vec::new(),
+            rng: Rng::from_time(),
+            lines: Vec::new(),
+        }
+    }
+
+    /// Seeds the random generator, making searches with a playout limit reproducible.
+    pub fn seed(&mut self, seed: u64) {
+        self.rng = Rng::new(seed);
+    }
+
+    /// Number of nodes in the tree kept for the next search.
+    pub fn tree_size(&self) -> usize {
+        self.tree.len()
+    }
+
+    /// Runs `playouts` playouts from `position` (or until stopped) and returns the best move.
+    pub fn search(&mut self, position: &Position, color: Color, playouts: u64) -> SearchResult {
+        self.run(position, color, Some(playouts), None)
+    }
+
+    fn run(&mut self, position: &Position, color: Color, playouts: Option<u64>, time: Option<TimeManager>) -> SearchResult {
+        let start = Instant::now();
+        self.nodes = 0;
+        self.reuse_tree(position, color);
+
+        let root = &self.tree[0];
+        if let Some(result) = root.terminal {
+            self.lines.clear();
+            let score = if result == 0.0 { -MATE_SCORE } else { 0 };
+            return SearchResult { best_move: None, score, nodes: 0 };
+        }
+        // a single legal move needs no search when the clock is running
+        if time.is_some() && root.untried.len() + root.children.len() == 1 {
+            let m = root.untried.first().copied().unwrap_or_else(|| self.tree[root.children[0]].m.expect("child has a move"));
+            self.lines = vec![PvLine { best_move: m, score: 0, pv: vec![m] }];
+            return SearchResult { best_move: Some(m), score: 0, nodes: 0 };
+        }
+
+        loop {
+            if playouts.is_some_and(|limit| self.nodes >= limit)
+                || self.stop.load(Ordering::Relaxed)
+                || time.as_ref().is_some_and(|t| self.nodes.is_multiple_of(64) && t.soft_limit_reached())
+            {
+                break;
+            }
+            self.playout_once();
+            self.nodes += 1;
+            if self.nodes.is_multiple_of(REPORT_INTERVAL) {
+                self.report(start);
+            }
+        }
+        self.stop.store(false, Ordering::Relaxed);
+        self.report(start);
+
+        if self.lines.is_empty() {
+            // stopped before the first playout
+            let m = self.tree[0].untried[0];
+            self.lines = vec![PvLine { best_move: m, score: 0, pv: vec![m] }];
+        }
+        let best = &self.lines[0];
+        SearchResult { best_move: Some(best.best_move), score: best.score, nodes: self.nodes }
+    }
+
+    /// Makes the node for `position` the root, keeping its subtree if the position
+    /// is the old root or one of its children or grandchildren.
+    fn reuse_tree(&mut self, position: &Position, color: Color) {
+        let matches = |node: &Node| node.position == *position && node.color == color;
+        let mut found = None;
+        if let Some(root) = self.tree.first() {
+            if matches(root) {
+                found = Some(0);
+            }
+            for &child in &root.children {
+                if found.is_some() {
+                    break;
+                }
+                if matches(&self.tree[child]) {
+                    found = Some(child);
+                }
+                found = found.or_else(|| self.tree[child].children.iter().copied().find(|&g| matches(&self.tree[g])));
+            }
+        }
+
+        match found {
+            Some(0) => {}
+            Some(index) => {
+                // copy the subtree into a fresh arena, breadth first
+                let mut tree: Vec<Node> = Vec::new();
+                let mut queue = vec![(index, None)];
+                let mut next = 0;
+                while next < queue.len() {
+                    let (old, parent) = queue[next];
+                    next += 1;
+                    let new_index = tree.len();
+                    let mut node = self.tree[old].clone();
+                    node.parent = parent;
+                    let children = std::mem::take(&mut node.children);
+                    if let Some(p) = parent {
+                        tree[p].children.push(new_index);
+                    }
+                    tree.push(node);
+                    queue.extend(children.into_iter().map(|c| (c, Some(new_index))));
+                }
+                self.tree = tree;
+            }
+            None => self.tree = vec![Node::new(*position, color, None, None)],
+        }
+    }
+
+    /// Selects, expands, evaluates and backs up one playout.
+    fn playout_once(&mut self) {
+        // selection
+        let mut index = 0;
+        while self.tree[index].terminal.is_none() && self.tree[index].untried.is_empty() {
+            index = self.select_child(index);
+        }
+
+        // expansion
+        let node = &mut self.tree[index];
+        let result_for_mover = if let Some(result) = node.terminal {
+            1.0 - result
+        } else {
+            let pick = self.rng.below(node.untried.len());
+            let m = node.untried.swap_remove(pick);
+            let mut position = node.position;
+            let color = node.color.opponent();
+            apply_move_unchecked(m, &mut position);
+            update_castling_rights(m, &mut position);
+            let child = Node::new(position, color, Some(m), Some(index));
+            let child_index = self.tree.len();
+            self.tree[index].children.push(child_index);
+            let result = match child.terminal {
+                Some(result) => result,
+                None => self.simulate(&child.position, color),
+            };
+            self.tree.push(child);
+            index = child_index;
+            1.0 - result
+        };
+
+        // backpropagation: each node stores results for the side that moved into it
+        let mut result = result_for_mover;
+        let mut current = Some(index);
+        while let Some(i) = current {
+            let node = &mut self.tree[i];
+            node.visits += 1;
+            node.value += result;
+            result = 1.0 - result;
+            current = node.parent;
+        }
+    }
+
+    /// Returns the child of `index` with the highest UCT value.
+    fn select_child(&self, index: usize) -> usize {
+        let node = &self.tree[index];
+        let log_visits = (node.visits.max(1) as f64).ln();
+        let uct = |child: &Node| {
+            if child.visits == 0 {
+                return f64::INFINITY;
+            }
+            child.mean() + self.exploration * (log_visits / child.visits as f64).sqrt()
+        };
+        node.children
+            .iter()
+            .copied()
+            .max_by(|&a, &b| uct(&self.tree[a]).total_cmp(&uct(&self.tree[b])))
+            .expect("expanded non-terminal node has children")
+    }
+
+    /// Plays out a position according to [`MctsSearcher::playout`].
+    ///
+    /// # Returns
+    /// The expected result for `color`, the side to move: `1.0` win, `0.5` draw, `0.0` loss.
+    fn simulate(&mut self, position: &Position, color: Color) -> f64 {
+        let (max_plies, guided) = match self.playout {
+            Playout::Random(plies) => (plies, false),
+            Playout::EvalGuided(plies) => (plies, true),
+        };
+        let mut position = *position;
+        let mut side = color;
+        for _ in 0..max_plies {
+            if let Some(result) = variant_result(side, &position) {
+                let result = result_value(result, side);
+                return if side == color { result } else { 1.0 - result };
+            }
+            let moves = legal_moves(side, &position);
+            if moves.is_empty() {
+                let result = result_value(no_moves_result(side, &position), side);
+                return if side == color { result } else { 1.0 - result };
+            }
+            let m = if guided && !self.rng.chance(0.1) {
+                *moves
+                    .iter()
+                    .max_by_key(|&&m| {
+                        let mut child = position;
+                        apply_move_unchecked(m, &mut child);
+                        -evaluate(side.opponent(), &child)
+                    })
+                    .expect("moves is not empty")
+            } else {
+                moves[self.rng.below(moves.len())]
+            };
+            apply_move_unchecked(m, &mut position);
+            update_castling_rights(m, &mut position);
+            side = side.opponent();
+        }
+        win_probability(evaluate(color, &position))
+    }
+
+    /// Builds the ranked lines from the root's children and calls `on_iteration`.
+    fn report(&mut self, start: Instant) {
+        let mut children = self.tree[0].children.clone();
+        children.sort_by_key(|&c| std::cmp::Reverse(self.tree[c].visits));
+        self.lines = children
+            .iter()
+            .take(self.multi_pv.max(1))
+            .map(|&c| {
+                let node = &self.tree[c];
+                let m = node.m.expect("child has a move");
+                // moving into checkmate is a certain win, not just a likely one
+                let score = if node.terminal == Some(0.0) { MATE_SCORE - 1 } else { centipawns(node.mean()) };
+                PvLine { best_move: m, score, pv: self.principal_variation(c) }
+            })
+            .collect();
+        if self.lines.is_empty() {
+            return;
+        }
+        if let Some(callback) = &mut self.on_iteration {
+            let depth = self.lines[0].pv.len() as u32;
+            callback(&IterationInfo { depth, lines: &self.lines, nodes: self.nodes, elapsed: start.elapsed() });
+        }
+    }
+
+    /// Follows the most visited children from `index`.
+    fn principal_variation(&self, mut index: usize) -> Vec<Move> {
+        let mut pv = Vec::new();
+        loop {
+            let node = &self.tree[index];
+            pv.extend(node.m);
+            match node.children.iter().copied().max_by_key(|&c| self.tree[c].visits) {
+                Some(child) if self.tree[child].visits > 0 => index = child,
+                _ => return pv,
+            }
+        }
+    }
+}
+
+/// Maps a centipawn score to winning chances.
+fn win_probability(score: i32) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-score as f64 / SCORE_SCALE))
+}
+
+/// Maps winning chances back to a centipawn score.
+fn centipawns(probability: f64) -> i32 {
+    let p = probability.clamp(0.001, 0.999);
+    (SCORE_SCALE * (p / (1.0 - p)).log10()).round() as i32
+}
+
+impl Engine for MctsSearcher {
+    /// Searches with the clock's soft limit if `limits.time` has time for `color`.
+    /// `limits.nodes` caps the playouts, and a depth limit allows
+    /// [`PLAYOUTS_PER_DEPTH`] playouts per ply. Without limits it runs until stopped.
+    fn go(&mut self, position: &Position, color: Color, limits: &SearchLimits) -> SearchResult {
+        let time = TimeManager::new(&limits.time, color, self.move_overhead_ms);
+        let depth_playouts = limits.depth.map(|d| d.max(1) as u64 * PLAYOUTS_PER_DEPTH);
+        let playouts = match (limits.nodes, depth_playouts) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+        self.run(position, color, playouts, time)
+    }
+
+    fn lines(&self) -> &[PvLine] {
+        &self.lines
+    }
+
+    fn new_game(&mut self) {
+        self.tree.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fen::{START_FEN, parse_fen};
+    use crate::make_move::is_legal;
+    use crate::search::play;
+    use crate::uci::{move_from_uci, move_to_uci};
+
+    fn seeded() -> MctsSearcher {
+        let mut searcher = MctsSearcher::new();
+        searcher.seed(7);
+        searcher
+    }
+
+    #[test]
+    fn search_returns_a_legal_move() {
+        let (position, color) = parse_fen(START_FEN).unwrap();
+        let mut searcher = seeded();
+        let result = searcher.search(&position, color, 300);
+        assert!(is_legal(result.best_move.unwrap(), color, &position));
+        assert_eq!(result.nodes, 300);
+        assert_eq!(searcher.tree[0].visits, 300);
+    }
+
+    #[test]
+    fn finds_mate_in_one() {
+        let (position, color) = parse_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+        let result = seeded().search(&position, color, 2000);
+        assert_eq!(move_to_uci(&result.best_move.unwrap()), "a1a8");
+        // the mated side has nothing to search
+        let mated = play(result.best_move.unwrap(), &position);
+        assert_eq!(seeded().search(&mated, color.opponent(), 100).best_move, None);
+    }
+
+    #[test]
+    fn reuse_tree_keeps_the_subtree() {
+        let (position, color) = parse_fen(START_FEN).unwrap();
+        let mut searcher = seeded();
+        searcher.search(&position, color, 500);
+        let visits_after = |searcher: &MctsSearcher, moves: &[&str]| {
+            let mut index = 0;
+            for text in moves {
+                let m = move_from_uci(text, searcher.tree[index].color, &searcher.tree[index].position).unwrap();
+                index = *searcher.tree[index]
+                    .children
+                    .iter()
+                    .find(|&&c| searcher.tree[c].m.is_some_and(|cm| cm.is_same(&m)))
+                    .unwrap();
+            }
+            searcher.tree[index].visits
+        };
+        let best = searcher.lines[0].best_move;
+        let reply = searcher.lines[0].pv[1];
+        let (best_text, reply_text) = (move_to_uci(&best), move_to_uci(&reply));
+        let child_visits = visits_after(&searcher, &[&best_text]);
+        let grandchild_visits = visits_after(&searcher, &[&best_text, &reply_text]);
+
+        // one ply on: the child becomes the root with its visits
+        let child = play(best, &position);
+        searcher.search(&child, color.opponent(), 0);
+        assert_eq!(searcher.tree[0].visits, child_visits);
+        assert_eq!(visits_after(&searcher, &[&reply_text]), grandchild_visits);
+
+        // the grandchild of the new root
+        let reply_move = move_from_uci(&reply_text, color.opponent(), &child).unwrap();
+        let grandchild = play(reply_move, &child);
+        let size = searcher.tree_size();
+        searcher.search(&grandchild, color, 0);
+        assert_eq!(searcher.tree[0].visits, grandchild_visits);
+        assert!(searcher.tree_size() < size);
+
+        // an unrelated position starts over
+        let (other, other_color) = parse_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+        searcher.search(&other, other_color, 0);
+        assert_eq!(searcher.tree_size(), 1);
+    }
+}
@@ -0,0 +1,327 @@
+use crate::eval::{PIECE_VALUES, piece_value};
+use crate::make_move::{capture_moves, is_legal, quiet_moves};
+use crate::moves::{Move, captured_piece};
+use crate::piece::{Color, Piece};
+use crate::position::Position;
+use crate::see::see;
+
+// see: https://www.chessprogramming.org/Move_Ordering
+
+/// Maximum search depth in plies that per-ply tables (such as killers) can hold.
+pub const MAX_PLY: usize = 128;
+/// History scores are kept within `-MAX_HISTORY..=MAX_HISTORY`.
+pub const MAX_HISTORY: i32 = 16_384;
+
+/// Scores a capture by *Most Valuable Victim - Least Valuable Attacker*.
+///
+/// Higher is better: taking a queen with a pawn scores highest, taking a pawn with a queen lowest.
+/// Promotions add the value gained by promoting.
+pub fn mvv_lva(m: &Move, position: &Position) -> i32 {
+    let victim = captured_piece(m, position).map_or(0, piece_value);
+    let attacker = if m.promoted_from_pawn { PIECE_VALUES[0] } else { piece_value(m.piece) };
+    let promotion = if m.promoted_from_pawn { piece_value(m.piece) } else { 0 };
+    victim * 10 + promotion - attacker / 10
+}
+
+/// Ordering heuristics learned during a search.
+///
+/// - **Killer moves**: two quiet moves per ply that recently caused a beta cutoff.
+///   Sibling nodes often share the same refutation.
+/// - **History table**: how often a quiet move (by side, piece and target square)
+///   caused cutoffs anywhere in the tree.
+/// - **Counter moves**: the quiet move that last refuted a given previous move
+///   (by side, piece and target square of the previous move).
+pub struct MoveOrdering {
+    pub killers: [[Option<Move>; 2]; MAX_PLY],
+    pub history: [[[i32; 64]; 6]; 2],
+    pub counter_moves: [[[Option<Move>; 64]; 6]; 2],
+}
+
+impl Default for MoveOrdering {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MoveOrdering {
+    pub fn new() -> Self {
+        MoveOrdering {
+            killers: [[None; 2]; MAX_PLY],
+            history: [[[0; 64]; 6]; 2],
+            counter_moves: [[[None; 64]; 6]; 2],
+        }
+    }
+
+    /// Resets all tables.
+    pub fn clear(&mut self) {
+        *self = Self::new();
+    }
+
+    /// Returns the killer moves stored for `ply`.
+    pub fn killers(&self, ply: usize) -> [Option<Move>; 2] {
+        self.killers.get(ply).copied().unwrap_or([None; 2])
+    }
+
+    /// Records a quiet move that caused a beta cutoff at `ply`.
+    pub fn store_killer(&mut self, ply: usize, m: Move) {
+        let Some(slot) = self.killers.get_mut(ply) else {
+            return;
+        };
+        if slot[0].is_some_and(|k| k.is_same(&m)) {
+            return;
+        }
+        slot[1] = slot[0];
+        slot[0] = Some(m);
+    }
+
+    /// Returns the history score of a quiet move.
+    pub fn history_score(&self, m: &Move) -> i32 {
+        self.history[m.piece.color().index()][m.piece.index()][m.to as usize]
+    }
+
+    /// Adds `bonus` (which may be negative) to the history score of `m`.
+    ///
+    /// Scores saturate towards [`MAX_HISTORY`] instead of growing without bound,
+    /// so that recent results keep influencing the ordering.
+    pub fn update_history(&mut self, m: &Move, bonus: i32) {
+        let bonus = bonus.clamp(-MAX_HISTORY, MAX_HISTORY);
+        let entry = &mut self.history[m.piece.color().index()][m.piece.index()][m.to as usize];
+        *entry += bonus - *entry * bonus.abs() / MAX_HISTORY;
+    }
+
+    /// Returns the stored reply to `previous`, if any.
+    pub fn counter_move(&self, previous: Option<Move>) -> Option<Move> {
+        let p = previous?;
+        self.counter_moves[p.piece.color().index()][p.piece.index()][p.to as usize]
+    }
+
+    /// Records `m` as the refutation of `previous`.
+    pub fn store_counter_move(&mut self, previous: Option<Move>, m: Move) {
+        if let Some(p) = previous {
+            self.counter_moves[p.piece.color().index()][p.piece.index()][p.to as usize] = Some(m);
+        }
+    }
+
+    /// Updates killers, history and counter moves after quiet move `m` caused a beta cutoff.
+    ///
+    /// # Arguments
+    /// * `m` - The quiet move that failed high.
+    /// * `tried_quiets` - Quiet moves searched before `m` at this node; their history is lowered.
+    /// * `previous` - The move that led to this node.
+    /// * `depth` - Remaining depth of the node.
+    /// * `ply` - Distance of the node from the root.
+    pub fn record_cutoff(&mut self, m: Move, tried_quiets: &[Move], previous: Option<Move>, depth: u32, ply: usize) {
+        let bonus = (depth * depth) as i32;
+        self.store_killer(ply, m);
+        self.store_counter_move(previous, m);
+        self.update_history(&m, bonus);
+        for q in tried_quiets {
+            self.update_history(q, -bonus);
+        }
+    }
+}
+
+/// Stages of a [`MovePicker`], in the order moves are returned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    HashMove,
+    GenerateCaptures,
+    GoodCaptures,
+    Killers,
+    CounterMove,
+    GenerateQuiets,
+    Quiets,
+    BadCaptures,
+    Done,
+}
+
+/// Returns legal moves one at a time, best-looking first, generating lazily.
+///
+/// Moves come out in this order:
+/// 1. the hash move (from the transposition table),
+/// 2. captures and queen promotions that do not lose material by [`see`], by [`mvv_lva`],
+/// 3. the killer moves of this ply,
+/// 4. the counter move to the previous move,
+/// 5. the remaining quiet moves, by history score,
+/// 6. losing captures and underpromotions.
+///
+/// Quiet moves are only generated once the earlier stages are exhausted, so a
+/// node that is refuted by a capture never pays for quiet move generation.
+pub struct MovePicker<'a> {
+    position: &'a Position,
+    color: Color,
+    stage: Stage,
+    captures_only: bool,
+    hash_move: Option<Move>,
+    killers: [Option<Move>; 2],
+    counter_move: Option<Move>,
+    /// Moves already returned by the hash, killer and counter move stages.
+    returned: Vec<Move>,
+    captures: Vec<(Move, i32)>,
+    bad_captures: Vec<Move>,
+    quiets: Vec<(Move, i32)>,
+}
+
+impl<'a> MovePicker<'a> {
+    /// Creates a picker over all legal moves.
+    ///
+    /// # Arguments
+    /// * `position` - The board state.
+    /// * `color` - The side to move.
+    /// * `hash_move` - Best move from the transposition table, if any. It is checked for legality.
+    /// * `ordering` - Killer, counter move and history tables.
+    /// * `ply` - Distance from the root, selects the killer slots.
+    /// * `previous` - The move that led to this position, selects the counter move.
+    pub fn new(
+        position: &'a Position,
+        color: Color,
+        hash_move: Option<Move>,
+        ordering: &MoveOrdering,
+        ply: usize,
+        previous: Option<Move>,
+    ) -> Self {
+        MovePicker {
+            position,
+            color,
+            stage: Stage::HashMove,
+            captures_only: false,
+            hash_move,
+            killers: ordering.killers(ply),
+            counter_move: ordering.counter_move(previous),
+            returned: Vec::new(),
+            captures: Vec::new(),
+            bad_captures: Vec::new(),
+            quiets: Vec::new(),
+        }
+    }
+
+    /// Creates a picker that only returns captures and promotions that do not lose material.
+    ///
+    /// Used by quiescence search.
+    pub fn captures(position: &'a Position, color: Color) -> Self {
+        MovePicker {
+            position,
+            color,
+            stage: Stage::GenerateCaptures,
+            captures_only: true,
+            hash_move: None,
+            killers: [None; 2],
+            counter_move: None,
+            returned: Vec::new(),
+            captures: Vec::new(),
+            bad_captures: Vec::new(),
+            quiets: Vec::new(),
+        }
+    }
+
+    /// Returns the stage the picker is currently in.
+    pub fn stage(&self) -> Stage {
+        self.stage
+    }
+
+    fn already_returned(&self, m: &Move) -> bool {
+        self.returned.iter().any(|r| r.is_same(m))
+    }
+
+    /// Returns a special (hash, killer or counter) move if it is usable here.
+    fn special_move(&mut self, candidate: Option<Move>, quiet_only: bool) -> Option<Move> {
+        let m = candidate?;
+        if self.already_returned(&m) {
+            return None;
+        }
+        if quiet_only && (m.promoted_from_pawn || captured_piece(&m, self.position).is_some()) {
+            return None;
+        }
+        if !is_legal(m, self.color, self.position) {
+            return None;
+        }
+        self.returned.push(m);
+        Some(m)
+    }
+
+    /// Removes and returns the highest-scored entry of `list`.
+    fn pop_best(list: &mut Vec<(Move, i32)>) -> Option<(Move, i32)> {
+        let best = (0..list.len()).max_by_key(|&i| list[i].1)?;
+        Some(list.swap_remove(best))
+    }
+
+    /// Returns the next move, or `None` once all moves have been returned.
+    ///
+    /// `ordering` is read when quiet moves are generated, to sort them by history.
+    pub fn next(&mut self, ordering: &MoveOrdering) -> Option<Move> {
+        loop {
+            match self.stage {
+                Stage::HashMove => {
+                    self.stage = Stage::GenerateCaptures;
+                    if let Some(m) = self.special_move(self.hash_move, false) {
+                        return Some(m);
+                    }
+                }
+                Stage::GenerateCaptures => {
+                    let position = self.position;
+                    for m in capture_moves(self.color, position) {
+                        if self.already_returned(&m) {
+                            continue;
+                        }
+                        let is_underpromotion = m.promoted_from_pawn && !matches!(m.piece, Piece::Queen(_));
+                        if is_underpromotion {
+                            self.bad_captures.push(m);
+                        } else {
+                            self.captures.push((m, mvv_lva(&m, position)));
+                        }
+                    }
+                    self.stage = Stage::GoodCaptures;
+                }
+                Stage::GoodCaptures => match Self::pop_best(&mut self.captures) {
+                    Some((m, _)) => {
+                        if see(self.position, m) < 0 {
+                            self.bad_captures.push(m);
+                        } else {
+                            return Some(m);
+                        }
+                    }
+                    None => {
+                        self.stage = if self.captures_only { Stage::Done } else { Stage::Killers };
+                    }
+                },
+                Stage::Killers => {
+                    self.stage = Stage::CounterMove;
+                    let killers = self.killers;
+                    for killer in killers {
+                        if let Some(m) = self.special_move(killer, true) {
+                            // revisit this stage for the second killer
+                            self.stage = Stage::Killers;
+                            return Some(m);
+                        }
+                    }
+                }
+                Stage::CounterMove => {
+                    self.stage = Stage::GenerateQuiets;
+                    if let Some(m) = self.special_move(self.counter_move, true) {
+                        return Some(m);
+                    }
+                }
+                Stage::GenerateQuiets => {
+                    for m in quiet_moves(self.color, self.position) {
+                        if !self.already_returned(&m) {
+                            self.quiets.push((m, ordering.history_score(&m)));
+                        }
+                    }
+                    self.stage = Stage::Quiets;
+                }
+                Stage::Quiets => match Self::pop_best(&mut self.quiets) {
+                    Some((m, _)) => return Some(m),
+                    None => self.stage = Stage::BadCaptures,
+                },
+                Stage::BadCaptures => {
+                    if self.bad_captures.is_empty() {
+                        self.stage = Stage::Done;
+                    } else {
+                        return Some(self.bad_captures.remove(0));
+                    }
+                }
+                Stage::Done => return None,
+            }
+        }
+    }
+}
@@ -1,6 +1,8 @@
 use crate::piece::{Color, Piece};
-use crate::position::Position;
-use crate::special_moves::{castling_moves, is_pawn_promotion, valid_pawn_promotions};
+use crate::position::{Position, get_piece_at};
+use crate::horde;
+use crate::special_moves::{castling_moves, is_pawn_promotion, variant_pawn_promotions};
+use crate::variant::Variant;
 
 /// Represents a chess move on the board.
 #[derive(Debug, Clone, Copy, Eq)]
@@ -13,6 +15,12 @@ pub struct Move {
     pub piece: Piece,
     /// Whether this move resulted from a pawn promotion.
     pub promoted_from_pawn: bool,
+    /// Crazyhouse: whether `piece` is dropped from the pocket onto `to`.
+    /// A drop has no origin square; `from` is set to `to`.
+    pub drop: bool,
+    /// Duck chess: the square the duck is placed on after the piece has moved.
+    /// `None` in other variants, and for the piece part of a duck chess move.
+    pub duck: Option<u8>,
 }
 
 impl PartialEq for Move {
@@ -23,6 +31,20 @@ impl PartialEq for Move {
     }
 }
 
+impl Move {
+    /// Strict equality: like `==`, but the pieces must match too.
+    ///
+    /// Use this to tell apart promotions to different pieces on the same squares.
+    pub fn is_same(&self, other: &Move) -> bool {
+        self.from == other.from
+            && self.to == other.to
+            && self.piece == other.piece
+            && self.promoted_from_pawn == other.promoted_from_pawn
+            && self.drop == other.drop
+            && self.duck == other.duck
+    }
+}
+
 /// Returns all pseudo-legal moves for the given piece on the given square.
 ///
 /// Delegates to the appropriate move generator based on the piece type.
@@ -46,6 +68,37 @@ pub fn valid_moves(from: u8, piece: Piece, position: &Position) -> Vec<Move> {
     }
 }
 
+/// Returns the piece captured by a move, if any.
+///
+/// En passant captures return the pawn that is removed from behind the target square.
+///
+/// # Arguments
+/// * `m` - The move to inspect.
+/// * `position` - Board state before the move is made.
+///
+/// # Returns
+/// * `Some(Piece)` if the move captures an enemy piece.
+/// * `None` for quiet moves.
+pub fn captured_piece(m: &Move, position: &Position) -> Option<Piece> {
+    let color = m.piece.color();
+    if let Some(target) = get_piece_at(position, m.to) {
+        return if target.color() != color { Some(target) } else { None };
+    }
+    // en passant: a pawn moving diagonally onto the empty en passant square
+    let is_pawn = m.promoted_from_pawn || matches!(m.piece, Piece::Pawn(_));
+    if is_pawn && Some(m.to) == position.en_passant && m.from % 8 != m.to % 8 {
+        let captured_square = match color {
+            Color::White => m.to - 8,
+            Color::Black => m.to + 8,
+        };
+        let enemy_pawn = Piece::Pawn(color.opponent());
+        if get_piece_at(position, captured_square) == Some(enemy_pawn) {
+            return Some(enemy_pawn);
+        }
+    }
+    None
+}
+
 
 /// Returns side indices for a piece.
 ///
@@ -79,7 +132,8 @@ pub fn piece_indexes(piece: Piece) -> (usize, usize) {
 /// Generates pseudo-legal knight moves from a square.
 ///
 /// Knights can jump over pieces.  
-/// This function checks board boundaries and excludes landing on friendly pieces.
+/// This function checks board boundaries and excludes landing on friendly pieces
+/// (or the duck).
 pub fn valid_knight_moves(from: u8, piece: Piece, position: &Position) -> Vec<Move> {
     // see knight-offset.jpg
     let knight_offsets: [i8; 8] = [17, 15, 10, 6, -17, -15, -10, -6];
@@ -122,7 +176,8 @@ pub fn valid_knight_moves(from: u8, piece: Piece, position: &Position) -> Vec<Mo
             },
         };
 
-        if (position.bb_sides[side_index].0 & spotlight) != 0 {
+        // nor on the duck
+        if ((position.bb_sides[side_index].0 | position.duck.0) & spotlight) != 0 {
             continue;
         }
 
@@ -131,6 +186,8 @@ pub fn valid_knight_moves(from: u8, piece: Piece, position: &Position) -> Vec<Mo
             to: target as u8,
             piece,
             promoted_from_pawn: false,
+            drop: false,
+            duck: None,
         });
     }
     moves
@@ -139,7 +196,7 @@ pub fn valid_knight_moves(from: u8, piece: Piece, position: &Position) -> Vec<Mo
 /// Generates pseudo-legal bishop moves from a square.
 ///
 /// Bishops move diagonally until blocked.  
-/// Captures are included, but the ray stops at the first blocking piece.
+/// Captures are included, but the ray stops at the first blocking piece (or the duck).
 pub fn valid_bishop_moves(from: u8, piece: Piece, position: &Position) -> Vec<Move> {
     let mut moves = Vec::new();
 
@@ -151,7 +208,7 @@ pub fn valid_bishop_moves(from: u8, piece: Piece, position: &Position) -> Vec<Mo
 
         loop {
             target += dir;
-            if target < 0 || target >= 63 {
+            if target < 0 || target > 63 {
                 break;
             }
 
@@ -165,8 +222,8 @@ pub fn valid_bishop_moves(from: u8, piece: Piece, position: &Position) -> Vec<Mo
             }
 
             let spotlight = 1u64 << target;
-            // friendly piece? stop
-            if (position.bb_sides[friendly_index].0 & spotlight) != 0 {
+            // friendly piece or the duck? stop
+            if ((position.bb_sides[friendly_index].0 | position.duck.0) & spotlight) != 0 {
                 break;
             }
             // enemy piece? push move, then stop
@@ -176,6 +233,8 @@ pub fn valid_bishop_moves(from: u8, piece: Piece, position: &Position) -> Vec<Mo
                     to: target as u8,
                     piece,
                     promoted_from_pawn: false,
+                    drop: false,
+                    duck: None,
                 });
                 break;
             }
@@ -184,6 +243,8 @@ pub fn valid_bishop_moves(from: u8, piece: Piece, position: &Position) -> Vec<Mo
                 to: target as u8,
                 piece,
                 promoted_from_pawn: false,
+                drop: false,
+                duck: None,
             });
         }
     }
@@ -193,7 +254,7 @@ pub fn valid_bishop_moves(from: u8, piece: Piece, position: &Position) -> Vec<Mo
 /// Generates pseudo-legal rook moves from a square.
 ///
 /// Rooks move horizontally and vertically until blocked.  
-/// Captures are included, but the ray stops at the first blocking piece.
+/// Captures are included, but the ray stops at the first blocking piece (or the duck).
 pub fn valid_rook_moves(from: u8, piece: Piece, position: &Position) -> Vec<Move> {
     let mut moves = Vec::new();
 
@@ -210,16 +271,15 @@ pub fn valid_rook_moves(from: u8, piece: Piece, position: &Position) -> Vec<Move
             }
 
             let target_row = target / 8;
-            let target_col = target % 8;
             let from_row = from as i8 / 8;
-            let from_col = from as i8 % 8;
 
-            if target_row != from_row && target_col != from_col {
+            // a rank ray must not wrap onto the next rank
+            if (dir == 1 || dir == -1) && target_row != from_row {
                 break;
             }
             let spotlight = 1u64 << target;
-            // friendly piece: stop
-            if (position.bb_sides[friendly_index].0 & spotlight) != 0 {
+            // friendly piece or the duck: stop
+            if ((position.bb_sides[friendly_index].0 | position.duck.0) & spotlight) != 0 {
                 break;
             }
             // enemy piece: push move, then stop
@@ -229,6 +289,8 @@ pub fn valid_rook_moves(from: u8, piece: Piece, position: &Position) -> Vec<Move
                     to: target as u8,
                     piece,
                     promoted_from_pawn: false,
+                    drop: false,
+                    duck: None,
                 });
                 break;
             }
@@ -237,6 +299,8 @@ pub fn valid_rook_moves(from: u8, piece: Piece, position: &Position) -> Vec<Move
                 to: target as u8,
                 piece,
                 promoted_from_pawn: false,
+                drop: false,
+                duck: None,
             });
         }
     }
@@ -256,10 +320,10 @@ pub fn valid_queen_moves(from: u8, piece: Piece, position: &Position) -> Vec<Mov
 /// Generates pseudo-legal pawn moves from a square.
 ///
 /// Handles:
-/// - Single and double forward pushes
+/// - Single and double forward pushes (in Horde also from the first rank), blocked by the duck too
 /// - Diagonal captures
 /// - En passant captures
-/// - Promotions at the last rank
+/// - Promotions at the last rank (including to king in antichess)
 pub fn valid_pawn_moves(from: u8, piece: Piece, position: &Position) -> Vec<Move> {
     let mut moves = Vec::new();
 
@@ -278,14 +342,17 @@ pub fn valid_pawn_moves(from: u8, piece: Piece, position: &Position) -> Vec<Move
     let forward1_mask = 1u64 << forward1;
     if (position.bb_sides[0].0 & forward1_mask == 0)
         && (position.bb_sides[1].0 & forward1_mask == 0)
+        && (position.duck.0 & forward1_mask == 0)
     {
         if is_pawn_promotion(forward1 as u8, piece) {
-            for promoted_piece in valid_pawn_promotions(piece) {
+            for promoted_piece in variant_pawn_promotions(piece, position) {
                 moves.push(Move {
                     from,
                     to: forward1 as u8,
                     piece: promoted_piece,
                     promoted_from_pawn: true,
+                    drop: false,
+                    duck: None,
                 });
             }
         } else {
@@ -294,15 +361,19 @@ pub fn valid_pawn_moves(from: u8, piece: Piece, position: &Position) -> Vec<Move
                 to: forward1 as u8,
                 piece,
                 promoted_from_pawn: false,
+                drop: false,
+                duck: None,
             });
         }
 
-        // 2-squares forward from starting row
-        if from_row == start_row {
+        // 2-squares forward from starting row (or the first rank, for the Horde)
+        let horde_first_rank = position.variant == Variant::Horde && horde::is_first_rank_pawn(piece.color(), from);
+        if from_row == start_row || horde_first_rank {
             let forward2 = forward1 + dir;
             let forward2_mask = 1u64 << forward2;
             if (position.bb_sides[0].0 & forward2_mask == 0)
                 && (position.bb_sides[1].0 & forward2_mask == 0)
+                && (position.duck.0 & forward2_mask == 0)
             {
                 // **also check intermediate square is empty**
                 if (position.bb_sides[0].0 & forward1_mask == 0)
@@ -313,6 +384,8 @@ pub fn valid_pawn_moves(from: u8, piece: Piece, position: &Position) -> Vec<Move
                         to: forward2 as u8,
                         piece,
                         promoted_from_pawn: false,
+                        drop: false,
+                        duck: None,
                     });
                 }
             }
@@ -330,16 +403,22 @@ pub fn valid_pawn_moves(from: u8, piece: Piece, position: &Position) -> Vec<Move
         }
 
         let target_mask = 1u64 << target;
+        // the duck may sit on the en passant square
+        if position.duck.0 & target_mask != 0 {
+            continue;
+        }
         if (position.bb_sides[enemy_index].0 & target_mask != 0)
             || Some(target as u8) == position.en_passant
         {
             if is_pawn_promotion(target as u8, piece) {
-                for promoted_piece in valid_pawn_promotions(piece) {
+                for promoted_piece in variant_pawn_promotions(piece, position) {
                     moves.push(Move {
                         from,
                         to: target as u8,
                         piece: promoted_piece,
                         promoted_from_pawn: true,
+                        drop: false,
+                        duck: None,
                     });
                 }
             } else {
@@ -348,17 +427,8 @@ pub fn valid_pawn_moves(from: u8, piece: Piece, position: &Position) -> Vec<Move
                     to: target as u8,
                     piece,
                     promoted_from_pawn: false,
-                });
-            }
-        }
-        // en passant square check
-        if let Some(ep_square) = position.en_passant {
-            if ep_square as i8 == target {
-                moves.push(Move {
-                    from,
-                    to: target as u8,
-                    piece,
-                    promoted_from_pawn: false,
+                    drop: false,
+                    duck: None,
                 });
             }
         }
@@ -371,14 +441,14 @@ pub fn valid_pawn_moves(from: u8, piece: Piece, position: &Position) -> Vec<Move
 ///
 /// Handles:
 /// - Single-square king moves in all directions
-/// - Excludes moves landing on friendly pieces
+/// - Excludes moves landing on friendly pieces (and, in atomic chess, on enemy pieces)
 /// - Adds possible castling moves via [`castling_moves`]
 ///
 /// Does not check for moving into check.
 pub fn valid_king_moves(from: u8, piece: Piece, position: &Position) -> Vec<Move> {
     let mut moves = Vec::new();
 
-    let (own_index, _enemy_index) = piece_indexes(piece);
+    let (own_index, enemy_index) = piece_indexes(piece);
     let directions: [i8; 8] = [1, -1, 8, -8, 7, -7, 9, -9];
 
     let from_row = (from / 8) as i8;
@@ -408,8 +478,12 @@ pub fn valid_king_moves(from: u8, piece: Piece, position: &Position) -> Vec<Move
 
         let spotlight = 1u64 << target;
 
-        // cannot land on own piece
-        if (position.bb_sides[own_index].0 & spotlight) != 0 {
+        // cannot land on own piece or the duck
+        if ((position.bb_sides[own_index].0 | position.duck.0) & spotlight) != 0 {
+            continue;
+        }
+        // atomic chess: the king cannot capture, it would blow itself up
+        if position.variant == Variant::Atomic && (position.bb_sides[enemy_index].0 & spotlight) != 0 {
             continue;
         }
 
@@ -418,6 +492,8 @@ pub fn valid_king_moves(from: u8, piece: Piece, position: &Position) -> Vec<Move
             to: target as u8,
             piece,
             promoted_from_pawn: false,
+            drop: false,
+            duck: None,
         });
     }
     moves.extend(castling_moves(from, piece, position));
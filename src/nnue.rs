@@ -0,0 +1,507 @@
+use std::fs;
+
+use crate::eval::{PIECE_SQUARE_TABLES, PIECE_VALUES, pst_index};
+use crate::make_move::apply_move_unchecked;
+use crate::moves::Move;
+use crate::piece::Color;
+use crate::position::{Pieces, Position, Sides};
+
+// see: https://www.chessprogramming.org/NNUE
+
+/// Number of king buckets: each perspective uses a separate set of feature
+/// weights depending on where its own king stands.
+pub const KING_BUCKETS: usize = 4;
+/// Features per king bucket: 2 sides x 6 piece types x 64 squares.
+pub const FEATURES_PER_BUCKET: usize = 768;
+/// Total number of input features per perspective.
+pub const INPUTS: usize = KING_BUCKETS * FEATURES_PER_BUCKET;
+/// Upper bound of the clipped ReLU applied to the accumulator.
+///
+/// High enough that the bundled network, which sums up all of a side's
+/// material in one neuron, does not saturate even with nine queens.
+pub const QA: i32 = 1023;
+/// Quantization factor of the output weights.
+pub const QB: i32 = 64;
+
+/// Magic bytes at the start of a network file.
+pub const MAGIC: &[u8; 4] = b"RCNN";
+/// File format version understood by [`Network::from_bytes`].
+pub const VERSION: u32 = 1;
+
+/// A tiny network shipped with the crate, used when no other network is given.
+///
+/// It has 8 hidden neurons, of which only the first is used: it adds up the
+/// classical material and piece-square values (see [`crate::eval`]) of the
+/// perspective's own pieces in units of 20 centipawns. It plays like the
+/// classical evaluation and mostly serves to check that inference works.
+/// The file is written by `cargo run --bin nnuegen`, see [`Network::classical`].
+pub const BUNDLED_NETWORK: &[u8] = include_bytes!("../nets/tiny.nnue");
+
+/*
+Network file format (all integers little endian):
+
+  magic           4 bytes, "RCNN"
+  version         u32, = 1
+  king buckets    u32, must equal KING_BUCKETS
+  hidden size     u32, number of accumulator neurons (H)
+  output scale    u32, multiplies the output before dequantization
+  feature weights i16 x INPUTS x H, feature-major (all H weights of feature 0 first)
+  feature biases  i16 x H
+  output weights  i16 x 2H, first H for the side to move, then H for the opponent
+  output bias     i32
+
+Feature index for a piece, seen from perspective `p` (White or Black):
+
+  bucket(own king) * 768 + (piece is p's ? 0 : 384) + piece type * 64 + square
+
+where squares (including the king's) are flipped vertically for Black, so both
+perspectives see their own pieces moving "up" the board.
+
+Evaluation:
+
+  acc[p]  = biases + sum of the weights of p's active features
+  output  = bias + sum(crelu(acc[us]) * w_us) + sum(crelu(acc[them]) * w_them)
+  score   = output * scale / (QA * QB)   centipawns, for the side to move
+*/
+
+/// Quantized network weights of a king-bucketed (HalfKA-style) NNUE.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Network {
+    hidden: usize,
+    scale: i32,
+    feature_weights: Vec<i16>,
+    feature_biases: Vec<i16>,
+    output_weights: Vec<i16>,
+    output_bias: i32,
+}
+
+/// Reads little-endian values from a byte slice.
+struct Reader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl Reader<'_> {
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], String> {
+        let end = self.offset + N;
+        let slice = self.bytes.get(self.offset..end).ok_or("Network file is truncated")?;
+        self.offset = end;
+        Ok(slice.try_into().expect("slice has length N"))
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        self.take::<4>().map(u32::from_le_bytes)
+    }
+
+    fn i32(&mut self) -> Result<i32, String> {
+        self.take::<4>().map(i32::from_le_bytes)
+    }
+
+    fn i16s(&mut self, count: usize) -> Result<Vec<i16>, String> {
+        (0..count).map(|_| self.take::<2>().map(i16::from_le_bytes)).collect()
+    }
+}
+
+impl Network {
+    /// Creates a network from raw quantized weights.
+    ///
+    /// # Arguments
+    /// * `scale` - Output scale, see the file format description.
+    /// * `feature_weights` - `INPUTS * H` weights, feature-major.
+    /// * `feature_biases` - `H` biases; their length defines the hidden size `H`.
+    /// * `output_weights` - `2 * H` weights.
+    /// * `output_bias` - Output bias.
+    ///
+    /// # Errors
+    /// Returns `Err(String)` if the lengths do not fit together.
+    pub fn new(
+        scale: i32,
+        feature_weights: Vec<i16>,
+        feature_biases: Vec<i16>,
+        output_weights: Vec<i16>,
+        output_bias: i32,
+    ) -> Result<Self, String> {
+        let hidden = feature_biases.len();
+        if hidden == 0 {
+            return Err("Network needs at least one hidden neuron".to_string());
+        }
+        if feature_weights.len() != INPUTS * hidden {
+            return Err(format!("Expected {} feature weights, got {}", INPUTS * hidden, feature_weights.len()));
+        }
+        if output_weights.len() != 2 * hidden {
+            return Err(format!("Expected {} output weights, got {}", 2 * hidden, output_weights.len()));
+        }
+        Ok(Network { hidden, scale, feature_weights, feature_biases, output_weights, output_bias })
+    }
+
+    /// Parses a network in the documented binary format.
+    ///
+    /// # Errors
+    /// Returns `Err(String)` if the magic, version or bucket count do not match,
+    /// or the data is truncated or has trailing bytes.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        let mut reader = Reader { bytes, offset: 0 };
+        if &reader.take::<4>()? != MAGIC {
+            return Err("Not a network file (bad magic)".to_string());
+        }
+        let version = reader.u32()?;
+        if version != VERSION {
+            return Err(format!("Unsupported network version {}", version));
+        }
+        let buckets = reader.u32()? as usize;
+        if buckets != KING_BUCKETS {
+            return Err(format!("Network has {} king buckets, expected {}", buckets, KING_BUCKETS));
+        }
+        let hidden = reader.u32()? as usize;
+        if hidden == 0 || hidden > 1 << 16 {
+            return Err(format!("Invalid hidden size {}", hidden));
+        }
+        let scale = reader.u32()? as i32;
+        let feature_weights = reader.i16s(INPUTS * hidden)?;
+        let feature_biases = reader.i16s(hidden)?;
+        let output_weights = reader.i16s(2 * hidden)?;
+        let output_bias = reader.i32()?;
+        if reader.offset != bytes.len() {
+            return Err("Network file has trailing data".to_string());
+        }
+        Network::new(scale, feature_weights, feature_biases, output_weights, output_bias)
+    }
+
+    /// Loads a network file from disk.
+    pub fn load(path: &str) -> Result<Self, String> {
+        let bytes = fs::read(path).map_err(|e| format!("Cannot read {}: {}", path, e))?;
+        Network::from_bytes(&bytes)
+    }
+
+    /// Loads the [`BUNDLED_NETWORK`].
+    pub fn bundled() -> Self {
+        Network::from_bytes(BUNDLED_NETWORK).expect("bundled network is valid")
+    }
+
+    /// Builds the network stored as [`BUNDLED_NETWORK`] from the classical
+    /// material and piece-square tables.
+    ///
+    /// Each of a perspective's own pieces weighs its value (the king counts
+    /// nothing) plus its piece-square value, divided by 20 and rounded, in every
+    /// king bucket. The output is the first neuron of the side to move minus that
+    /// of the opponent, scaled back to centipawns.
+    pub fn classical() -> Self {
+        const HIDDEN: usize = 8;
+        const UNIT: f64 = 20.0;
+        let mut feature_weights = vec![0i16; INPUTS * HIDDEN];
+        for bucket in 0..KING_BUCKETS {
+            for piece_type in 0..6 {
+                let value = if piece_type == Pieces::KING { 0 } else { PIECE_VALUES[piece_type] };
+                for square in 0..64 {
+                    // features are already oriented for the perspective, so read the tables as White
+                    let pst = PIECE_SQUARE_TABLES[piece_type][pst_index(square as u8, Color::White)];
+                    let f = bucket * FEATURES_PER_BUCKET + piece_type * 64 + square;
+                    feature_weights[f * HIDDEN] = ((value + pst) as f64 / UNIT).round() as i16;
+                }
+            }
+        }
+        let mut output_weights = vec![0i16; 2 * HIDDEN];
+        output_weights[0] = QB as i16;
+        output_weights[HIDDEN] = -QB as i16;
+        let scale = UNIT as i32 * QA;
+        Network::new(scale, feature_weights, vec![0; HIDDEN], output_weights, 0).expect("sizes fit")
+    }
+
+    /// Serializes the network in the documented binary format.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(24 + 2 * (INPUTS + 3) * self.hidden);
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&VERSION.to_le_bytes());
+        bytes.extend_from_slice(&(KING_BUCKETS as u32).to_le_bytes());
+        bytes.extend_from_slice(&(self.hidden as u32).to_le_bytes());
+        bytes.extend_from_slice(&(self.scale as u32).to_le_bytes());
+        for weights in [&self.feature_weights, &self.feature_biases, &self.output_weights] {
+            for w in weights.iter() {
+                bytes.extend_from_slice(&w.to_le_bytes());
+            }
+        }
+        bytes.extend_from_slice(&self.output_bias.to_le_bytes());
+        bytes
+    }
+
+    /// Number of hidden (accumulator) neurons.
+    pub fn hidden_size(&self) -> usize {
+        self.hidden
+    }
+
+    fn weights(&self, feature: usize) -> &[i16] {
+        &self.feature_weights[feature * self.hidden..(feature + 1) * self.hidden]
+    }
+
+    /// Evaluates a position from its accumulator.
+    ///
+    /// # Arguments
+    /// * `accumulator` - An accumulator that is up to date with the position.
+    /// * `color` - The side to move; the score is relative to it.
+    ///
+    /// # Returns
+    /// The evaluation in centipawns.
+    pub fn evaluate(&self, accumulator: &Accumulator, color: Color) -> i32 {
+        let us = &accumulator.values[color.index()];
+        let them = &accumulator.values[color.opponent().index()];
+        let (w_us, w_them) = self.output_weights.split_at(self.hidden);
+
+        let mut output = self.output_bias as i64;
+        for i in 0..self.hidden {
+            output += crelu(us[i]) as i64 * w_us[i] as i64;
+            output += crelu(them[i]) as i64 * w_them[i] as i64;
+        }
+        (output * self.scale as i64 / (QA * QB) as i64) as i32
+    }
+
+    /// Evaluates a position from scratch, without an existing accumulator.
+    pub fn evaluate_position(&self, color: Color, position: &Position) -> i32 {
+        self.evaluate(&Accumulator::new(self, position), color)
+    }
+}
+
+fn crelu(x: i16) -> i32 {
+    (x as i32).clamp(0, QA)
+}
+
+/// Returns the king bucket of a (perspective-oriented) king square:
+/// queen side or king side, and home ranks (1-2) or further up.
+fn king_bucket(king_square: usize) -> usize {
+    let file = king_square % 8;
+    let rank = king_square / 8;
+    (file >= 4) as usize + 2 * (rank >= 2) as usize
+}
+
+/// Flips a square vertically for Black's perspective.
+fn orient(square: usize, perspective: usize) -> usize {
+    if perspective == Sides::WHITE { square } else { square ^ 56 }
+}
+
+/// Returns the king bucket of `perspective` in `position`.
+fn bucket_of(position: &Position, perspective: usize) -> usize {
+    let king = position.bb_pieces[perspective][Pieces::KING].0;
+    // a position without a king (e.g. in tests) uses bucket 0
+    if king == 0 {
+        return 0;
+    }
+    king_bucket(orient(king.trailing_zeros() as usize, perspective))
+}
+
+/// Returns the feature index of a piece as seen by `perspective`.
+fn feature(bucket: usize, perspective: usize, side: usize, piece_type: usize, square: usize) -> usize {
+    let relation = if side == perspective { 0 } else { 384 };
+    bucket * FEATURES_PER_BUCKET + relation + piece_type * 64 + orient(square, perspective)
+}
+
+/// Hidden layer sums of both perspectives for one position.
+///
+/// Instead of being recomputed for every position, an accumulator is updated
+/// with the few features a move changes (see [`Accumulator::update`]). Only
+/// when a king moves into another bucket is that perspective recomputed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Accumulator {
+    /// Sums indexed by perspective (`Sides::WHITE` / `Sides::BLACK`).
+    values: [Vec<i16>; 2],
+    /// King bucket each perspective was computed with.
+    buckets: [usize; 2],
+}
+
+impl Accumulator {
+    /// Computes the accumulator of `position` from scratch.
+    pub fn new(network: &Network, position: &Position) -> Self {
+        let mut accumulator = Accumulator {
+            values: [network.feature_biases.clone(), network.feature_biases.clone()],
+            buckets: [0; 2],
+        };
+        accumulator.refresh(network, position, Sides::WHITE);
+        accumulator.refresh(network, position, Sides::BLACK);
+        accumulator
+    }
+
+    /// Recomputes one perspective from scratch.
+    fn refresh(&mut self, network: &Network, position: &Position, perspective: usize) {
+        let bucket = bucket_of(position, perspective);
+        self.buckets[perspective] = bucket;
+        let values = &mut self.values[perspective];
+        values.copy_from_slice(&network.feature_biases);
+
+        for side in [Sides::WHITE, Sides::BLACK] {
+            for piece_type in 0..6 {
+                let mut bb = position.bb_pieces[side][piece_type].0;
+                while bb != 0 {
+                    let square = bb.trailing_zeros() as usize;
+                    bb &= bb - 1;
+                    let f = feature(bucket, perspective, side, piece_type, square);
+                    for (v, w) in values.iter_mut().zip(network.weights(f)) {
+                        *v = v.wrapping_add(*w);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Updates the accumulator from `before` to `after`.
+    ///
+    /// Only the pieces that differ between the two positions are added or removed,
+    /// which for a normal move is two to four features. This covers every kind of
+    /// move (captures, castling, en passant, promotions) without special cases.
+    ///
+    /// # Arguments
+    /// * `network` - The network this accumulator belongs to.
+    /// * `before` - The position this accumulator is currently up to date with.
+    /// * `after` - The position to update to, usually `before` plus one move.
+    pub fn update(&mut self, network: &Network, before: &Position, after: &Position) {
+        for perspective in [Sides::WHITE, Sides::BLACK] {
+            let bucket = bucket_of(after, perspective);
+            if bucket != self.buckets[perspective] {
+                self.refresh(network, after, perspective);
+                continue;
+            }
+            let values = &mut self.values[perspective];
+            for side in [Sides::WHITE, Sides::BLACK] {
+                for piece_type in 0..6 {
+                    let old = before.bb_pieces[side][piece_type].0;
+                    let new = after.bb_pieces[side][piece_type].0;
+
+                    let mut removed = old & !new;
+                    while removed != 0 {
+                        let square = removed.trailing_zeros() as usize;
+                        removed &= removed - 1;
+                        let f = feature(bucket, perspective, side, piece_type, square);
+                        for (v, w) in values.iter_mut().zip(network.weights(f)) {
+                            *v = v.wrapping_sub(*w);
+                        }
+                    }
+                    let mut added = new & !old;
+                    while added != 0 {
+                        let square = added.trailing_zeros() as usize;
+                        added &= added - 1;
+                        let f = feature(bucket, perspective, side, piece_type, square);
+                        for (v, w) in values.iter_mut().zip(network.weights(f)) {
+                            *v = v.wrapping_add(*w);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Applies a move like [`apply_move_unchecked`] and keeps `accumulator` in sync.
+///
+/// # Arguments
+/// * `m` - The move to apply.
+/// * `position` - The board state to update.
+/// * `network` - The network `accumulator` belongs to.
+/// * `accumulator` - The accumulator of `position`, updated in place.
+pub fn apply_move_nnue(m: Move, position: &mut Position, network: &Network, accumulator: &mut Accumulator) {
+    let before = *position;
+    apply_move_unchecked(m, position);
+    accumulator.update(network, &before, position);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fen::{START_FEN, parse_fen};
+    use crate::make_move::{legal_moves, update_castling_rights};
+    use crate::uci::move_from_uci;
+
+    #[test]
+    fn bundled_network_is_the_classical_one() {
+        assert_eq!(Network::bundled(), Network::classical());
+        assert_eq!(Network::classical().to_bytes(), BUNDLED_NETWORK);
+    }
+
+    #[test]
+    fn file_round_trip() {
+        let network = Network::bundled();
+        assert_eq!(Network::from_bytes(&network.to_bytes()).unwrap(), network);
+        let bytes = network.to_bytes();
+        assert!(Network::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        assert!(Network::from_bytes(&[bytes.as_slice(), &[0]].concat()).is_err());
+    }
+
+    #[test]
+    fn known_evaluations() {
+        let network = Network::bundled();
+        let evaluate = |fen: &str| {
+            let (position, color) = parse_fen(fen).unwrap();
+            network.evaluate_position(color, &position)
+        };
+        assert_eq!(evaluate(START_FEN), 0);
+        // a pawn on e2 is worth 100 - 20 centipawns, 4 units of 20
+        assert_eq!(evaluate("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1"), 80);
+        assert_eq!(evaluate("4k3/8/8/8/8/8/4P3/4K3 b - - 0 1"), -80);
+        // 203 units for White against 205 for Black
+        assert_eq!(evaluate("r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4"), -40);
+    }
+
+    #[test]
+    fn heavy_material_does_not_saturate() {
+        let network = Network::bundled();
+        let fens = [
+            "QQQQ1k2/8/8/8/8/8/PPPPPPPP/RNBQKBNR w KQ - 0 1",
+            "rnbqkbnr/pppppppp/8/8/8/8/8/2K1qqqq b kq - 0 1",
+            "QQQQkQQQ/8/8/8/8/8/8/qqqqKqqq w - - 0 1",
+            "QQQQkQQQ/QQQQQQQQ/8/8/8/8/8/RRBBKNNQ w - - 0 1",
+        ];
+        for fen in fens {
+            let (position, color) = parse_fen(fen).unwrap();
+            // every piece's weight is rounded to 20 centipawns
+            let pieces = (position.bb_sides[0].0 | position.bb_sides[1].0).count_ones() as i32;
+            let classical = crate::eval::evaluate(color, &position);
+            let nnue = network.evaluate_position(color, &position);
+            assert!((nnue - classical).abs() <= 10 * pieces, "{}: {} vs {}", fen, nnue, classical);
+        }
+    }
+
+    #[test]
+    fn incremental_update_matches_refresh() {
+        let network = Network::bundled();
+        let (mut position, mut color) = parse_fen("r3k2r/1P6/8/3pP3/8/8/7p/R3K1NR w KQkq d6 0 1").unwrap();
+        let mut accumulator = Accumulator::new(&network, &position);
+        let moves = [
+            "e5d6", // en passant
+            "h2g1n", // capture and underpromotion
+            "e1c1", // castling, the king changes to the queen side bucket
+            "e8g8", // castling
+            "b7a8q", // capture and promotion
+            "g8g7",
+            "c1d2",
+            "g7g6", // Black's king leaves its home ranks
+            "d2e3", // White's king leaves its home ranks and the queen side
+            "f8a8",
+        ];
+        for text in moves {
+            let m = move_from_uci(text, color, &position).unwrap_or_else(|| panic!("{} is legal", text));
+            apply_move_nnue(m, &mut position, &network, &mut accumulator);
+            update_castling_rights(m, &mut position);
+            color = color.opponent();
+            assert_eq!(accumulator, Accumulator::new(&network, &position), "after {}", text);
+        }
+    }
+
+    #[test]
+    fn incremental_update_matches_refresh_in_random_games() {
+        let network = Network::bundled();
+        // a fixed linear congruential sequence picks the moves
+        let mut seed: u64 = 42;
+        for _ in 0..4 {
+            let (mut position, mut color) = parse_fen(START_FEN).unwrap();
+            let mut accumulator = Accumulator::new(&network, &position);
+            for _ in 0..80 {
+                let moves = legal_moves(color, &position);
+                if moves.is_empty() {
+                    break;
+                }
+                seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+                let m = moves[(seed >> 33) as usize % moves.len()];
+                apply_move_nnue(m, &mut position, &network, &mut accumulator);
+                update_castling_rights(m, &mut position);
+                color = color.opponent();
+                assert_eq!(accumulator, Accumulator::new(&network, &position));
+            }
+        }
+    }
+}
@@ -0,0 +1,439 @@
+use std::fs;
+
+use crate::duck::{placement_suffix, split_placement};
+use crate::fen::{START_FEN, parse_fen};
+use crate::helper::{index_to_square, square_to_index};
+use crate::game::Game;
+use crate::make_move::{is_checked, legal_moves, make_move_quiet};
+use crate::moves::{Move, captured_piece};
+use crate::piece::{Color, Piece};
+use crate::position::Position;
+use crate::search::play;
+use crate::special_moves::castling_squares;
+use crate::variant::Variant;
+
+// see: https://www.chessprogramming.org/Portable_Game_Notation
+// and: https://www.chessprogramming.org/Algebraic_Chess_Notation#SAN
+
+/// SAN letter of a piece type (pawns have none).
+fn piece_letter(piece: Piece) -> Option<char> {
+    match piece {
+        Piece::Pawn(_) => None,
+        Piece::Knight(_) => Some('N'),
+        Piece::Bishop(_) => Some('B'),
+        Piece::Rook(_) => Some('R'),
+        Piece::Queen(_) => Some('Q'),
+        Piece::King(_) => Some('K'),
+    }
+}
+
+/// The piece that stands on `m.from` before the move (a pawn for promotions).
+fn moving_piece(m: &Move) -> Piece {
+    if m.promoted_from_pawn { Piece::Pawn(m.piece.color()) } else { m.piece }
+}
+
+/// Writes a legal move in Standard Algebraic Notation, e.g. `Nbd7`, `exd5`, `e8=Q+`, `O-O#`,
+/// the Crazyhouse drop `N@f3` or the duck chess move `e4,d5`.
+///
+/// # Arguments
+/// * `m` - A legal move of the side to move.
+/// * `position` - The position before the move.
+pub fn move_to_san(m: &Move, position: &Position) -> String {
+    let color = m.piece.color();
+    let mut san = String::new();
+    let piece = moving_piece(m);
+
+    if m.drop {
+        san.push(piece_letter(piece).unwrap_or('P'));
+        san.push('@');
+        san.push_str(&index_to_square(m.to).to_ascii_lowercase());
+    } else if let Some(castling) = castling_squares(m, position) {
+        san.push_str(if castling.kingside { "O-O" } else { "O-O-O" });
+    } else {
+        let from = index_to_square(m.from).to_ascii_lowercase();
+        let capture = captured_piece(m, position).is_some();
+        match piece_letter(piece) {
+            Some(letter) => {
+                san.push(letter);
+                // other pieces of the same kind that can reach the same square
+                let rivals: Vec<Move> = legal_moves(color, position)
+                    .into_iter()
+                    .filter(|other| other.to == m.to && other.from != m.from && !other.drop && moving_piece(other) == piece)
+                    .collect();
+                if !rivals.is_empty() {
+                    if rivals.iter().all(|r| r.from % 8 != m.from % 8) {
+                        san.push_str(&from[0..1]);
+                    } else if rivals.iter().all(|r| r.from / 8 != m.from / 8) {
+                        san.push_str(&from[1..2]);
+                    } else {
+                        san.push_str(&from);
+                    }
+                }
+            }
+            None if capture => san.push_str(&from[0..1]),
+            None => {}
+        }
+        if capture {
+            san.push('x');
+        }
+        san.push_str(&index_to_square(m.to).to_ascii_lowercase());
+        if m.promoted_from_pawn {
+            san.push('=');
+            san.push(piece_letter(m.piece).unwrap_or('Q'));
+        }
+    }
+    san.push_str(&placement_suffix(m));
+
+    let after = play(*m, position);
+    if is_checked(color.opponent(), &after) {
+        san.push(if legal_moves(color.opponent(), &after).is_empty() { '#' } else { '+' });
+    }
+    san
+}
+
+/// Finds the legal move described by a SAN string.
+///
+/// Check and annotation suffixes (`+`, `#`, `!`, `?`) are ignored, castling may
+/// be written with `O` or `0`, promotions with or without `=`, and pawn drops
+/// with or without the `P`.
+///
+/// # Arguments
+/// * `text` - e.g. `e4`, `Nbd7`, `exd6`, `O-O-O`, `e8=Q+`, `N@f3` or `e4,d5`.
+/// * `color` - The side to move.
+/// * `position` - The board state.
+///
+/// # Returns
+/// The matching legal move, or `None` if the text is malformed, illegal or ambiguous.
+pub fn move_from_san(text: &str, color: Color, position: &Position) -> Option<Move> {
+    let text = text.trim_end_matches(['+', '#', '!', '?']);
+    let (text, duck) = split_placement(text)?;
+    let mut moves = legal_moves(color, position);
+    moves.retain(|m| m.duck == duck);
+
+    let castle = text.replace('0', "O");
+    if castle == "O-O" || castle == "O-O-O" {
+        let kingside = castle == "O-O";
+        return moves
+            .into_iter()
+            .find(|m| castling_squares(m, position).is_some_and(|castling| castling.kingside == kingside));
+    }
+
+    if let Some((letter, square)) = text.split_once('@') {
+        let letter = letter.chars().next().unwrap_or('P');
+        let to = square_to_index(square)?;
+        return moves
+            .into_iter()
+            .find(|m| m.drop && m.to == to && piece_letter(m.piece).unwrap_or('P') == letter);
+    }
+
+    let mut body = text;
+    let mut promotion = None;
+    if let Some(i) = body.find('=') {
+        promotion = body[i + 1..].chars().next();
+        body = &body[..i];
+    } else if let Some(last) = body.chars().last()
+        && "NBRQK".contains(last)
+        && body.len() > 2
+    {
+        promotion = Some(last);
+        body = &body[..body.len() - 1];
+    }
+
+    let letter = body.chars().next().filter(|c| "NBRQK".contains(*c));
+    if letter.is_some() {
+        body = &body[1..];
+    }
+    if body.len() < 2 || !body.is_ascii() {
+        return None;
+    }
+    let to = square_to_index(&body[body.len() - 2..])?;
+    // disambiguation: a file, a rank or both
+    let hint: Vec<char> = body[..body.len() - 2].chars().filter(|&c| c != 'x').collect();
+
+    let found: Vec<Move> = moves
+        .into_iter()
+        .filter(|m| {
+            let piece = moving_piece(m);
+            m.to == to
+                && !m.drop
+                && piece_letter(piece) == letter
+                && match promotion {
+                    None => !m.promoted_from_pawn,
+                    Some(p) => m.promoted_from_pawn && piece_letter(m.piece) == Some(p),
+                }
+                && hint.iter().all(|&c| match c {
+                    'a'..='h' => m.from % 8 == c as u8 - b'a',
+                    '1'..='8' => m.from / 8 == c as u8 - b'1',
+                    _ => false,
+                })
+        })
+        .collect();
+    match found.as_slice() {
+        [m] => Some(*m),
+        _ => None,
+    }
+}
+
+/// One game of a PGN file.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PgnGame {
+    /// Tag pairs in file order, e.g. `("White", "Carlsen, Magnus")`.
+    pub tags: Vec<(String, String)>,
+    /// Main line moves in SAN; comments, variations and NAGs are dropped.
+    pub moves: Vec<String>,
+    /// Game termination marker: `1-0`, `0-1`, `1/2-1/2` or `*`.
+    pub result: String,
+}
+
+impl PgnGame {
+    /// Returns the value of a tag, if present.
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags.iter().find(|(n, _)| n == name).map(|(_, v)| v.as_str())
+    }
+
+    /// The position the game starts from: the `FEN` tag if present, else the start
+    /// position of the variant, played under the rules of the `Variant` tag
+    /// (standard chess without one).
+    ///
+    /// # Errors
+    /// Returns `Err(String)` if the `FEN` or `Variant` tag is invalid.
+    pub fn start_position(&self) -> Result<(Position, Color), String> {
+        let (chess960, variant) = match self.tag("Variant") {
+            Some(name) if ["chess960", "fischerandom"].contains(&name.to_ascii_lowercase().as_str()) => (true, None),
+            Some(name) => {
+                (false, Some(Variant::from_name(name).ok_or_else(|| format!("Unsupported variant '{}'", name))?))
+            }
+            None => (false, None),
+        };
+        let start_fen = variant.map_or(START_FEN, Variant::start_fen);
+        let (mut position, color) = parse_fen(self.tag("FEN").unwrap_or(start_fen))?;
+        position.castling_rights.chess960 |= chess960;
+        if let Some(variant) = variant {
+            position.variant = variant;
+        }
+        Ok((position, color))
+    }
+
+    /// White's score: `1.0`, `0.5` or `0.0`, or `None` if the result is unknown.
+    ///
+    /// Falls back to the `Result` tag if the movetext has no termination marker.
+    pub fn white_score(&self) -> Option<f64> {
+        let result = if self.result.is_empty() { self.tag("Result").unwrap_or("*") } else { &self.result };
+        match result {
+            "1-0" => Some(1.0),
+            "0-1" => Some(0.0),
+            "1/2-1/2" => Some(0.5),
+            _ => None,
+        }
+    }
+
+    /// Replays the main line from [`PgnGame::start_position`] with [`make_move_quiet`].
+    ///
+    /// # Returns
+    /// The moves with the position and side to move before each of them.
+    ///
+    /// # Errors
+    /// Returns `Err(String)` naming the first move that is malformed or illegal.
+    pub fn replay(&self) -> Result<Vec<(Position, Color, Move)>, String> {
+        let (position, color) = self.start_position()?;
+        let mut game = Game::new(position);
+        if color == Color::Black {
+            game.turn_tracker();
+        }
+        let mut line = Vec::with_capacity(self.moves.len());
+        for (ply, text) in self.moves.iter().enumerate() {
+            let color = game.player_tracker();
+            let illegal = || format!("illegal move '{}' at ply {}", text, ply + 1);
+            let m = move_from_san(text, color, &game.position).ok_or_else(illegal)?;
+            if game.is_over() {
+                return Err(illegal());
+            }
+            line.push((game.position, color, m));
+            make_move_quiet(m, &mut game).map_err(|_| illegal())?;
+        }
+        Ok(line)
+    }
+}
+
+/// A piece of PGN text.
+enum Token {
+    Tag(String, String),
+    Move(String),
+    Result(String),
+}
+
+/// Splits PGN text into tags, moves and results, dropping comments (`{...}`, `;`),
+/// variations (`(...)`), NAGs (`$1`), move numbers and escaped lines (`%`).
+fn tokenize(text: &str) -> Vec<Token> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    let mut depth = 0; // nesting of variations
+    while i < chars.len() {
+        let c = chars[i];
+        let line_start = i == 0 || chars[i - 1] == '\n';
+        match c {
+            _ if c.is_whitespace() => i += 1,
+            '{' => {
+                while i < chars.len() && chars[i] != '}' {
+                    i += 1;
+                }
+                i += 1;
+            }
+            ';' => {
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                }
+            }
+            '%' if line_start => {
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                }
+            }
+            '(' => {
+                depth += 1;
+                i += 1;
+            }
+            ')' => {
+                depth = (depth - 1).max(0);
+                i += 1;
+            }
+            '[' if depth == 0 => {
+                let start = i + 1;
+                let mut in_quotes = false;
+                i += 1;
+                while i < chars.len() && (in_quotes || chars[i] != ']') {
+                    if chars[i] == '\\' {
+                        i += 1;
+                    } else if chars[i] == '"' {
+                        in_quotes = !in_quotes;
+                    }
+                    i += 1;
+                }
+                let inner: String = chars[start..i.min(chars.len())].iter().collect();
+                i += 1;
+                let (name, value) = inner.trim().split_once(char::is_whitespace).unwrap_or((inner.trim(), ""));
+                let value = value.trim().trim_matches('"').replace("\\\"", "\"").replace("\\\\", "\\");
+                tokens.push(Token::Tag(name.to_string(), value));
+            }
+            _ => {
+                let start = i;
+                while i < chars.len() && !chars[i].is_whitespace() && !"[]{}();".contains(chars[i]) {
+                    i += 1;
+                }
+                if i == start {
+                    // a stray delimiter
+                    i += 1;
+                    continue;
+                }
+                if depth > 0 {
+                    continue;
+                }
+                let word: String = chars[start..i].iter().collect();
+                match word.as_str() {
+                    "1-0" | "0-1" | "1/2-1/2" | "*" => tokens.push(Token::Result(word)),
+                    _ if word.starts_with('$') => {}
+                    _ => {
+                        // "12." / "12..." / "12.e4": drop the move number
+                        let san = if word.starts_with(|c: char| c.is_ascii_digit()) && word.contains('.') {
+                            word.trim_start_matches(|c: char| c.is_ascii_digit()).trim_start_matches('.')
+                        } else {
+                            &word
+                        };
+                        if !san.is_empty() {
+                            tokens.push(Token::Move(san.to_string()));
+                        }
+                    }
+                }
+            }
+        }
+    }
+    tokens
+}
+
+/// Parses all games of a PGN text.
+///
+/// Parsing is lenient: a game ends at its result marker or where the next
+/// game's tags begin. Moves are not checked here; see [`PgnGame::replay`].
+pub fn parse_pgn(text: &str) -> Vec<PgnGame> {
+    let mut games = Vec::new();
+    let mut game = PgnGame::default();
+    for token in tokenize(text) {
+        match token {
+            Token::Tag(name, value) => {
+                if !game.moves.is_empty() {
+                    games.push(std::mem::take(&mut game));
+                }
+                game.tags.push((name, value));
+            }
+            Token::Move(san) => game.moves.push(san),
+            Token::Result(result) => {
+                game.result = result;
+                games.push(std::mem::take(&mut game));
+            }
+        }
+    }
+    if !game.moves.is_empty() || !game.tags.is_empty() {
+        games.push(game);
+    }
+    games
+}
+
+/// Reads and parses a PGN file.
+///
+/// # Errors
+/// Returns `Err(String)` if the file cannot be read.
+pub fn load_pgn(path: &str) -> Result<Vec<PgnGame>, String> {
+    // PGN files are often Latin-1; decode lossily rather than failing
+    let bytes = fs::read(path).map_err(|e| format!("Cannot read {}: {}", path, e))?;
+    Ok(parse_pgn(&String::from_utf8_lossy(&bytes)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::uci::move_to_uci;
+
+    #[test]
+    fn san_round_trip() {
+        let cases = [
+            ("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1", "O-O", "e1g1"),
+            ("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1", "O-O-O", "e1c1"),
+            ("8/P6k/8/8/8/8/8/K7 w - - 0 1", "a8=Q", "a7a8q"),
+            ("8/P6k/8/8/8/8/8/K7 w - - 0 1", "a8=N", "a7a8n"),
+            ("7k/8/8/8/8/8/8/R3R2K w - - 0 1", "Rad1", "a1d1"),
+            ("7k/8/8/8/8/8/8/R3R2K w - - 0 1", "Red1", "e1d1"),
+            ("7k/8/8/R7/8/8/8/R6K w - - 0 1", "R1a3", "a1a3"),
+            ("7k/8/8/R7/8/8/8/R6K w - - 0 1", "R5a3", "a5a3"),
+            ("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1", "exd6", "e5d6"),
+            ("4k3/8/8/8/8/8/8/R3K3 w - - 0 1", "Ra8+", "a1a8"),
+            ("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", "Ra8#", "a1a8"),
+        ];
+        for (fen, san, uci) in cases {
+            let (position, color) = parse_fen(fen).unwrap();
+            let m = move_from_san(san, color, &position).unwrap_or_else(|| panic!("{} in {}", san, fen));
+            assert_eq!(move_to_uci(&m), uci, "{}", san);
+            assert_eq!(move_to_san(&m, &position), san);
+            // every legal move survives the round trip
+            for m in legal_moves(color, &position) {
+                let parsed = move_from_san(&move_to_san(&m, &position), color, &position).unwrap();
+                assert!(parsed.is_same(&m), "{}", move_to_uci(&m));
+            }
+        }
+    }
+
+    #[test]
+    fn lenient_san() {
+        let (position, color) = parse_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        let castle = move_from_san("O-O", color, &position).unwrap();
+        assert!(move_from_san("0-0", color, &position).unwrap().is_same(&castle));
+        let (position, color) = parse_fen("8/P6k/8/8/8/8/8/K7 w - - 0 1").unwrap();
+        let queen = move_from_san("a8=Q", color, &position).unwrap();
+        assert!(move_from_san("a8Q!?", color, &position).unwrap().is_same(&queen));
+        // a plain pawn move to the last rank needs a promotion
+        assert!(move_from_san("a8", color, &position).is_none());
+        // ambiguous without the file
+        let (position, color) = parse_fen("7k/8/8/8/8/8/8/R3R2K w - - 0 1").unwrap();
+        assert!(move_from_san("Rd1", color, &position).is_none());
+    }
+}
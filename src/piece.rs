@@ -24,9 +24,55 @@ impl Piece {
             | Piece::King(c) => *c,
         }
     }
+
+    /// Returns the piece-type index used by `bb_pieces` (see [`Pieces`](crate::position::Pieces)).
+    pub fn index(&self) -> usize {
+        match self {
+            Piece::Pawn(_) => 0,
+            Piece::Knight(_) => 1,
+            Piece::Bishop(_) => 2,
+            Piece::Rook(_) => 3,
+            Piece::Queen(_) => 4,
+            Piece::King(_) => 5,
+        }
+    }
+
+    /// Builds a piece from a piece-type index (`0..=5`, pawn -> king) and a color.
+    pub fn from_index(piece_type: usize, color: Color) -> Piece {
+        match piece_type {
+            0 => Piece::Pawn(color),
+            1 => Piece::Knight(color),
+            2 => Piece::Bishop(color),
+            3 => Piece::Rook(color),
+            4 => Piece::Queen(color),
+            5 => Piece::King(color),
+            _ => unreachable!(),
+        }
+    }
+}
+impl Color {
+    /// Returns the opposing color.
+    pub fn opponent(&self) -> Color {
+        match self {
+            Color::White => Color::Black,
+            Color::Black => Color::White,
+        }
+    }
+
+    /// Returns the side index used by `bb_sides` and `bb_pieces` (see [`Sides`](crate::position::Sides)).
+    pub fn index(&self) -> usize {
+        match self {
+            Color::White => 0,
+            Color::Black => 1,
+        }
+    }
 }
 
 
+/// Castling rights, tracked as "has moved" flags for each king and castling rook.
+///
+/// The rooks' starting files are stored too, so the same rules cover Chess960,
+/// where king and rooks may start on any file of the back rank.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct CastlingRights {
     pub white_king_moved: bool,
@@ -35,6 +81,13 @@ pub struct CastlingRights {
     pub black_king_moved: bool,
     pub black_kingside_rook_moved: bool,
     pub black_queenside_rook_moved: bool,
+    /// Starting file (0-7) of the rook castling kingside, by side index; `7` in standard chess.
+    pub kingside_rook_files: [u8; 2],
+    /// Starting file (0-7) of the rook castling queenside, by side index; `0` in standard chess.
+    pub queenside_rook_files: [u8; 2],
+    /// Chess960 castling: castling moves are encoded as the king capturing its own
+    /// rook (`e1h1`) instead of as the king's two-square move (`e1g1`).
+    pub chess960: bool,
 }
 impl CastlingRights {
     /// Creates a new `CastlingRights` with all rights available (no king or rook has moved).
@@ -46,6 +99,39 @@ impl CastlingRights {
             black_king_moved: false,
             black_kingside_rook_moved: false,
             black_queenside_rook_moved: false,
+            kingside_rook_files: [7, 7],
+            queenside_rook_files: [0, 0],
+            chess960: false,
+        }
+    }
+
+    /// Whether `color` still has the right to castle on the given side, i.e. neither
+    /// its king nor that rook has moved.
+    pub fn has_right(&self, color: Color, kingside: bool) -> bool {
+        match (color, kingside) {
+            (Color::White, true) => !self.white_king_moved && !self.white_kingside_rook_moved,
+            (Color::White, false) => !self.white_king_moved && !self.white_queenside_rook_moved,
+            (Color::Black, true) => !self.black_king_moved && !self.black_kingside_rook_moved,
+            (Color::Black, false) => !self.black_king_moved && !self.black_queenside_rook_moved,
+        }
+    }
+
+    /// The starting square of the rook `color` castles with on the given side.
+    pub fn rook_square(&self, color: Color, kingside: bool) -> u8 {
+        let file = if kingside { self.kingside_rook_files[color.index()] } else { self.queenside_rook_files[color.index()] };
+        match color {
+            Color::White => file,
+            Color::Black => 56 + file,
+        }
+    }
+
+    /// Removes the right of `color` to castle on the given side.
+    pub fn revoke(&mut self, color: Color, kingside: bool) {
+        match (color, kingside) {
+            (Color::White, true) => self.white_kingside_rook_moved = true,
+            (Color::White, false) => self.white_queenside_rook_moved = true,
+            (Color::Black, true) => self.black_kingside_rook_moved = true,
+            (Color::Black, false) => self.black_queenside_rook_moved = true,
         }
     }
 }
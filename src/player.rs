@@ -0,0 +1,366 @@
+use crate::engine::{Engine, SearchLimits};
+use crate::eval::PIECE_VALUES;
+use crate::game::{Game, GameResult};
+use crate::make_move::{is_insufficient_material, legal_moves, make_move_quiet, no_moves_result, variant_result};
+use crate::move_ordering::mvv_lva;
+use crate::moves::{Move, captured_piece};
+use crate::piece::Color;
+use crate::position::{Pieces, Position};
+use crate::rng::Rng;
+use crate::search::play;
+use crate::time_manager::TimeControl;
+
+/// Something that picks moves for a [`Game`]: a bot, an engine, or a human behind a UI.
+///
+/// Apart from [`Player::choose_move`], all methods have defaults that never
+/// offer or accept draws and never resign.
+pub trait Player {
+    /// Name shown in match results.
+    fn name(&self) -> String;
+
+    /// Picks a move for the side to move.
+    ///
+    /// Only called while the game is ongoing, so there is at least one legal move.
+    /// The returned move must be legal.
+    fn choose_move(&mut self, game: &Game) -> Move;
+
+    /// Asked before each move; return `true` to offer a draw to the opponent.
+    fn offer_draw(&mut self, _game: &Game) -> bool {
+        false
+    }
+
+    /// Called when the opponent offers a draw; return `true` to accept it.
+    fn accept_draw(&mut self, _game: &Game) -> bool {
+        false
+    }
+
+    /// Asked before each move; return `true` to resign instead of moving.
+    fn should_resign(&mut self, _game: &Game) -> bool {
+        false
+    }
+
+    /// Called before a new game starts, e.g. to clear caches.
+    fn new_game(&mut self) {}
+
+    /// Score of the player's last move from its own point of view, in centipawns,
+    /// if it has one. Used by the match runner to adjudicate games.
+    fn last_score(&self) -> Option<i32> {
+        None
+    }
+
+    /// Called before [`Player::choose_move`] when the game is played with a clock.
+    fn set_clock(&mut self, _time: &TimeControl) {}
+}
+
+/// Material balance from `color`'s point of view, in centipawns (kings not counted).
+fn material(position: &Position, color: Color) -> i32 {
+    let mut score = 0;
+    for (piece_type, value) in PIECE_VALUES.iter().enumerate().take(Pieces::KING) {
+        let ours = position.bb_pieces[color.index()][piece_type].0.count_ones() as i32;
+        let theirs = position.bb_pieces[color.opponent().index()][piece_type].0.count_ones() as i32;
+        score += (ours - theirs) * value;
+    }
+    score
+}
+
+/// Returns the legal moves of the side to move.
+fn game_moves(game: &Game) -> Vec<Move> {
+    let moves = legal_moves(game.player_tracker(), &game.position);
+    assert!(!moves.is_empty(), "choose_move called without legal moves");
+    moves
+}
+
+/// Picks uniformly among the moves with the highest key, so ties are broken randomly.
+fn best_by_key<F>(moves: &[Move], rng: &mut Rng, key: F) -> Move
+where
+    F: Fn(&Move) -> i32,
+{
+    let best = moves.iter().map(&key).max().expect("moves is not empty");
+    let candidates: Vec<&Move> = moves.iter().filter(|m| key(m) == best).collect();
+    *candidates[rng.below(candidates.len())]
+}
+
+/// Plays a uniformly random legal move.
+pub struct RandomPlayer {
+    rng: Rng,
+}
+
+impl RandomPlayer {
+    /// Creates a player with a seeded random generator.
+    pub fn new(seed: u64) -> Self {
+        RandomPlayer { rng: Rng::new(seed) }
+    }
+}
+
+impl Player for RandomPlayer {
+    fn name(&self) -> String {
+        "random".to_string()
+    }
+
+    fn choose_move(&mut self, game: &Game) -> Move {
+        let moves = game_moves(game);
+        moves[self.rng.below(moves.len())]
+    }
+}
+
+/// Captures whenever it can, taking the most valuable piece with the least valuable
+/// attacker (MVV-LVA). Otherwise plays a random move.
+pub struct GreedyCapturePlayer {
+    rng: Rng,
+}
+
+impl GreedyCapturePlayer {
+    /// Creates a player with a seeded random generator.
+    pub fn new(seed: u64) -> Self {
+        GreedyCapturePlayer { rng: Rng::new(seed) }
+    }
+}
+
+impl Player for GreedyCapturePlayer {
+    fn name(&self) -> String {
+        "greedy-capture".to_string()
+    }
+
+    fn choose_move(&mut self, game: &Game) -> Move {
+        let moves = game_moves(game);
+        let captures: Vec<Move> = moves.iter().copied().filter(|m| captured_piece(m, &game.position).is_some()).collect();
+        if captures.is_empty() {
+            return moves[self.rng.below(moves.len())];
+        }
+        best_by_key(&captures, &mut self.rng, |m| mvv_lva(m, &game.position))
+    }
+}
+
+/// Plays the move that leaves it with the best material balance one ply later
+/// (so it sees captures and promotions, but not recaptures). Ties are broken randomly.
+pub struct MaterialPlayer {
+    rng: Rng,
+}
+
+impl MaterialPlayer {
+    /// Creates a player with a seeded random generator.
+    pub fn new(seed: u64) -> Self {
+        MaterialPlayer { rng: Rng::new(seed) }
+    }
+}
+
+impl Player for MaterialPlayer {
+    fn name(&self) -> String {
+        "material".to_string()
+    }
+
+    fn choose_move(&mut self, game: &Game) -> Move {
+        let moves = game_moves(game);
+        let color = game.player_tracker();
+        best_by_key(&moves, &mut self.rng, |&m| material(&play(m, &game.position), color))
+    }
+}
+
+/// Plays the moves of an [`Engine`], such as the alpha-beta [`Searcher`](crate::Searcher)
+/// or the [`MctsSearcher`](crate::mcts::MctsSearcher).
+///
+/// It resigns once the engine's score has been below [`EnginePlayer::resign_score`]
+/// for [`EnginePlayer::resign_moves`] moves in a row, accepts draw offers when its
+/// score is at most [`EnginePlayer::draw_score`], and offers draws itself when the
+/// score has stayed within `±draw_score` for [`EnginePlayer::draw_moves`] moves.
+pub struct EnginePlayer {
+    name: String,
+    pub engine: Box<dyn Engine>,
+    pub limits: SearchLimits,
+    /// Score (from the engine's side) at or below which it considers resigning; `None` never resigns.
+    pub resign_score: Option<i32>,
+    pub resign_moves: usize,
+    pub draw_score: i32,
+    /// Number of balanced moves before offering a draw; `0` never offers.
+    pub draw_moves: usize,
+    /// Scores of the engine's own moves in this game.
+    scores: Vec<i32>,
+}
+
+impl EnginePlayer {
+    /// Wraps an engine that searches within `limits` for every move.
+    pub fn new(name: &str, engine: Box<dyn Engine>, limits: SearchLimits) -> Self {
+        EnginePlayer {
+            name: name.to_string(),
+            engine,
+            limits,
+            resign_score: Some(-1000),
+            resign_moves: 3,
+            draw_score: 10,
+            draw_moves: 20,
+            scores: Vec::new(),
+        }
+    }
+}
+
+impl Player for EnginePlayer {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn choose_move(&mut self, game: &Game) -> Move {
+        let result = self.engine.go(&game.position, game.player_tracker(), &self.limits);
+        self.scores.push(result.score);
+        result.best_move.unwrap_or_else(|| game_moves(game)[0])
+    }
+
+    fn offer_draw(&mut self, _game: &Game) -> bool {
+        self.draw_moves > 0
+            && self.scores.len() >= self.draw_moves
+            && self.scores[self.scores.len() - self.draw_moves..].iter().all(|s| s.abs() <= self.draw_score)
+    }
+
+    fn accept_draw(&mut self, _game: &Game) -> bool {
+        self.last_score().is_some_and(|score| score <= self.draw_score)
+    }
+
+    fn should_resign(&mut self, _game: &Game) -> bool {
+        let Some(threshold) = self.resign_score else { return false };
+        self.resign_moves > 0
+            && self.scores.len() >= self.resign_moves
+            && self.scores[self.scores.len() - self.resign_moves..].iter().all(|&s| s <= threshold)
+    }
+
+    fn new_game(&mut self) {
+        self.scores.clear();
+        self.engine.new_game();
+    }
+
+    /// Score of the engine's last search, if it has moved in this game.
+    fn last_score(&self) -> Option<i32> {
+        self.scores.last().copied()
+    }
+
+    fn set_clock(&mut self, time: &TimeControl) {
+        self.limits.time = *time;
+    }
+}
+
+/// Plays one game between two players until it ends, or until `max_plies` plies
+/// have been played (then it is a draw).
+///
+/// Before each move the side to move may resign, or offer a draw which the
+/// opponent may accept. Moves are made with [`make_move_quiet`]; an illegal
+/// move loses the game for the player who made it. Positions without mating
+/// material are drawn.
+///
+/// # Arguments
+/// * `white`, `black` - The players.
+/// * `game` - The game to continue, usually a fresh [`Game`].
+/// * `max_plies` - Ply limit, counted from the start of this call.
+///
+/// # Returns
+/// The final result, which is also stored in `game.result`.
+pub fn play_game<'a>(white: &mut (dyn Player + 'a), black: &mut (dyn Player + 'a), game: &mut Game, max_plies: u32) -> GameResult {
+    for _ in 0..max_plies {
+        if game.is_over() {
+            return game.result;
+        }
+        let color = game.player_tracker();
+        if let Some(result) = variant_result(color, &game.position) {
+            game.result = result;
+            return game.result;
+        }
+        if legal_moves(color, &game.position).is_empty() {
+            game.result = no_moves_result(color, &game.position);
+            return game.result;
+        }
+        if is_insufficient_material(&game.position) {
+            game.result = GameResult::Draw;
+            return game.result;
+        }
+        let (mover, opponent) = match color {
+            Color::White => (&mut *white, &mut *black),
+            Color::Black => (&mut *black, &mut *white),
+        };
+
+        if mover.should_resign(game) {
+            game.result = GameResult::Resigned(color);
+            return game.result;
+        }
+        if mover.offer_draw(game) && opponent.accept_draw(game) {
+            game.result = GameResult::Draw;
+            return game.result;
+        }
+        let m = mover.choose_move(game);
+        if make_move_quiet(m, game).is_err() {
+            game.result = GameResult::Resigned(color);
+            return game.result;
+        }
+    }
+    if !game.is_over() {
+        game.result = GameResult::Draw;
+    }
+    game.result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fen::{START_FEN, parse_fen};
+    use crate::make_move::is_legal;
+    use crate::search::Searcher;
+    use crate::uci::move_to_uci;
+
+    /// Checks every move of the wrapped player and records it.
+    struct Checked<P: Player> {
+        player: P,
+        moves: Vec<String>,
+    }
+
+    impl<P: Player> Player for Checked<P> {
+        fn name(&self) -> String {
+            self.player.name()
+        }
+
+        fn choose_move(&mut self, game: &Game) -> Move {
+            let m = self.player.choose_move(game);
+            assert!(is_legal(m, game.player_tracker(), &game.position), "{} played {}", self.name(), move_to_uci(&m));
+            self.moves.push(move_to_uci(&m));
+            m
+        }
+    }
+
+    fn checked<P: Player>(player: P) -> Checked<P> {
+        Checked { player, moves: Vec::new() }
+    }
+
+    fn new_game() -> Game {
+        Game::new(parse_fen(START_FEN).unwrap().0)
+    }
+
+    fn bots(seed: u64) -> Vec<Box<dyn Player>> {
+        let limits = SearchLimits { depth: Some(1), ..Default::default() };
+        vec![
+            Box::new(checked(RandomPlayer::new(seed))),
+            Box::new(checked(GreedyCapturePlayer::new(seed))),
+            Box::new(checked(MaterialPlayer::new(seed))),
+            Box::new(checked(EnginePlayer::new("engine", Box::new(Searcher::new()), limits))),
+        ]
+    }
+
+    #[test]
+    fn players_only_play_legal_moves() {
+        for (i, white) in bots(1).iter_mut().enumerate() {
+            for (j, black) in bots(2).iter_mut().enumerate() {
+                if i != j {
+                    let mut game = new_game();
+                    play_game(white.as_mut(), black.as_mut(), &mut game, 40);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn seeded_players_are_deterministic() {
+        let play = |seed: u64| {
+            let mut white = checked(RandomPlayer::new(seed));
+            let mut black = checked(MaterialPlayer::new(seed + 1));
+            let mut game = new_game();
+            let result = play_game(&mut white, &mut black, &mut game, 60);
+            (white.moves, black.moves, result)
+        };
+        assert_eq!(play(5), play(5));
+        assert_ne!(play(5).0, play(6).0);
+    }
+}
@@ -1,5 +1,6 @@
 use crate::bitboard::BitBoard;
 use crate::piece::{Piece, Color, CastlingRights};
+use crate::variant::Variant;
 
 // is copy needed? idk. added for debug
 
@@ -26,6 +27,20 @@ pub struct Position{
     /// If `Some(u8)`, it is the square index (0–63, where 0 = A1, 63 = H8).  
     /// If `None`, no en passant is available.
     pub en_passant: Option<u8>, // None or bit position
+    /// The rule set this position is played under.
+    pub variant: Variant,
+    /// Crazyhouse pockets: pieces in hand, counted as `pockets[side][piece_type]`
+    /// for pawn to queen. Always empty in other variants.
+    pub pockets: [[u8; 5]; 2],
+    /// Crazyhouse: squares of pieces that were promoted from pawns, which
+    /// go back to the pocket as pawns when captured.
+    pub promoted: BitBoard,
+    /// Three-check: the number of checks given so far, by side index.
+    /// Always zero in other variants.
+    pub checks: [u8; 2],
+    /// Duck chess: the square of the duck, a blocker that belongs to neither
+    /// side and cannot be captured. Empty in other variants.
+    pub duck: BitBoard,
 }
 /// Constants representing the two sides in a chess game.
 pub struct Sides;
@@ -0,0 +1,104 @@
+use crate::make_move::legal_moves;
+use crate::piece::{Color, Piece};
+use crate::position::{Pieces, Position};
+
+// see: https://lichess.org/variant/racingKings
+
+/// The Racing Kings start position: both armies on the first two ranks, side by side.
+pub const START_FEN: &str = "8/8/8/8/8/8/krbnNBRK/qrbnNBRQ w - - 0 1";
+
+/// The goal: the eighth rank.
+pub const GOAL: u64 = 0xFF00_0000_0000_0000;
+
+/// Evaluation bonus in centipawns per rank the king has advanced.
+pub const RANK_BONUS: i32 = 60;
+
+/// Returns `true` if `color`'s king stands on the eighth rank.
+pub fn has_reached_goal(color: Color, position: &Position) -> bool {
+    position.bb_pieces[color.index()][Pieces::KING].0 & GOAL != 0
+}
+
+/// Returns `true` if `color` can bring its king to the eighth rank with a legal move.
+pub fn can_reach_goal(color: Color, position: &Position) -> bool {
+    legal_moves(color, position)
+        .iter()
+        .any(|m| matches!(m.piece, Piece::King(_)) && GOAL & 1u64 << m.to != 0)
+}
+
+/// Decides the race with `color` to move.
+///
+/// A king on the eighth rank wins, except that after White gets there Black may
+/// still answer by reaching it too, which draws.
+///
+/// # Returns
+/// `Some(Some(winner))` if the race is won, `Some(None)` if it is drawn and
+/// `None` while it goes on.
+pub fn race_result(color: Color, position: &Position) -> Option<Option<Color>> {
+    match (has_reached_goal(Color::White, position), has_reached_goal(Color::Black, position)) {
+        (true, true) => Some(None),
+        (false, true) => Some(Some(Color::Black)),
+        (true, false) if color == Color::Black && can_reach_goal(Color::Black, position) => None,
+        (true, false) => Some(Some(Color::White)),
+        (false, false) => None,
+    }
+}
+
+/// Evaluation term for the kings' progress, from the point of view of `color`.
+pub fn evaluate(color: Color, position: &Position) -> i32 {
+    let rank = |side: Color| {
+        let king = position.bb_pieces[side.index()][Pieces::KING].0;
+        if king == 0 { 0 } else { (king.trailing_zeros() / 8) as i32 }
+    };
+    (rank(color) - rank(color.opponent())) * RANK_BONUS
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{Game, GameResult};
+    use crate::make_move::{apply_move_unchecked, is_checked};
+    use crate::uci::move_from_uci;
+    use crate::variant::Variant;
+    use crate::variant::testing::{play, variant_game};
+
+    fn racing_game(fen: &str) -> (Game, Color) {
+        variant_game(fen, Variant::RacingKings)
+    }
+
+    #[test]
+    fn no_move_may_give_check() {
+        let (game, color) = racing_game("k7/8/8/8/8/8/8/1R5K w - - 0 1");
+        assert!(move_from_uci("b1a1", color, &game.position).is_none());
+        assert!(move_from_uci("b1b8", color, &game.position).is_none());
+        assert!(move_from_uci("b1b2", color, &game.position).is_some());
+
+        let (game, color) = racing_game(START_FEN);
+        let moves = legal_moves(color, &game.position);
+        assert_eq!(moves.len(), 21);
+        for m in moves {
+            let mut after = game.position;
+            apply_move_unchecked(m, &mut after);
+            assert!(!is_checked(Color::Black, &after));
+        }
+    }
+
+    #[test]
+    fn black_gets_an_equalizing_reply() {
+        let (mut game, color) = racing_game("8/1k4K1/8/8/8/8/8/8 w - - 0 1");
+        play("g7g8", color, &mut game);
+        assert_eq!(game.result, GameResult::Ongoing);
+        play("b7b8", Color::Black, &mut game);
+        assert_eq!(game.result, GameResult::Draw);
+    }
+
+    #[test]
+    fn reaching_the_goal_first_wins() {
+        let (mut game, color) = racing_game("8/6K1/8/1k6/8/8/8/8 w - - 0 1");
+        play("g7g8", color, &mut game);
+        assert_eq!(game.result, GameResult::RaceLost(Color::Black));
+
+        let (mut game, color) = racing_game("8/1k4K1/8/8/8/8/8/8 b - - 0 1");
+        play("b7b8", color, &mut game);
+        assert_eq!(game.result, GameResult::RaceLost(Color::White));
+    }
+}
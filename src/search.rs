@@ -0,0 +1,1230 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::crazyhouse::pocket_material;
+use crate::eval::{DEFAULT_PARAMS, EvalParams, PIECE_VALUES, evaluate_with, piece_value, variant_bonus};
+use crate::game::GameResult;
+use crate::make_move::{
+    apply_move_unchecked, checking_moves, is_checked, is_legal, legal_moves, no_moves_result, update_castling_rights,
+    variant_result,
+};
+use crate::move_ordering::{MAX_PLY, MoveOrdering, MovePicker};
+use crate::moves::{Move, captured_piece};
+use crate::nnue::{Accumulator, Network};
+use crate::piece::Color;
+use crate::position::{Pieces, Position};
+use crate::rng::Rng;
+use crate::skill::{Skill, SkillChoice};
+use crate::syzygy::Syzygy;
+use crate::tablebase::Tablebase;
+use crate::time_manager::{DEFAULT_MOVE_OVERHEAD_MS, TimeControl, TimeManager};
+use crate::tt::{Bound, TranspositionTable};
+use crate::variant::Variant;
+use crate::zobrist::zobrist_key;
+
+// see: https://www.chessprogramming.org/Quiescence_Search
+
+/// Score for checkmating the opponent at the root.
+///
+/// Mates found deeper in the tree score `MATE_SCORE - ply`, so shorter mates are preferred.
+pub const MATE_SCORE: i32 = 30_000;
+/// Bound larger than any reachable score, used as the initial search window.
+pub const INFINITY: i32 = 32_000;
+/// Safety margin for delta pruning: a capture is skipped if even winning the
+/// captured piece plus this margin cannot raise the score to alpha.
+pub const DELTA_MARGIN: i32 = 200;
+/// Default transposition table size in megabytes.
+pub const DEFAULT_HASH_MB: usize = 16;
+
+/// Returns `true` if `score` encodes a forced mate for either side.
+pub fn is_mate_score(score: i32) -> bool {
+    score.abs() >= MATE_SCORE - 1000
+}
+
+/// Scores a finished game from the point of view of `color`.
+///
+/// Wins and losses score like mates at `ply`, so faster wins are preferred.
+pub fn result_score(result: GameResult, color: Color, ply: i32) -> i32 {
+    match result.winner() {
+        Some(winner) if winner == color => MATE_SCORE - ply,
+        Some(_) => -MATE_SCORE + ply,
+        None => 0,
+    }
+}
+
+/// Result of a search.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SearchResult {
+    /// Best move found, or `None` if the side to move has no legal moves.
+    pub best_move: Option<Move>,
+    /// Score of the best move in centipawns, from the point of view of the side to move.
+    pub score: i32,
+    /// Number of nodes visited (including quiescence nodes).
+    pub nodes: u64,
+}
+
+/// One ranked line of a (MultiPV) search.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PvLine {
+    /// The root move of this line.
+    pub best_move: Move,
+    /// Score of the line in centipawns, from the point of view of the side to move.
+    pub score: i32,
+    /// Principal variation, starting with `best_move`.
+    pub pv: Vec<Move>,
+}
+
+/// Progress report passed to [`Searcher::on_iteration`] after each completed iteration.
+pub struct IterationInfo<'a> {
+    /// Depth of the completed iteration.
+    pub depth: u32,
+    /// Ranked lines of this iteration, best first.
+    pub lines: &'a [PvLine],
+    /// Nodes searched so far by the calling thread.
+    pub nodes: u64,
+    /// Time since the search started.
+    pub elapsed: Duration,
+}
+
+/// Callback type of [`Searcher::on_iteration`].
+pub type IterationCallback = Box<dyn FnMut(&IterationInfo) + Send>;
+
+/// Plays a move on a copy of the position, including castling right updates.
+///
+/// This is the make-move used inside the search tree, where `make_move`'s
+/// validation and game bookkeeping are not wanted.
+pub fn play(m: Move, position: &Position) -> Position {
+    let mut next = *position;
+    apply_move_unchecked(m, &mut next);
+    update_castling_rights(m, &mut next);
+    next
+}
+
+/// Switches for the individual search techniques of [`Searcher`].
+///
+/// Everything except quiescence checks is enabled by default. Turning a technique
+/// off makes it possible to measure its effect on node counts and playing strength.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SearchOptions {
+    /// Null-move pruning: let the opponent move twice in a row; if that still fails
+    /// high, prune the node. Disabled when the side to move has only pawns (and king),
+    /// where passing could be an advantage (zugzwang), and in antichess, where forced
+    /// captures make that the rule.
+    pub null_move: bool,
+    /// Late move reductions: search quiet moves late in the move order with reduced
+    /// depth, and only re-search them at full depth if they beat alpha.
+    pub late_move_reductions: bool,
+    /// Futility pruning: near the leaves, skip quiet moves when the static evaluation
+    /// plus a margin cannot reach alpha.
+    pub futility: bool,
+    /// Reverse futility pruning: near the leaves, fail high right away when the static
+    /// evaluation minus a margin is still above beta.
+    pub reverse_futility: bool,
+    /// Aspiration windows: search each iteration with a narrow window around the
+    /// previous score, widening it on failure.
+    pub aspiration_windows: bool,
+    /// Principal variation search: search all but the first move with a null window
+    /// and only re-search moves that beat alpha.
+    pub pvs: bool,
+    /// Check extensions: search one ply deeper when the side to move is in check.
+    pub check_extensions: bool,
+    /// Whether quiescence search also tries quiet checking moves at its first ply.
+    pub quiescence_checks: bool,
+}
+
+impl Default for SearchOptions {
+    fn default() -> Self {
+        SearchOptions {
+            null_move: true,
+            late_move_reductions: true,
+            futility: true,
+            reverse_futility: true,
+            aspiration_windows: true,
+            pvs: true,
+            check_extensions: true,
+            quiescence_checks: false,
+        }
+    }
+}
+
+/// Futility margins in centipawns, indexed by remaining depth (`1..=3`).
+pub const FUTILITY_MARGINS: [i32; 4] = [0, 200, 300, 500];
+/// Reverse futility margin in centipawns per ply of remaining depth.
+pub const REVERSE_FUTILITY_MARGIN: i32 = 120;
+/// Half-width of the first aspiration window in centipawns.
+pub const ASPIRATION_WINDOW: i32 = 50;
+
+/// Returns `true` if `color` has a knight, bishop, rook or queen.
+///
+/// Null-move pruning is unsafe without such pieces, as king and pawn endings are
+/// full of zugzwang positions where passing would be the best move.
+pub fn has_non_pawn_material(color: Color, position: &Position) -> bool {
+    let pieces = &position.bb_pieces[color.index()];
+    [Pieces::KNIGHT, Pieces::BISHOP, Pieces::ROOK, Pieces::QUEEN]
+        .iter()
+        .any(|&piece_type| pieces[piece_type].0 != 0)
+}
+
+/// Depth reduction for a late quiet move, growing with depth and move number.
+fn late_move_reduction(depth: u32, moves_searched: u32) -> u32 {
+    1 + ((depth as f64).ln() * (moves_searched as f64).ln() / 2.0) as u32
+}
+
+/// Alpha-beta search state.
+///
+/// Holds everything that is carried between nodes of one search, such as node counts,
+/// and between searches, such as the transposition table and move ordering tables.
+pub struct Searcher {
+    /// Nodes visited so far.
+    pub nodes: u64,
+    pub options: SearchOptions,
+    /// Transposition table, shared with helper threads.
+    pub tt: Arc<TranspositionTable>,
+    pub ordering: MoveOrdering,
+    /// Number of threads used by [`Searcher::search`]; `1` searches on the calling thread only.
+    pub threads: usize,
+    /// Set to `true` (e.g. from another thread) to stop the running search as soon as possible.
+    pub stop: Arc<AtomicBool>,
+    /// `true` while a ponder search (see [`Searcher::search_ponder`]) is waiting for the
+    /// opponent's move. Store `false` from another thread to signal a ponder hit.
+    pub pondering: Arc<AtomicBool>,
+    /// Time reserved per move for communication lag in timed searches, in milliseconds.
+    pub move_overhead_ms: u64,
+    /// Stops the search once this many nodes have been visited (by the calling thread);
+    /// the result of the last completed iteration is returned.
+    pub node_limit: Option<u64>,
+    /// Playing strength; below full strength every search is weakened (see [`Skill`]).
+    pub skill: Skill,
+    /// Number of best root moves to report (MultiPV). Each iteration searches the root
+    /// this many times, excluding the root moves already reported in earlier passes.
+    pub multi_pv: usize,
+    /// Called after every completed iteration of the calling thread, e.g. to print UCI `info` lines.
+    pub on_iteration: Option<IterationCallback>,
+    /// Neural network used instead of the classical [`evaluate`](crate::eval::evaluate) when set.
+    pub network: Option<Arc<Network>>,
+    /// Parameters of the classical evaluation, e.g. tuned ones; the built-in ones if `None`.
+    pub eval_params: Option<Arc<EvalParams>>,
+    /// Endgame tables consulted at the root and inside the tree when set.
+    pub tablebase: Option<Arc<Tablebase>>,
+    /// Syzygy tables consulted at the root and inside the tree when set.
+    pub syzygy: Option<Arc<Syzygy>>,
+    /// Time limits of the running timed search, if any.
+    time: Option<TimeManager>,
+    /// Whether the running search is pondering and has not seen the ponder hit yet.
+    /// Time limits are ignored until it has.
+    ponder_active: bool,
+    /// Ranked lines of the last completed iteration.
+    lines: Vec<PvLine>,
+    /// Root moves skipped by the current MultiPV pass.
+    excluded_root_moves: Vec<Move>,
+    /// Root moves keeping the Syzygy result, if the search is restricted to them.
+    root_filter: Vec<Move>,
+    /// Whether the current search noticed `stop` and is unwinding.
+    stopped: bool,
+    /// Best root move of the iteration in progress.
+    root_best: Option<Move>,
+    /// NNUE accumulators of the current line, indexed by ply.
+    accumulators: Vec<Accumulator>,
+    /// Randomness of weakened play, see [`Searcher::seed_skill`].
+    skill_rng: Rng,
+}
+
+impl Default for Searcher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Searcher {
+    /// Creates a single-threaded searcher with default options and a transposition table of [`DEFAULT_HASH_MB`] MB.
+    pub fn new() -> Self {
+        Searcher {
+            nodes: 0,
+            options: SearchOptions::default(),
+            tt: Arc::new(TranspositionTable::new(DEFAULT_HASH_MB)),
+            ordering: MoveOrdering::new(),
+            threads: 1,
+            stop: Arc::new(AtomicBool::new(false)),
+            pondering: Arc::new(AtomicBool::new(false)),
+            move_overhead_ms: DEFAULT_MOVE_OVERHEAD_MS,
+            node_limit: None,
+            skill: Skill::default(),
+            multi_pv: 1,
+            on_iteration: None,
+            network: None,
+            eval_params: None,
+            tablebase: None,
+            syzygy: None,
+            time: None,
+            ponder_active: false,
+            lines: Vec::new(),
+            excluded_root_moves: Vec::new(),
+            root_filter: Vec::new(),
+            stopped: false,
+            root_best: None,
+            accumulators: Vec::new(),
+            skill_rng: Rng::from_time(),
+        }
+    }
+
+    /// Creates a helper searcher sharing this searcher's options, transposition table and stop flag.
+    fn helper(&self) -> Searcher {
+        Searcher {
+            nodes: 0,
+            options: self.options,
+            tt: Arc::clone(&self.tt),
+            ordering: MoveOrdering::new(),
+            threads: 1,
+            stop: Arc::clone(&self.stop),
+            pondering: Arc::new(AtomicBool::new(false)),
+            move_overhead_ms: self.move_overhead_ms,
+            node_limit: None,
+            skill: Skill::default(),
+            multi_pv: 1,
+            on_iteration: None,
+            network: self.network.clone(),
+            eval_params: self.eval_params.clone(),
+            tablebase: self.tablebase.clone(),
+            syzygy: self.syzygy.clone(),
+            time: None,
+            ponder_active: false,
+            lines: Vec::new(),
+            excluded_root_moves: Vec::new(),
+            root_filter: self.root_filter.clone(),
+            stopped: false,
+            root_best: None,
+            accumulators: Vec::new(),
+            skill_rng: Rng::new(0),
+        }
+    }
+
+    /// Seeds the randomness of weakened play, so that games at a [`Skill`] level
+    /// below full strength can be reproduced. Unseeded searchers use the clock.
+    pub fn seed_skill(&mut self, seed: u64) {
+        self.skill_rng = Rng::new(seed);
+    }
+
+    /// Searches the position to a fixed depth and returns the best move.
+    ///
+    /// Runs iterative deepening from depth 1 up to `depth`, so that every
+    /// iteration starts from the best moves the previous one stored in the
+    /// transposition table, and (with aspiration windows) from a narrow window
+    /// around the previous score. Leaves of the main search are resolved with
+    /// [`Searcher::quiescence`], so pending captures are played out instead of
+    /// being cut off at the horizon.
+    ///
+    /// With `threads > 1` this is a *Lazy SMP* search: helper threads run their own
+    /// iterative deepening on the same position, filling the shared transposition
+    /// table, while the calling thread searches as usual. When the calling thread
+    /// finishes (or `stop` is raised) the helpers are stopped and the calling
+    /// thread's result is returned, with `nodes` summed over all threads.
+    /// A single-threaded search is fully deterministic.
+    ///
+    /// If `stop` is raised, the result of the last completed iteration is returned.
+    /// The flag is cleared again when the search returns.
+    ///
+    /// With `multi_pv > 1` the ranked lines are available from [`Searcher::lines`];
+    /// the result describes the best one.
+    ///
+    /// see: <https://www.chessprogramming.org/Lazy_SMP>
+    ///
+    /// # Arguments
+    /// * `position` - The position to search.
+    /// * `color` - The side to move.
+    /// * `depth` - Search depth in plies (`0` is treated as `1`).
+    pub fn search(&mut self, position: &Position, color: Color, depth: u32) -> SearchResult {
+        let result = self.search_threads(position, color, depth);
+        self.stop.store(false, Ordering::Relaxed);
+        result
+    }
+
+    /// Searches the top `count` root moves to a fixed depth.
+    ///
+    /// Shorthand for setting [`Searcher::multi_pv`] and calling [`Searcher::search`].
+    ///
+    /// # Returns
+    /// Up to `count` lines ranked best first (fewer if there are fewer legal moves).
+    pub fn search_multi_pv(&mut self, position: &Position, color: Color, depth: u32, count: usize) -> Vec<PvLine> {
+        let previous = self.multi_pv;
+        self.multi_pv = count;
+        self.search(position, color, depth);
+        self.multi_pv = previous;
+        self.lines.clone()
+    }
+
+    /// Ranked lines of the last completed iteration of the last search, best first.
+    pub fn lines(&self) -> &[PvLine] {
+        &self.lines
+    }
+
+    /// Searches with all threads, weakened according to [`Searcher::skill`].
+    ///
+    /// Below full strength the depth and node count are capped, enough MultiPV
+    /// lines are searched to choose from, and the chosen line is moved to the
+    /// front of [`Searcher::lines`].
+    fn search_threads(&mut self, position: &Position, color: Color, depth: u32) -> SearchResult {
+        if self.skill.is_full_strength() {
+            return self.search_parallel(position, color, depth);
+        }
+        let (multi_pv, node_limit) = (self.multi_pv, self.node_limit);
+        self.multi_pv = multi_pv.max(self.skill.candidates);
+        self.node_limit = match (node_limit, self.skill.node_limit) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+        let mut result = self.search_parallel(position, color, depth.min(self.skill.max_depth));
+        self.multi_pv = multi_pv;
+        self.node_limit = node_limit;
+
+        match self.skill.pick(&self.lines, position, color, &mut self.skill_rng) {
+            Some(SkillChoice::Line(i)) => {
+                let line = self.lines.remove(i);
+                result.best_move = Some(line.best_move);
+                result.score = line.score;
+                self.lines.insert(0, line);
+            }
+            Some(SkillChoice::Blunder(m, score)) => {
+                result.best_move = Some(m);
+                result.score = score;
+                self.lines = vec![PvLine { best_move: m, score, pv: vec![m] }];
+            }
+            None => {}
+        }
+        result
+    }
+
+    fn search_parallel(&mut self, position: &Position, color: Color, depth: u32) -> SearchResult {
+        if let Some(result) = self.tablebase_root(position, color) {
+            return result;
+        }
+        if let Some(result) = self.syzygy_root(position, color) {
+            return result;
+        }
+        // without DTZ files, still only search the moves keeping the WDL result
+        self.root_filter = self.syzygy.as_ref().and_then(|syzygy| syzygy.filter_root_moves(position, color)).unwrap_or_default();
+        if self.threads <= 1 {
+            return self.iterative_deepening(position, color, 1, depth.max(1));
+        }
+
+        let mut helpers: Vec<Searcher> = (1..self.threads).map(|_| self.helper()).collect();
+        thread::scope(|scope| {
+            let handles: Vec<_> = helpers
+                .iter_mut()
+                .enumerate()
+                .map(|(i, helper)| {
+                    // odd helpers skip the first iteration, so threads spread over different depths
+                    let first_depth = 1 + (i as u32 + 1) % 2;
+                    scope.spawn(move || helper.iterative_deepening(position, color, first_depth, MAX_PLY as u32 / 2))
+                })
+                .collect();
+
+            let mut result = self.iterative_deepening(position, color, 1, depth.max(1));
+            self.stop.store(true, Ordering::Relaxed);
+            for handle in handles {
+                result.nodes += handle.join().map_or(0, |r| r.nodes);
+            }
+            // leave the flag as the calling thread saw it, not as raised for the helpers
+            self.stop.store(self.stopped, Ordering::Relaxed);
+            result
+        })
+    }
+
+    /// Answers without searching if [`Searcher::tablebase`] covers the root position:
+    /// the lines are the root moves ranked by their tablebase values (fastest win first),
+    /// each followed by the opponent's best reply.
+    fn tablebase_root(&mut self, position: &Position, color: Color) -> Option<SearchResult> {
+        let tablebase = self.tablebase.as_ref()?;
+        let moves = tablebase.root_moves(position, color)?;
+        self.lines = moves
+            .iter()
+            .take(self.multi_pv.max(1))
+            .map(|&(m, value)| {
+                let mut pv = vec![m];
+                if let Some((reply, _)) = tablebase.best_move(&play(m, position), color.opponent()) {
+                    pv.push(reply);
+                }
+                PvLine { best_move: m, score: value.to_score(0), pv }
+            })
+            .collect();
+        if let Some(callback) = &mut self.on_iteration {
+            callback(&IterationInfo { depth: 1, lines: &self.lines, nodes: 0, elapsed: Duration::ZERO });
+        }
+        Some(SearchResult { best_move: Some(self.lines[0].best_move), score: self.lines[0].score, nodes: 0 })
+    }
+
+    /// Answers without searching if [`Searcher::syzygy`] has the WDL and DTZ files of
+    /// the root position: the lines are the root moves ranked by their results,
+    /// winning moves by the fewest plies to the next capture or pawn move.
+    fn syzygy_root(&mut self, position: &Position, color: Color) -> Option<SearchResult> {
+        let syzygy = self.syzygy.as_ref()?;
+        let moves = syzygy.root_moves(position, color)?;
+        self.lines = moves
+            .iter()
+            .take(self.multi_pv.max(1))
+            .map(|root| PvLine { best_move: root.m, score: root.wdl.to_score(1), pv: vec![root.m] })
+            .collect();
+        if let Some(callback) = &mut self.on_iteration {
+            callback(&IterationInfo { depth: 1, lines: &self.lines, nodes: 0, elapsed: Duration::ZERO });
+        }
+        Some(SearchResult { best_move: Some(self.lines[0].best_move), score: self.lines[0].score, nodes: 0 })
+    }
+
+    /// Searches the position for as long as the clock allows.
+    ///
+    /// A [`TimeManager`] allocates a soft and a hard time limit for this move
+    /// (reserving [`Searcher::move_overhead_ms`]). Iterative deepening keeps going
+    /// until an iteration completes after the soft limit, which is extended while
+    /// the best move is unstable or the score is dropping; the hard limit stops
+    /// the search mid-iteration. With only one legal move, it is returned
+    /// without searching.
+    ///
+    /// If `control` gives no time for `color`, the search runs until `stop` is raised.
+    ///
+    /// # Arguments
+    /// * `position` - The position to search.
+    /// * `color` - The side to move.
+    /// * `control` - Clock state, as given by the UCI `go` command.
+    pub fn search_timed(&mut self, position: &Position, color: Color, control: &TimeControl) -> SearchResult {
+        let moves = legal_moves(color, position);
+        if moves.len() == 1 {
+            self.lines = vec![PvLine { best_move: moves[0], score: 0, pv: vec![moves[0]] }];
+            return SearchResult { best_move: Some(moves[0]), score: 0, nodes: 0 };
+        }
+        self.time = TimeManager::new(control, color, self.move_overhead_ms);
+        let result = self.search(position, color, MAX_PLY as u32 / 2);
+        self.time = None;
+        result
+    }
+
+    /// Searches the position the opponent is expected to reach, on the opponent's time.
+    ///
+    /// Call this with the position after the move the previous search expected
+    /// (the second move of its PV, see [`Searcher::ponder_move`]) and the clock as it
+    /// would be for that position. Until the ponder hit, the search ignores all time
+    /// limits; this method does not return before either:
+    /// - the opponent played the expected move: store `false` in [`Searcher::pondering`].
+    ///   The search becomes a normal [`Searcher::search_timed`] search, with the clock
+    ///   started at the ponder hit, and everything searched so far is kept.
+    /// - the opponent played another move: raise [`Searcher::stop`] and discard the result.
+    ///   The transposition table keeps what was learned, which helps the next search.
+    ///
+    /// see: <https://www.chessprogramming.org/Pondering>
+    pub fn search_ponder(&mut self, position: &Position, color: Color, control: &TimeControl) -> SearchResult {
+        self.pondering.store(true, Ordering::Relaxed);
+        self.ponder_active = true;
+
+        let moves = legal_moves(color, position);
+        let result = if moves.len() == 1 {
+            self.lines = vec![PvLine { best_move: moves[0], score: 0, pv: vec![moves[0]] }];
+            SearchResult { best_move: Some(moves[0]), score: 0, nodes: 0 }
+        } else {
+            self.time = TimeManager::new(control, color, self.move_overhead_ms);
+            let result = self.search_threads(position, color, MAX_PLY as u32 / 2);
+            self.time = None;
+            result
+        };
+
+        // the search may finish early (e.g. a forced mate); the result is only due after the ponder hit
+        while !self.stopped && self.pondering.load(Ordering::Relaxed) && !self.stop.load(Ordering::Relaxed) {
+            thread::sleep(Duration::from_millis(1));
+        }
+        self.pondering.store(false, Ordering::Relaxed);
+        self.ponder_active = false;
+        self.stop.store(false, Ordering::Relaxed);
+        result
+    }
+
+    /// The opponent's expected reply after the last search's best move, used for pondering.
+    pub fn ponder_move(&self) -> Option<Move> {
+        self.lines.first().and_then(|line| line.pv.get(1).copied())
+    }
+
+    /// Notices a ponder hit: from then on time limits apply, counted from now.
+    fn check_ponderhit(&mut self) {
+        if self.ponder_active && !self.pondering.load(Ordering::Relaxed) {
+            self.ponder_active = false;
+            if let Some(time) = &mut self.time {
+                time.restart();
+            }
+        }
+    }
+
+    /// Runs iterative deepening from `first_depth` to `max_depth`, until done or stopped.
+    ///
+    /// Each iteration searches the root [`Searcher::multi_pv`] times; every pass
+    /// excludes the root moves found by the previous passes.
+    fn iterative_deepening(&mut self, position: &Position, color: Color, first_depth: u32, max_depth: u32) -> SearchResult {
+        let start = Instant::now();
+        self.nodes = 0;
+        self.stopped = false;
+
+        if let Some(result) = variant_result(color, position) {
+            self.lines.clear();
+            return SearchResult { best_move: None, score: result_score(result, color, 0), nodes: 1 };
+        }
+        let mut moves = legal_moves(color, position);
+        if !self.root_filter.is_empty() {
+            moves.retain(|m| self.root_filter.iter().any(|allowed| allowed.is_same(m)));
+        }
+        if moves.is_empty() {
+            self.lines.clear();
+            let score = result_score(no_moves_result(color, position), color, 0);
+            return SearchResult { best_move: None, score, nodes: 1 };
+        }
+        let line_count = self.multi_pv.clamp(1, moves.len());
+
+        // fallback in case the search is stopped before the first iteration completes
+        self.lines = vec![PvLine { best_move: moves[0], score: 0, pv: vec![moves[0]] }];
+        for current_depth in first_depth..=max_depth {
+            let mut lines: Vec<PvLine> = Vec::with_capacity(line_count);
+            self.excluded_root_moves.clear();
+            for k in 0..line_count {
+                let previous_score = self.lines.get(k).map_or(0, |line| line.score);
+                let score = self.aspiration_search(position, color, current_depth, previous_score);
+                if self.stopped {
+                    break;
+                }
+                let Some(best) = self.root_best else { break };
+                let pv = self.principal_variation(position, color, best);
+                lines.push(PvLine { best_move: best, score, pv });
+                self.excluded_root_moves.push(best);
+            }
+            self.excluded_root_moves.clear();
+            if self.stopped || lines.is_empty() {
+                break;
+            }
+            lines.sort_by_key(|line| -line.score);
+
+            let best_move_changed = current_depth > first_depth && !lines[0].best_move.is_same(&self.lines[0].best_move);
+            let score_drop = self.lines[0].score - lines[0].score;
+            self.lines = lines;
+
+            if let Some(callback) = &mut self.on_iteration {
+                callback(&IterationInfo { depth: current_depth, lines: &self.lines, nodes: self.nodes, elapsed: start.elapsed() });
+            }
+            self.check_ponderhit();
+            if let Some(time) = &mut self.time {
+                time.update(best_move_changed, score_drop);
+                if !self.ponder_active && time.soft_limit_reached() {
+                    break;
+                }
+            }
+        }
+        SearchResult { best_move: Some(self.lines[0].best_move), score: self.lines[0].score, nodes: self.nodes }
+    }
+
+    /// Follows the best moves stored in the transposition table, starting with `first`.
+    fn principal_variation(&self, position: &Position, color: Color, first: Move) -> Vec<Move> {
+        let mut pv = vec![first];
+        let mut current = play(first, position);
+        let mut side = color.opponent();
+        let mut seen = vec![zobrist_key(position, color)];
+
+        while pv.len() < MAX_PLY {
+            let key = zobrist_key(&current, side);
+            if seen.contains(&key) {
+                break;
+            }
+            seen.push(key);
+            let Some(m) = self.tt.probe(key, 0).and_then(|e| e.best_move) else { break };
+            if !is_legal(m, side, &current) {
+                break;
+            }
+            pv.push(m);
+            current = play(m, &current);
+            side = side.opponent();
+        }
+        pv
+    }
+
+    /// Statically evaluates a position with the network if one is set, the classical evaluation otherwise.
+    fn static_eval(&self, position: &Position, color: Color, ply: i32) -> i32 {
+        match &self.network {
+            // the network only sees the board; Crazyhouse pockets and variant terms are added on top
+            Some(network) if position.variant != Variant::Antichess => {
+                network.evaluate(&self.accumulators[ply as usize], color)
+                    + pocket_material(color, position)
+                    + variant_bonus(color, position)
+            }
+            _ => evaluate_with(self.eval_params.as_deref().unwrap_or(&DEFAULT_PARAMS), color, position),
+        }
+    }
+
+    /// Computes the accumulator of `position` at `ply` from scratch.
+    fn refresh_accumulator(&mut self, position: &Position, ply: i32) {
+        let Some(network) = &self.network else { return };
+        let accumulator = Accumulator::new(network, position);
+        let ply = ply as usize;
+        if self.accumulators.len() <= ply {
+            self.accumulators.resize(ply + 1, accumulator);
+        } else {
+            self.accumulators[ply] = accumulator;
+        }
+    }
+
+    /// Derives the accumulator of `child` at `ply + 1` from the one of `parent` at `ply`.
+    fn update_accumulator(&mut self, parent: &Position, child: &Position, ply: i32) {
+        let Some(network) = &self.network else { return };
+        let ply = ply as usize;
+        if self.accumulators.len() <= ply + 1 {
+            let copy = self.accumulators[ply].clone();
+            self.accumulators.resize(ply + 2, copy);
+        }
+        let (head, tail) = self.accumulators.split_at_mut(ply + 1);
+        tail[0].clone_from(&head[ply]);
+        tail[0].update(network, parent, child);
+    }
+
+    /// Plays `m` like [`play`], keeping the NNUE accumulators in sync.
+    fn play_child(&mut self, m: Move, position: &Position, ply: i32) -> Position {
+        let child = play(m, position);
+        self.update_accumulator(position, &child, ply);
+        child
+    }
+
+    /// Checks the stop flag (and the hard time limit) every few thousand nodes.
+    /// Returns `true` once the search must unwind.
+    fn should_stop(&mut self) -> bool {
+        if !self.stopped && self.node_limit.is_some_and(|limit| self.nodes >= limit) {
+            // also stops helper threads
+            self.stop.store(true, Ordering::Relaxed);
+            self.stopped = true;
+        }
+        if !self.stopped && self.nodes.is_multiple_of(2048) {
+            self.check_ponderhit();
+            if !self.ponder_active && self.time.as_ref().is_some_and(|time| time.hard_limit_reached()) {
+                // also stops helper threads
+                self.stop.store(true, Ordering::Relaxed);
+            }
+            self.stopped = self.stop.load(Ordering::Relaxed);
+        }
+        self.stopped
+    }
+
+    /// Searches the root at `depth`, starting with a narrow window around `previous_score`
+    /// if aspiration windows are enabled, and widening it until the score falls inside.
+    fn aspiration_search(&mut self, position: &Position, color: Color, depth: u32, previous_score: i32) -> i32 {
+        let mut delta = ASPIRATION_WINDOW;
+        let (mut alpha, mut beta) = if self.options.aspiration_windows && depth >= 4 {
+            ((previous_score - delta).max(-INFINITY), (previous_score + delta).min(INFINITY))
+        } else {
+            (-INFINITY, INFINITY)
+        };
+
+        loop {
+            self.root_best = None;
+            let score = self.alpha_beta(position, color, depth, 0, alpha, beta, None, true);
+            if self.stopped {
+                return score;
+            }
+            if score <= alpha && alpha > -INFINITY {
+                delta *= 2;
+                alpha = (score - delta).max(-INFINITY);
+            } else if score >= beta && beta < INFINITY {
+                delta *= 2;
+                beta = (score + delta).min(INFINITY);
+            } else {
+                return score;
+            }
+            if delta > 1000 {
+                alpha = -INFINITY;
+                beta = INFINITY;
+            }
+        }
+    }
+
+    /// Negamax alpha-beta search with principal variation search, pruning and reductions.
+    ///
+    /// Returns a fail-hard score within `[alpha, beta]` from the point of view of `color`.
+    /// At the root (`ply == 0`) the best move is recorded in `root_best`.
+    /// `null_allowed` is `false` right after a null move, so two never follow each other.
+    #[allow(clippy::too_many_arguments)]
+    fn alpha_beta(
+        &mut self,
+        position: &Position,
+        color: Color,
+        mut depth: u32,
+        ply: i32,
+        mut alpha: i32,
+        beta: i32,
+        previous: Option<Move>,
+        null_allowed: bool,
+    ) -> i32 {
+        let in_check = is_checked(color, position);
+        if in_check && self.options.check_extensions && (ply as usize) < MAX_PLY / 2 {
+            depth += 1;
+        }
+        if ply == 0 {
+            self.refresh_accumulator(position, ply);
+        }
+        if depth == 0 {
+            return self.quiescence_inner(position, color, alpha, beta, ply, 0);
+        }
+        self.nodes += 1;
+        if self.should_stop() {
+            return 0;
+        }
+        if let Some(result) = variant_result(color, position) {
+            return result_score(result, color, ply).clamp(alpha, beta);
+        }
+        if ply as usize >= MAX_PLY - 1 {
+            return self.static_eval(position, color, ply).clamp(alpha, beta);
+        }
+
+        if ply > 0
+            && let Some(tablebase) = &self.tablebase
+            && let Some(value) = tablebase.probe(position, color)
+        {
+            return value.to_score(ply).clamp(alpha, beta);
+        }
+        if ply > 0
+            && let Some(syzygy) = &self.syzygy
+            && let Some(wdl) = syzygy.probe_wdl(position, color)
+        {
+            return wdl.to_score(ply).clamp(alpha, beta);
+        }
+
+        let key = zobrist_key(position, color);
+        let entry = self.tt.probe(key, ply);
+        if let Some(e) = entry
+            && ply > 0
+            && e.depth >= depth
+        {
+            match e.bound {
+                Bound::Exact => return e.score.clamp(alpha, beta),
+                Bound::Lower if e.score >= beta => return beta,
+                Bound::Upper if e.score <= alpha => return alpha,
+                _ => {}
+            }
+        }
+        let hash_move = entry.and_then(|e| e.best_move);
+
+        let pv_node = beta - alpha > 1;
+        let static_eval = if in_check { -INFINITY } else { self.static_eval(position, color, ply) };
+
+        // reverse futility pruning
+        if self.options.reverse_futility
+            && !pv_node
+            && !in_check
+            && depth <= 3
+            && !is_mate_score(beta)
+            && static_eval - REVERSE_FUTILITY_MARGIN * depth as i32 >= beta
+        {
+            return beta;
+        }
+
+        // null-move pruning
+        if self.options.null_move
+            && null_allowed
+            && !pv_node
+            && !in_check
+            && depth >= 3
+            && static_eval >= beta
+            && has_non_pawn_material(color, position)
+            && position.variant != Variant::Antichess
+        {
+            let reduction = if depth > 6 { 3 } else { 2 };
+            let mut child = *position;
+            child.en_passant = None;
+            self.update_accumulator(position, &child, ply);
+            let score = -self.alpha_beta(&child, color.opponent(), depth - 1 - reduction, ply + 1, -beta, -beta + 1, None, false);
+            if self.stopped {
+                return 0;
+            }
+            if score >= beta {
+                return beta;
+            }
+        }
+
+        let futility_pruning = self.options.futility
+            && !pv_node
+            && !in_check
+            && depth <= 3
+            && !is_mate_score(alpha)
+            && static_eval + FUTILITY_MARGINS[depth as usize] <= alpha;
+
+        let original_alpha = alpha;
+        let mut best_move = None;
+        let mut tried_quiets = Vec::new();
+        let mut picker = MovePicker::new(position, color, hash_move, &self.ordering, ply as usize, previous);
+        let mut any_move = false;
+        let mut moves_searched = 0u32;
+
+        while let Some(m) = picker.next(&self.ordering) {
+            if ply == 0 && self.excluded_root_moves.iter().any(|e| e.is_same(&m)) {
+                continue;
+            }
+            if ply == 0 && !self.root_filter.is_empty() && !self.root_filter.iter().any(|allowed| allowed.is_same(&m)) {
+                continue;
+            }
+            any_move = true;
+            let is_quiet = !m.promoted_from_pawn && captured_piece(&m, position).is_none();
+            let child = self.play_child(m, position, ply);
+            let gives_check = is_checked(color.opponent(), &child);
+
+            if futility_pruning && moves_searched > 0 && is_quiet && !gives_check {
+                continue;
+            }
+
+            let new_depth = depth - 1;
+            let mut score;
+            if moves_searched == 0 {
+                score = -self.alpha_beta(&child, color.opponent(), new_depth, ply + 1, -beta, -alpha, Some(m), true);
+            } else {
+                // window used for moves after the first: null window with PVS, full window without
+                let scout_alpha = if self.options.pvs { -alpha - 1 } else { -beta };
+
+                let reduction = if self.options.late_move_reductions
+                    && depth >= 3
+                    && moves_searched >= 3
+                    && is_quiet
+                    && !in_check
+                    && !gives_check
+                {
+                    late_move_reduction(depth, moves_searched).min(new_depth - 1)
+                } else {
+                    0
+                };
+
+                score = alpha + 1;
+                if reduction > 0 {
+                    score = -self.alpha_beta(&child, color.opponent(), new_depth - reduction, ply + 1, scout_alpha, -alpha, Some(m), true);
+                }
+                if score > alpha {
+                    score = -self.alpha_beta(&child, color.opponent(), new_depth, ply + 1, scout_alpha, -alpha, Some(m), true);
+                    if self.options.pvs && score > alpha && score < beta {
+                        score = -self.alpha_beta(&child, color.opponent(), new_depth, ply + 1, -beta, -alpha, Some(m), true);
+                    }
+                }
+            }
+            moves_searched += 1;
+            if self.stopped {
+                return 0;
+            }
+
+            if score >= beta {
+                if is_quiet {
+                    self.ordering.record_cutoff(m, &tried_quiets, previous, depth, ply as usize);
+                }
+                if ply > 0 || self.excluded_root_moves.is_empty() {
+                    self.tt.store(key, Some(m), beta, depth, Bound::Lower, ply);
+                }
+                if ply == 0 {
+                    self.root_best = Some(m);
+                }
+                return beta;
+            }
+            if score > alpha || best_move.is_none() {
+                if score > alpha {
+                    alpha = score;
+                }
+                best_move = Some(m);
+                if ply == 0 {
+                    self.root_best = Some(m);
+                }
+            }
+            if is_quiet {
+                tried_quiets.push(m);
+            }
+        }
+
+        if !any_move {
+            return result_score(no_moves_result(color, position), color, ply);
+        }
+
+        // later MultiPV passes search a restricted root; keep the full search's entry
+        if ply > 0 || self.excluded_root_moves.is_empty() {
+            let bound = if alpha > original_alpha { Bound::Exact } else { Bound::Upper };
+            self.tt.store(key, best_move, alpha, depth, bound, ply);
+        }
+        alpha
+    }
+
+    /// Quiescence search: only captures and promotions are searched until the position is quiet.
+    ///
+    /// - **Stand pat**: the static evaluation is a lower bound, since the side to move
+    ///   is never forced to capture. If it already reaches `beta` the node fails high.
+    /// - **Delta pruning**: captures that cannot raise the score to `alpha` even with a
+    ///   safety margin ([`DELTA_MARGIN`]) are skipped, as are captures losing material by [`see`](crate::see::see).
+    /// - When in check, all evasions are searched instead and standing pat is not allowed.
+    ///
+    /// # Arguments
+    /// * `position` - The position to search.
+    /// * `color` - The side to move.
+    /// * `alpha`, `beta` - The search window.
+    /// * `ply` - Distance from the root, used for mate scores.
+    ///
+    /// # Returns
+    /// A fail-hard score within `[alpha, beta]` from the point of view of `color`.
+    pub fn quiescence(&mut self, position: &Position, color: Color, alpha: i32, beta: i32, ply: i32) -> i32 {
+        self.refresh_accumulator(position, ply);
+        self.quiescence_inner(position, color, alpha, beta, ply, 0)
+    }
+
+    fn quiescence_inner(&mut self, position: &Position, color: Color, mut alpha: i32, beta: i32, ply: i32, qply: u32) -> i32 {
+        self.nodes += 1;
+        if self.should_stop() {
+            return 0;
+        }
+        if let Some(result) = variant_result(color, position) {
+            return result_score(result, color, ply).clamp(alpha, beta);
+        }
+
+        if is_checked(color, position) {
+            let moves = legal_moves(color, position);
+            if moves.is_empty() {
+                return -MATE_SCORE + ply;
+            }
+            for m in moves {
+                let child = self.play_child(m, position, ply);
+                let score = -self.quiescence_inner(&child, color.opponent(), -beta, -alpha, ply + 1, qply + 1);
+                if score >= beta {
+                    return beta;
+                }
+                if score > alpha {
+                    alpha = score;
+                }
+            }
+            return alpha;
+        }
+
+        let stand_pat = self.static_eval(position, color, ply);
+        if stand_pat >= beta {
+            return beta;
+        }
+        if stand_pat > alpha {
+            alpha = stand_pat;
+        }
+
+        // losing captures (by `see`) are never returned by a captures-only picker
+        let mut picker = MovePicker::captures(position, color);
+        while let Some(m) = picker.next(&self.ordering) {
+            // delta pruning
+            let mut gain = captured_piece(&m, position).map_or(0, piece_value);
+            if m.promoted_from_pawn {
+                gain += piece_value(m.piece) - PIECE_VALUES[Pieces::PAWN];
+            }
+            if stand_pat + gain + DELTA_MARGIN < alpha {
+                continue;
+            }
+
+            let child = self.play_child(m, position, ply);
+            let score = -self.quiescence_inner(&child, color.opponent(), -beta, -alpha, ply + 1, qply + 1);
+            if score >= beta {
+                return beta;
+            }
+            if score > alpha {
+                alpha = score;
+            }
+        }
+
+        if self.options.quiescence_checks && qply == 0 {
+            for m in checking_moves(color, position) {
+                let child = self.play_child(m, position, ply);
+                let score = -self.quiescence_inner(&child, color.opponent(), -beta, -alpha, ply + 1, qply + 1);
+                if score >= beta {
+                    return beta;
+                }
+                if score > alpha {
+                    alpha = score;
+                }
+            }
+        }
+        alpha
+    }
+}
+
+/// Searches the position to a fixed depth with a fresh [`Searcher`].
+///
+/// # Arguments
+/// * `position` - The position to search.
+/// * `color` - The side to move.
+/// * `depth` - Search depth in plies.
+///
+/// # Returns
+/// A [`SearchResult`] with the best move and its score.
+pub fn search(position: &Position, color: Color, depth: u32) -> SearchResult {
+    Searcher::new().search(position, color, depth)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::eval::evaluate;
+    use std::time::{Duration, Instant};
+    use crate::fen::{START_FEN, parse_fen};
+    use crate::helper::index_to_square;
+    use crate::make_move::is_legal;
+
+    /// The squares of a move, e.g. `e2e4`.
+    fn move_name(m: &Move) -> String {
+        format!("{}{}", index_to_square(m.from), index_to_square(m.to)).to_lowercase()
+    }
+
+    fn quiescence_of(fen: &str) -> (i32, i32) {
+        let (position, color) = parse_fen(fen).unwrap();
+        let score = Searcher::new().quiescence(&position, color, -INFINITY, INFINITY, 0);
+        (score, evaluate(color, &position))
+    }
+
+    #[test]
+    fn quiescence_takes_hanging_piece() {
+        let (score, static_eval) = quiescence_of("4k3/8/8/3r4/8/8/3Q4/4K3 w - - 0 1");
+        assert!(score >= static_eval + 400, "{} vs {}", score, static_eval);
+    }
+
+    #[test]
+    fn quiescence_stands_pat_on_defended_pawn() {
+        // Qxd5 cxd5 would lose the queen
+        let (score, static_eval) = quiescence_of("4k3/8/2p5/3p4/8/8/3Q4/4K3 w - - 0 1");
+        assert_eq!(score, static_eval);
+    }
+
+    #[test]
+    fn search_avoids_poisoned_pawn() {
+        let (position, color) = parse_fen("4k3/8/2p5/3p4/8/8/3Q4/4K3 w - - 0 1").unwrap();
+        let result = search(&position, color, 2);
+        let best = result.best_move.unwrap();
+        assert!(!(best.from == 11 && best.to == 35), "queen takes the defended pawn");
+    }
+
+    #[test]
+    fn search_options_change_the_tree_but_not_the_answer() {
+        type Toggle = fn(&mut SearchOptions);
+        let toggles: [(&str, Toggle); 6] = [
+            ("null move", |options| options.null_move = false),
+            ("late move reductions", |options| options.late_move_reductions = false),
+            ("futility", |options| options.futility = false),
+            ("reverse futility", |options| options.reverse_futility = false),
+            ("aspiration windows", |options| options.aspiration_windows = false),
+            ("pvs", |options| options.pvs = false),
+        ];
+        // Nxg5 wins the queen; Qa2+ mates in three
+        let positions = [
+            ("r1b1kbnr/pppp1ppp/2n5/4p1q1/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 0 1", 5, "f3g5"),
+            ("6k1/pp4pp/8/8/8/8/5PPP/1Q1R2K1 w - - 0 1", 5, "b1a2"),
+        ];
+        let run = |toggle: Option<Toggle>| {
+            positions.map(|(fen, depth, _)| {
+                let (position, color) = parse_fen(fen).unwrap();
+                let mut searcher = Searcher::new();
+                if let Some(toggle) = toggle {
+                    toggle(&mut searcher.options);
+                }
+                searcher.search(&position, color, depth)
+            })
+        };
+
+        let full = run(None);
+        for (result, (_, _, best)) in full.iter().zip(positions) {
+            assert_eq!(move_name(&result.best_move.unwrap()), best);
+        }
+        assert_eq!(full[1].score, MATE_SCORE - 5);
+        for (name, toggle) in toggles {
+            let reduced = run(Some(toggle));
+            for (result, expected) in reduced.iter().zip(&full) {
+                assert!(result.best_move.unwrap().is_same(&expected.best_move.unwrap()), "{}", name);
+                assert_eq!(result.score, expected.score, "{}", name);
+            }
+            let nodes = |results: &[SearchResult; 2]| results.iter().map(|r| r.nodes).collect::<Vec<_>>();
+            assert_ne!(nodes(&reduced), nodes(&full), "{} changes nothing", name);
+        }
+    }
+
+    const MIDDLEGAME: &str = "r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4";
+
+    #[test]
+    fn single_thread_search_is_deterministic() {
+        let (position, color) = parse_fen(MIDDLEGAME).unwrap();
+        let mut searcher = Searcher::new();
+        let first = searcher.search(&position, color, 5);
+        searcher.tt.clear();
+        searcher.ordering.clear();
+        let second = searcher.search(&position, color, 5);
+        assert!(first.best_move.unwrap().is_same(&second.best_move.unwrap()));
+        assert_eq!(first.score, second.score);
+        assert_eq!(first.nodes, second.nodes);
+        // a fresh searcher agrees as well
+        let third = Searcher::new().search(&position, color, 5);
+        assert_eq!(first.nodes, third.nodes);
+    }
+
+    #[test]
+    fn multi_pv_lines_are_distinct_sorted_and_capped() {
+        let (position, color) = parse_fen(MIDDLEGAME).unwrap();
+        let single = Searcher::new().search(&position, color, 4);
+        let mut searcher = Searcher::new();
+        let lines = searcher.search_multi_pv(&position, color, 4, 3);
+        assert_eq!(lines.len(), 3);
+        for (i, line) in lines.iter().enumerate() {
+            assert!(is_legal(line.best_move, color, &position));
+            assert!(line.pv[0].is_same(&line.best_move));
+            assert!(lines[..i].iter().all(|other| !other.best_move.is_same(&line.best_move)));
+        }
+        assert!(lines.windows(2).all(|pair| pair[0].score >= pair[1].score));
+        // the later passes leave the main line and its root entry alone
+        assert!(lines[0].best_move.is_same(&single.best_move.unwrap()));
+        assert_eq!(lines[0].score, single.score);
+        let entry = searcher.tt.probe(zobrist_key(&position, color), 0).unwrap();
+        assert!(entry.best_move.unwrap().is_same(&lines[0].best_move));
+
+        // never more lines than legal moves
+        let (position, color) = parse_fen("k7/8/8/8/8/8/1r6/K7 w - - 0 1").unwrap();
+        assert_eq!(searcher.search_multi_pv(&position, color, 3, 10).len(), 1);
+        let (position, color) = parse_fen("7k/8/8/8/8/8/8/K7 w - - 0 1").unwrap();
+        let lines = searcher.search_multi_pv(&position, color, 3, 10);
+        assert_eq!(lines.len(), legal_moves(color, &position).len());
+        assert_eq!(searcher.multi_pv, 1);
+    }
+
+    #[test]
+    fn multi_threaded_search_returns_legal_move() {
+        let (position, color) = parse_fen(MIDDLEGAME).unwrap();
+        let mut searcher = Searcher::new();
+        searcher.threads = 4;
+        let result = searcher.search(&position, color, 4);
+        assert!(is_legal(result.best_move.unwrap(), color, &position));
+    }
+
+    #[test]
+    fn multi_threaded_search_stops_promptly() {
+        let (position, color) = parse_fen(START_FEN).unwrap();
+        let mut searcher = Searcher::new();
+        searcher.threads = 4;
+        let stop = Arc::clone(&searcher.stop);
+        let stopper = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(100));
+            stop.store(true, Ordering::Relaxed);
+        });
+        let start = Instant::now();
+        let result = searcher.search(&position, color, 60);
+        stopper.join().unwrap();
+        assert!(start.elapsed() < Duration::from_secs(2), "took {:?}", start.elapsed());
+        assert!(is_legal(result.best_move.unwrap(), color, &position));
+        // the flag is cleared for the next search
+        assert!(!searcher.stop.load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn timed_search_plays_a_single_reply_at_once() {
+        // Kxb2 is the only legal move
+        let (position, color) = parse_fen("k7/8/8/8/8/8/1r6/K7 w - - 0 1").unwrap();
+        let control = TimeControl { wtime: Some(600_000), btime: Some(600_000), ..Default::default() };
+        let mut searcher = Searcher::new();
+        let start = Instant::now();
+        let result = searcher.search_timed(&position, color, &control);
+        assert!(start.elapsed() < Duration::from_millis(100));
+        assert_eq!(move_name(&result.best_move.unwrap()), "a1b2");
+        assert_eq!(result.nodes, 0);
+        assert_eq!(searcher.lines().len(), 1);
+
+        // with a choice, the clock is used
+        let (position, color) = parse_fen(START_FEN).unwrap();
+        let control = TimeControl { movetime: Some(200), ..Default::default() };
+        let result = searcher.search_timed(&position, color, &control);
+        assert!(result.nodes > 0);
+        assert!(is_legal(result.best_move.unwrap(), color, &position));
+    }
+}
@@ -0,0 +1,217 @@
+use crate::eval::{PIECE_VALUES, piece_value};
+use crate::moves::{Move, captured_piece};
+use crate::piece::Color;
+use crate::position::{Pieces, Position};
+
+// see: https://www.chessprogramming.org/Static_Exchange_Evaluation
+
+const ROOK_DIRECTIONS: [(i8, i8); 4] = [(1, 0), (-1, 0), (0, 1), (0, -1)];
+const BISHOP_DIRECTIONS: [(i8, i8); 4] = [(1, 1), (1, -1), (-1, 1), (-1, -1)];
+const KNIGHT_JUMPS: [(i8, i8); 8] = [
+    (1, 2), (2, 1), (2, -1), (1, -2), (-1, -2), (-2, -1), (-2, 1), (-1, 2),
+];
+const KING_STEPS: [(i8, i8); 8] = [
+    (1, 0), (1, 1), (0, 1), (-1, 1), (-1, 0), (-1, -1), (0, -1), (1, -1),
+];
+
+/// Returns the square reached by stepping `(file, rank)` from `square`, or `None` if it leaves the board.
+fn offset(square: u8, (file_step, rank_step): (i8, i8)) -> Option<u8> {
+    let file = (square % 8) as i8 + file_step;
+    let rank = (square / 8) as i8 + rank_step;
+    if (0..8).contains(&file) && (0..8).contains(&rank) {
+        Some((rank * 8 + file) as u8)
+    } else {
+        None
+    }
+}
+
+/// Returns a bitboard of the squares reached by single steps from `square`.
+fn step_attacks(square: u8, steps: &[(i8, i8)]) -> u64 {
+    steps
+        .iter()
+        .filter_map(|&step| offset(square, step))
+        .fold(0u64, |acc, sq| acc | (1u64 << sq))
+}
+
+/// Returns a bitboard of the squares a slider on `square` sees along `directions`.
+///
+/// Each ray stops at (and includes) the first square set in `occupied`.
+fn slider_attacks(square: u8, directions: &[(i8, i8)], occupied: u64) -> u64 {
+    let mut attacks = 0u64;
+    for &dir in directions {
+        let mut current = square;
+        while let Some(next) = offset(current, dir) {
+            attacks |= 1u64 << next;
+            if occupied & (1u64 << next) != 0 {
+                break;
+            }
+            current = next;
+        }
+    }
+    attacks
+}
+
+/// Returns all pieces of both colors that attack `square`, given the occupancy `occupied`.
+///
+/// Pieces that are not in `occupied` are ignored and do not block rays. Removing
+/// an attacker from `occupied` therefore uncovers any slider standing behind it
+/// (an *x-ray* attacker), which is what [`see`] relies on.
+///
+/// # Arguments
+/// * `position` - The board state providing piece locations.
+/// * `square` - The attacked square (0–63).
+/// * `occupied` - The occupancy to use for blocking and for filtering attackers.
+///
+/// # Returns
+/// A bitboard of attacking pieces.
+pub fn attackers_to(position: &Position, square: u8, occupied: u64) -> u64 {
+    let pieces = &position.bb_pieces;
+    let both = |piece_type: usize| pieces[0][piece_type].0 | pieces[1][piece_type].0;
+
+    // a white pawn attacks `square` if it sits diagonally below it, a black pawn if above
+    let white_pawns = step_attacks(square, &[(-1, -1), (1, -1)]) & pieces[0][Pieces::PAWN].0;
+    let black_pawns = step_attacks(square, &[(-1, 1), (1, 1)]) & pieces[1][Pieces::PAWN].0;
+    let knights = step_attacks(square, &KNIGHT_JUMPS) & both(Pieces::KNIGHT);
+    let kings = step_attacks(square, &KING_STEPS) & both(Pieces::KING);
+    let rooks = slider_attacks(square, &ROOK_DIRECTIONS, occupied)
+        & (both(Pieces::ROOK) | both(Pieces::QUEEN));
+    let bishops = slider_attacks(square, &BISHOP_DIRECTIONS, occupied)
+        & (both(Pieces::BISHOP) | both(Pieces::QUEEN));
+
+    (white_pawns | black_pawns | knights | kings | rooks | bishops) & occupied
+}
+
+/// Finds the least valuable piece of `color` within `attackers`.
+///
+/// # Returns
+/// `Some((square, piece_type))`, or `None` if `color` has no piece in `attackers`.
+fn least_valuable_attacker(position: &Position, attackers: u64, color: Color) -> Option<(u8, usize)> {
+    for piece_type in 0..6 {
+        let subset = attackers & position.bb_pieces[color.index()][piece_type].0;
+        if subset != 0 {
+            return Some((subset.trailing_zeros() as u8, piece_type));
+        }
+    }
+    None
+}
+
+/// Static exchange evaluation of a move.
+///
+/// Plays out the whole capture sequence on the move's target square, with both
+/// sides always recapturing with their least valuable piece and free to stop
+/// whenever continuing would lose material. Sliders hidden behind other
+/// attackers (x-rays) join the exchange as soon as the piece in front of them
+/// has captured.
+///
+/// # Arguments
+/// * `position` - Board state before the move is made.
+/// * `m` - The move to evaluate. Quiet moves are evaluated as well, which tells
+///   whether the moved piece can safely stand on its target square.
+///
+/// # Returns
+/// The expected material gain for the moving side in centipawns
+/// (negative if the exchange loses material).
+pub fn see(position: &Position, m: Move) -> i32 {
+    let mut gain = [0i32; 32];
+    let mut depth = 0;
+
+    let mut occupied = position.bb_sides[0].0 | position.bb_sides[1].0;
+    let mut from_bb = 1u64 << m.from;
+    let mut side = m.piece.color();
+
+    let captured = captured_piece(&m, position);
+    gain[0] = captured.map_or(0, piece_value);
+    if m.promoted_from_pawn {
+        gain[0] += piece_value(m.piece) - PIECE_VALUES[Pieces::PAWN];
+    }
+    if captured.is_some() && occupied & (1u64 << m.to) == 0 {
+        // en passant: the captured pawn is behind the target square
+        let captured_square = match side {
+            Color::White => m.to - 8,
+            Color::Black => m.to + 8,
+        };
+        occupied &= !(1u64 << captured_square);
+    }
+    // value of the piece currently standing on the target square
+    let mut attacker_value = piece_value(m.piece);
+
+    loop {
+        depth += 1;
+        // speculative score if the piece on the target square is captured next
+        gain[depth] = attacker_value - gain[depth - 1];
+        if (-gain[depth - 1]).max(gain[depth]) < 0 {
+            break;
+        }
+
+        occupied &= !from_bb;
+        side = side.opponent();
+
+        // recompute attackers so sliders behind the piece that just moved are uncovered
+        let attackers = attackers_to(position, m.to, occupied);
+        match least_valuable_attacker(position, attackers, side) {
+            Some((square, piece_type)) => {
+                from_bb = 1u64 << square;
+                attacker_value = PIECE_VALUES[piece_type];
+            }
+            None => break,
+        }
+        if depth == gain.len() - 1 {
+            break;
+        }
+    }
+
+    while depth > 1 {
+        depth -= 1;
+        gain[depth - 1] = -(-gain[depth - 1]).max(gain[depth]);
+    }
+    gain[0]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fen::parse_fen;
+    use crate::helper::square_to_index;
+    use crate::make_move::legal_moves;
+
+    /// SEE of the legal move `from`-`to` in `fen`.
+    fn see_of(fen: &str, from: &str, to: &str) -> i32 {
+        let (position, color) = parse_fen(fen).unwrap();
+        let (from, to) = (square_to_index(from).unwrap(), square_to_index(to).unwrap());
+        let m = legal_moves(color, &position).into_iter().find(|m| m.from == from && m.to == to).unwrap();
+        see(&position, m)
+    }
+
+    #[test]
+    fn undefended_pawn() {
+        assert_eq!(see_of("1k1r4/1pp4p/p7/4p3/8/P5P1/1PP4P/2K1R3 w - - 0 1", "e1", "e5"), 100);
+    }
+
+    #[test]
+    fn defended_pawn() {
+        // pawn takes pawn, pawn takes back
+        assert_eq!(see_of("4k3/8/3p4/4p3/3P4/8/8/4K3 w - - 0 1", "d4", "e5"), 0);
+        // a rook for a pawn
+        assert_eq!(see_of("4k3/8/3p4/4p3/8/8/8/4RK2 w - - 0 1", "e1", "e5"), -400);
+    }
+
+    #[test]
+    fn x_ray_batteries() {
+        // the rook behind recaptures, so Black does better not to take back
+        assert_eq!(see_of("4k3/4r3/8/4p3/8/8/4R3/4R1K1 w - - 0 1", "e2", "e5"), 100);
+        assert_eq!(see_of("4k3/4r3/8/4p3/8/8/4R3/4Q1K1 w - - 0 1", "e2", "e5"), 100);
+        // without it the rook is lost for a pawn
+        assert_eq!(see_of("4k3/4r3/8/4p3/8/8/4R3/6K1 w - - 0 1", "e2", "e5"), -400);
+    }
+
+    #[test]
+    fn losing_capture() {
+        // both sides have x-ray batteries (Re2/Qe1 and Bf6/Qh8)
+        assert_eq!(see_of("1k1r3q/1ppn3p/p4b2/4p3/8/P2N2P1/1PP1R1BP/2K1Q3 w - - 0 1", "d3", "e5"), -220);
+    }
+
+    #[test]
+    fn en_passant() {
+        assert_eq!(see_of("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1", "e5", "d6"), 100);
+    }
+}
@@ -0,0 +1,195 @@
+use crate::eval::evaluate;
+use crate::make_move::legal_moves;
+use crate::moves::Move;
+use crate::piece::Color;
+use crate::position::Position;
+use crate::rng::Rng;
+use crate::search::{PvLine, play};
+
+/// Highest skill level; it plays at full strength.
+pub const MAX_SKILL_LEVEL: u8 = 20;
+/// Elo rating mapped to skill level 0 by [`Skill::from_elo`].
+pub const MIN_ELO: u32 = 800;
+/// Elo rating mapped to [`MAX_SKILL_LEVEL`] by [`Skill::from_elo`].
+pub const MAX_ELO: u32 = 2800;
+
+/// How strongly the engine plays.
+///
+/// Below full strength the engine is weakened in three ways:
+/// - the search is limited in depth and nodes,
+/// - the move is picked with some randomness among the best few root moves,
+///   preferring moves that are close to the best one (see [`Skill::pick`]),
+/// - occasionally it "misses a tactic" and plays the move that looks best
+///   one ply deep, without seeing the opponent's reply.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Skill {
+    /// Skill level from 0 (weakest) to [`MAX_SKILL_LEVEL`].
+    pub level: u8,
+    /// Maximum search depth in plies.
+    pub max_depth: u32,
+    /// Maximum nodes per search.
+    pub node_limit: Option<u64>,
+    /// Number of root moves (MultiPV lines) the move is picked from.
+    pub candidates: usize,
+    /// How far the choice may stray from the best move; larger is weaker.
+    pub weakness: i32,
+    /// Probability of playing the one-ply-greedy move instead of searching.
+    pub blunder_chance: f64,
+}
+
+impl Default for Skill {
+    fn default() -> Self {
+        Skill::from_level(MAX_SKILL_LEVEL)
+    }
+}
+
+impl Skill {
+    /// Returns the settings of a skill level.
+    ///
+    /// Level [`MAX_SKILL_LEVEL`] (and above) is full strength. Lower levels search
+    /// `level / 2 + 1` plies and at most `200 * 2^(level / 2)` nodes.
+    pub fn from_level(level: u8) -> Self {
+        if level >= MAX_SKILL_LEVEL {
+            return Skill {
+                level: MAX_SKILL_LEVEL,
+                max_depth: u32::MAX,
+                node_limit: None,
+                candidates: 1,
+                weakness: 0,
+                blunder_chance: 0.0,
+            };
+        }
+        Skill {
+            level,
+            max_depth: level as u32 / 2 + 1,
+            node_limit: Some(200 << (level / 2)),
+            candidates: 4,
+            weakness: 120 - 2 * level as i32,
+            blunder_chance: 0.15 * (MAX_SKILL_LEVEL - level) as f64 / MAX_SKILL_LEVEL as f64,
+        }
+    }
+
+    /// Returns the skill level closest to an Elo rating.
+    ///
+    /// The mapping is linear from [`MIN_ELO`] (level 0) to [`MAX_ELO`] (full strength)
+    /// and only a rough guide; it is not calibrated against rated opponents.
+    pub fn from_elo(elo: u32) -> Self {
+        let elo = elo.clamp(MIN_ELO, MAX_ELO);
+        let level = (elo - MIN_ELO) * MAX_SKILL_LEVEL as u32 / (MAX_ELO - MIN_ELO);
+        Skill::from_level(level as u8)
+    }
+
+    /// Returns `true` if this skill does not weaken play at all.
+    pub fn is_full_strength(&self) -> bool {
+        self.level >= MAX_SKILL_LEVEL
+    }
+
+    /// Picks the move to play from the ranked lines of a search.
+    ///
+    /// Every line gets a bonus growing with how much worse it is than the best
+    /// line (scaled by `weakness`) plus a random part, and the line with the
+    /// highest score plus bonus is played. With probability `blunder_chance` the
+    /// move with the best static evaluation after one ply is played instead.
+    ///
+    /// see: Stockfish's `Skill::pick_best`
+    ///
+    /// # Arguments
+    /// * `lines` - Ranked search lines, best first.
+    /// * `position` - The searched position.
+    /// * `color` - The side to move.
+    /// * `rng` - Source of randomness; the same seed gives the same choices.
+    ///
+    /// # Returns
+    /// The index into `lines` of the chosen line, or a move not among the lines
+    /// (a missed tactic) together with its one-ply evaluation.
+    pub fn pick(&self, lines: &[PvLine], position: &Position, color: Color, rng: &mut Rng) -> Option<SkillChoice> {
+        if lines.is_empty() {
+            return None;
+        }
+        if self.is_full_strength() {
+            return Some(SkillChoice::Line(0));
+        }
+        if rng.chance(self.blunder_chance)
+            && let Some((m, score)) = greedy_move(position, color)
+        {
+            return Some(SkillChoice::Blunder(m, score));
+        }
+
+        let top = lines[0].score;
+        let delta = (top - lines[lines.len() - 1].score).min(100);
+        let weakness = self.weakness.max(1);
+        let mut best = 0;
+        let mut best_value = i32::MIN;
+        for (i, line) in lines.iter().enumerate() {
+            let push = (weakness * (top - line.score) + delta * rng.below(weakness as usize) as i32) / 128;
+            if line.score + push > best_value {
+                best_value = line.score + push;
+                best = i;
+            }
+        }
+        Some(SkillChoice::Line(best))
+    }
+}
+
+/// The move chosen by [`Skill::pick`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SkillChoice {
+    /// Play the line at this index.
+    Line(usize),
+    /// Play this move, found without looking at the opponent's replies, with its score.
+    Blunder(Move, i32),
+}
+
+/// Returns the move with the best static evaluation right after it is played.
+fn greedy_move(position: &Position, color: Color) -> Option<(Move, i32)> {
+    legal_moves(color, position)
+        .into_iter()
+        .map(|m| (m, -evaluate(color.opponent(), &play(m, position))))
+        .max_by_key(|&(_, score)| score)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fen::{START_FEN, parse_fen};
+    use crate::search::Searcher;
+
+    /// Plays the first `plies` moves of a game between two weakened searchers seeded with `seed`.
+    fn weakened_game(seed: u64, plies: usize) -> Vec<Move> {
+        let (mut position, mut color) = parse_fen(START_FEN).unwrap();
+        let mut searcher = Searcher::new();
+        searcher.skill = Skill::from_level(2);
+        searcher.seed_skill(seed);
+        let mut moves = Vec::new();
+        for _ in 0..plies {
+            let Some(m) = searcher.search(&position, color, 64).best_move else { break };
+            moves.push(m);
+            position = play(m, &position);
+            color = color.opponent();
+        }
+        moves
+    }
+
+    fn same_moves(a: &[Move], b: &[Move]) -> bool {
+        a.len() == b.len() && a.iter().zip(b).all(|(x, y)| x.is_same(y))
+    }
+
+    #[test]
+    fn levels() {
+        assert!(Skill::from_level(MAX_SKILL_LEVEL).is_full_strength());
+        assert!(!Skill::from_level(0).is_full_strength());
+        assert_eq!(Skill::from_elo(MAX_ELO).level, MAX_SKILL_LEVEL);
+        assert_eq!(Skill::from_elo(0).level, 0);
+    }
+
+    #[test]
+    fn same_seed_plays_same_game() {
+        assert!(same_moves(&weakened_game(7, 12), &weakened_game(7, 12)));
+    }
+
+    #[test]
+    fn different_seeds_can_diverge() {
+        let reference = weakened_game(0, 12);
+        assert!((1..10).any(|seed| !same_moves(&reference, &weakened_game(seed, 12))));
+    }
+}
@@ -1,6 +1,7 @@
 use crate::piece::{Piece, Color};
-use crate::position::Position;
-use crate::moves::{Move, piece_indexes};
+use crate::position::{Pieces, Position};
+use crate::moves::Move;
+use crate::variant::Variant;
 
 /*
 - Pawn !
@@ -43,7 +44,8 @@ pub fn is_pawn_promotion(to: u8, piece: Piece) -> bool {
 /// Returns the possible promotion pieces for a pawn that reaches the last rank.
 ///
 /// By chess rules, promotion is mandatory, and the pawn can be promoted to
-/// a queen, rook, bishop, or knight of the same color.
+/// a queen, rook, bishop, or knight of the same color. In antichess it may
+/// become a king as well, see [`variant_pawn_promotions`].
 ///
 /// # Arguments
 ///
@@ -70,65 +72,120 @@ pub fn valid_pawn_promotions(piece: Piece) -> Vec<Piece> {
     }
 }
 
+/// Like [`valid_pawn_promotions`], but for the rules of `position`'s variant:
+/// antichess adds the king.
+pub fn variant_pawn_promotions(piece: Piece, position: &Position) -> Vec<Piece> {
+    let mut pieces = valid_pawn_promotions(piece);
+    if position.variant == Variant::Antichess && !pieces.is_empty() {
+        pieces.push(Piece::King(piece.color()));
+    }
+    pieces
+}
+
+/// The squares of a castling move.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Castling {
+    pub kingside: bool,
+    pub king_from: u8,
+    /// The g-file square for kingside castling, the c-file square for queenside castling.
+    pub king_to: u8,
+    pub rook_from: u8,
+    /// The f-file square for kingside castling, the d-file square for queenside castling.
+    pub rook_to: u8,
+}
+
+/// Recognizes a castling move.
+///
+/// In standard chess castling is the king's two-square move (`e1g1`); with
+/// [`CastlingRights::chess960`](crate::piece::CastlingRights::chess960) it is the
+/// king moving onto its own rook (`e1h1`), which stays unambiguous when the king
+/// starts next to (or on) its destination.
+///
+/// # Arguments
+/// * `m` - A pseudo-legal move.
+/// * `position` - The position before the move.
+///
+/// # Returns
+/// The castling squares, or `None` if `m` is not a castling move.
+pub fn castling_squares(m: &Move, position: &Position) -> Option<Castling> {
+    let Piece::King(color) = m.piece else { return None };
+    let rights = &position.castling_rights;
+    let kingside = m.to > m.from;
+    if rights.chess960 {
+        let rook = 1u64 << m.to;
+        if position.bb_pieces[color.index()][Pieces::ROOK].0 & rook == 0 || m.to != rights.rook_square(color, kingside) {
+            return None;
+        }
+    } else if m.from.abs_diff(m.to) != 2 || m.from / 8 != m.to / 8 {
+        return None;
+    }
+    let base = m.from - m.from % 8;
+    Some(Castling {
+        kingside,
+        king_from: m.from,
+        king_to: base + if kingside { 6 } else { 2 },
+        rook_from: rights.rook_square(color, kingside),
+        rook_to: base + if kingside { 5 } else { 3 },
+    })
+}
+
 /// Generates *pseudo-legal* castling moves for a king, if available, based on castling rights
 /// and whether the squares between the king and rook are empty.
 ///
 /// Castling is only possible if:
 /// - The king and the involved rook have not previously moved.
-/// - The path between the king and rook is empty.
+/// - Every square the king and the rook cross or land on is empty (apart from the two of them).
+///
+/// Whether the king is in check or crosses an attacked square is left to the legality
+/// check (see [`is_legal`](crate::make_move::is_legal)). King and rooks may start on any
+/// file of the back rank (Chess960); the king always ends on the g- or c-file and the
+/// rook next to it, on the f- or d-file.
 ///
 /// # Arguments
 ///
-/// * `from` — The square index of the king (should be `4` for White, `60` for Black).
+/// * `from` — The square index of the king.
 /// * `piece` — The king piece (must be `Piece::King(Color::White|Black)`).
 /// * `position` — The current position, including castling rights and occupancy.
 ///
 /// # Returns
 ///
-/// A vector of possible castling moves. May be empty if no castling is available.
+/// A vector of possible castling moves. May be empty if no castling is available,
+/// and always is in antichess.
 pub fn castling_moves(from: u8, piece: Piece, position: &Position) -> Vec<Move> {
     let mut moves = Vec::new();
-    let cr = &position.castling_rights;
-    let (own_index, enemy_index) = piece_indexes(piece);
+    if position.variant == Variant::Antichess {
+        return moves;
+    }
+    let rights = &position.castling_rights;
+    let color = piece.color();
+    let base = match color {
+        Color::White => 0,
+        Color::Black => 56,
+    };
+    if from / 8 != base / 8 {
+        return moves;
+    }
+    let occupied = position.bb_sides[0].0 | position.bb_sides[1].0 | position.duck.0;
 
-    match piece.color() {
-        Color::White => {
-            if from == 4 { 
-                // kingside
-                if !cr.white_king_moved && !cr.white_kingside_rook_moved {
-                    let empty = !(position.bb_sides[own_index].0 | position.bb_sides[enemy_index].0);
-                    if (empty & (1<<5 | 1<<6)) == (1<<5 | 1<<6) {
-                        moves.push(Move { from, to: 6, piece, promoted_from_pawn: false });
-                    }
-                }
-                // queenside
-                if !cr.white_king_moved && !cr.white_queenside_rook_moved {
-                    let empty = !(position.bb_sides[own_index].0 | position.bb_sides[enemy_index].0);
-                    if (empty & (1<<1 | 1<<2 | 1<<3)) == (1<<1 | 1<<2 | 1<<3) {
-                        moves.push(Move { from, to: 2, piece, promoted_from_pawn: false });
-                    }
-                }
-            }
+    for kingside in [true, false] {
+        let rook_from = rights.rook_square(color, kingside);
+        if !rights.has_right(color, kingside)
+            || position.bb_pieces[color.index()][Pieces::ROOK].0 & (1 << rook_from) == 0
+            || (rook_from > from) != kingside
+        {
+            continue;
         }
-        Color::Black => {
-            if from == 60 { 
-                // kingside
-                if !cr.black_king_moved && !cr.black_kingside_rook_moved {
-                    let empty = !(position.bb_sides[own_index].0 | position.bb_sides[enemy_index].0);
-                    if (empty & (1<<61 | 1<<62)) == (1<<61 | 1<<62) {
-                        moves.push(Move { from, to: 62, piece, promoted_from_pawn: false,});
-                    }
-                }
-                // queenside
-                if !cr.black_king_moved && !cr.black_queenside_rook_moved {
-                    let empty = !(position.bb_sides[own_index].0 | position.bb_sides[enemy_index].0);
-                    if (empty & (1<<57 | 1<<58 | 1<<59)) == (1<<57 | 1<<58 | 1<<59) {
-                        moves.push(Move { from, to: 58, piece, promoted_from_pawn: false, });
-                    }
-                }
-            }
+        let king_to = base + if kingside { 6 } else { 2 };
+        let rook_to = base + if kingside { 5 } else { 3 };
+        let low = from.min(king_to).min(rook_from).min(rook_to);
+        let high = from.max(king_to).max(rook_from).max(rook_to);
+        let path = (low..=high).fold(0u64, |path, square| path | 1 << square);
+        if occupied & path & !(1 << from | 1 << rook_from) != 0 {
+            continue;
         }
+        let to = if rights.chess960 { rook_from } else { king_to };
+        moves.push(Move { from, to, piece, promoted_from_pawn: false, drop: false, duck: None });
     }
 
     moves
-}
\ No newline at end of file
+}
@@ -0,0 +1,1307 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use crate::make_move::{is_checkmated, legal_moves};
+use crate::moves::Move;
+use crate::piece::Color;
+use crate::position::{Pieces, Position, Sides};
+use crate::search::play;
+use crate::tablebase::can_castle;
+use crate::variant::Variant;
+
+// see: https://www.chessprogramming.org/Syzygy_Bases
+// The index scheme and the compression follow the probing code published with the
+// tables (Fathom, Stockfish's tbprobe); the comments below only summarize it.
+
+/// Largest number of pieces (kings included) of any Syzygy table.
+pub const TB_PIECES: usize = 7;
+/// Extension of WDL (win/draw/loss) files, e.g. `KRPvKR.rtbw`.
+pub const WDL_EXTENSION: &str = "rtbw";
+/// Extension of DTZ (distance to zeroing move) files, e.g. `KRPvKR.rtbz`.
+pub const DTZ_EXTENSION: &str = "rtbz";
+/// Score of a tablebase win one ply from the root; below the mate scores, so a
+/// mate found by the search still ranks higher than a win known from the tables.
+pub const TB_WIN_SCORE: i32 = 20_000;
+
+const WDL_MAGIC: [u8; 4] = [0x71, 0xE8, 0x23, 0x5D];
+const DTZ_MAGIC: [u8; 4] = [0xD7, 0x66, 0x0C, 0xA5];
+
+/// Flags of the file header.
+const SPLIT: u8 = 1;
+const HAS_PAWNS: u8 = 2;
+/// Flags of a sub-table.
+const STM: u8 = 1;
+const MAPPED: u8 = 2;
+const WIN_PLIES: u8 = 4;
+const LOSS_PLIES: u8 = 8;
+const WIDE: u8 = 16;
+const SINGLE_VALUE: u8 = 128;
+
+/// Piece letters in the order used by file names, strongest first.
+const NAME_ORDER: [(char, usize); 6] = [
+    ('K', Pieces::KING),
+    ('Q', Pieces::QUEEN),
+    ('R', Pieces::ROOK),
+    ('B', Pieces::BISHOP),
+    ('N', Pieces::KNIGHT),
+    ('P', Pieces::PAWN),
+];
+
+/// Result of a position under perfect play from the point of view of the side to
+/// move, taking the fifty-move rule into account.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Wdl {
+    Loss,
+    /// Lost without the fifty-move rule, drawn with it.
+    BlessedLoss,
+    Draw,
+    /// Won without the fifty-move rule, drawn with it.
+    CursedWin,
+    Win,
+}
+
+impl Wdl {
+    fn from_value(value: i32) -> Option<Wdl> {
+        match value {
+            -2 => Some(Wdl::Loss),
+            -1 => Some(Wdl::BlessedLoss),
+            0 => Some(Wdl::Draw),
+            1 => Some(Wdl::CursedWin),
+            2 => Some(Wdl::Win),
+            _ => None,
+        }
+    }
+
+    /// The value as stored in the tables, from `-2` (loss) to `2` (win).
+    pub fn value(self) -> i32 {
+        self as i32 - 2
+    }
+
+    /// The same result seen from the other side.
+    pub fn negate(self) -> Wdl {
+        match self {
+            Wdl::Loss => Wdl::Win,
+            Wdl::BlessedLoss => Wdl::CursedWin,
+            Wdl::Draw => Wdl::Draw,
+            Wdl::CursedWin => Wdl::BlessedLoss,
+            Wdl::Win => Wdl::Loss,
+        }
+    }
+
+    /// Win, draw or loss as `1`, `0` or `-1` under the fifty-move rule, e.g. for
+    /// [`Adjudication::tablebase`](crate::match_runner::Adjudication::tablebase).
+    pub fn wdl(self) -> i32 {
+        match self {
+            Wdl::Win => 1,
+            Wdl::Loss => -1,
+            _ => 0,
+        }
+    }
+
+    /// The value as a search score at `ply`: wins and losses are [`TB_WIN_SCORE`]
+    /// minus the ply, cursed wins and blessed losses are just above and below a draw.
+    pub fn to_score(self, ply: i32) -> i32 {
+        match self {
+            Wdl::Win => TB_WIN_SCORE - ply,
+            Wdl::CursedWin => 1,
+            Wdl::Draw => 0,
+            Wdl::BlessedLoss => -1,
+            Wdl::Loss => -(TB_WIN_SCORE - ply),
+        }
+    }
+}
+
+/// A legal root move with its tablebase result, see [`Syzygy::root_moves`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RootMove {
+    pub m: Move,
+    /// Result after the move, for the side playing it.
+    pub wdl: Wdl,
+    /// Plies until the next capture or pawn move (the move itself counts), positive
+    /// when winning and negative when losing; `0` for draws. Values beyond 100
+    /// belong to cursed wins and blessed losses.
+    pub dtz: i32,
+}
+
+/// Index tables shared by all files, see [`build_maps`].
+struct Maps {
+    /// Squares a2-h7 numbered 47 down to 0 from the edge files inwards and from
+    /// rank 2 upwards; the leading pawn is the one with the highest number.
+    pawns: [u64; 64],
+    /// Squares below the a1-h8 diagonal numbered 0..28.
+    b1h1h7: [u64; 64],
+    /// The a1-d1-d4 triangle numbered 0..10, its diagonal last.
+    a1d1d4: [u64; 64],
+    /// The 462 placements of two kings with the first in the triangle, indexed by
+    /// the first king's triangle number and the second king's square.
+    kk: [[u64; 64]; 10],
+    /// `binomial[k][n]`: the number of ways to choose `k` of `n` squares.
+    binomial: [[u64; 64]; TB_PIECES],
+    /// Index of the leading pawn group by group size and leading pawn square.
+    lead_pawn_idx: [[u64; 64]; 6],
+    /// Number of leading pawn placements by group size and file (a-d).
+    lead_pawns_size: [[u64; 4]; 6],
+}
+
+static MAPS: Maps = build_maps();
+
+const fn off_diagonal(square: usize) -> i32 {
+    (square >> 3) as i32 - (square & 7) as i32
+}
+
+const fn build_maps() -> Maps {
+    let mut maps = Maps {
+        pawns: [0; 64],
+        b1h1h7: [0; 64],
+        a1d1d4: [0; 64],
+        kk: [[0; 64]; 10],
+        binomial: [[0; 64]; TB_PIECES],
+        lead_pawn_idx: [[0; 64]; 6],
+        lead_pawns_size: [[0; 4]; 6],
+    };
+
+    let mut code = 0;
+    let mut square = 0;
+    while square < 64 {
+        if off_diagonal(square) < 0 {
+            maps.b1h1h7[square] = code;
+            code += 1;
+        }
+        square += 1;
+    }
+
+    code = 0;
+    square = 0;
+    while square <= 27 {
+        if off_diagonal(square) < 0 && square & 7 <= 3 {
+            maps.a1d1d4[square] = code;
+            code += 1;
+        }
+        square += 1;
+    }
+    // the diagonal a1, b2, c3, d4 comes last
+    square = 0;
+    while square <= 27 {
+        maps.a1d1d4[square] = code;
+        code += 1;
+        square += 9;
+    }
+
+    code = 0;
+    let mut both_on_diagonal = [(0usize, 0usize); 64];
+    let mut both_count = 0;
+    let mut slot = 0;
+    while slot < 10 {
+        let mut s1 = 0;
+        while s1 <= 27 {
+            // squares outside the triangle are 0 as well, b1 is the real 0
+            if maps.a1d1d4[s1] == slot as u64 && (slot > 0 || s1 == 1) && (off_diagonal(s1) < 0 || s1 % 9 == 0) && s1 & 7 <= 3 {
+                let mut s2 = 0;
+                while s2 < 64 {
+                    let file_distance = ((s1 & 7) as i32 - (s2 & 7) as i32).abs();
+                    let rank_distance = ((s1 >> 3) as i32 - (s2 >> 3) as i32).abs();
+                    if file_distance <= 1 && rank_distance <= 1 {
+                        // same square or adjacent kings
+                    } else if off_diagonal(s1) == 0 && off_diagonal(s2) > 0 {
+                        // first king on the diagonal, second above it
+                    } else if off_diagonal(s1) == 0 && off_diagonal(s2) == 0 {
+                        both_on_diagonal[both_count] = (slot, s2);
+                        both_count += 1;
+                    } else {
+                        maps.kk[slot][s2] = code;
+                        code += 1;
+                    }
+                    s2 += 1;
+                }
+            }
+            s1 += 1;
+        }
+        slot += 1;
+    }
+    let mut i = 0;
+    while i < both_count {
+        maps.kk[both_on_diagonal[i].0][both_on_diagonal[i].1] = code;
+        code += 1;
+        i += 1;
+    }
+
+    maps.binomial[0][0] = 1;
+    let mut n = 1;
+    while n < 64 {
+        let mut k = 0;
+        while k < TB_PIECES && k <= n {
+            let with = if k > 0 { maps.binomial[k - 1][n - 1] } else { 0 };
+            let without = if k < n { maps.binomial[k][n - 1] } else { 0 };
+            maps.binomial[k][n] = with + without;
+            k += 1;
+        }
+        n += 1;
+    }
+
+    let mut available = 0;
+    let mut lead_count = 1;
+    while lead_count <= 5 {
+        let mut file = 0;
+        while file < 4 {
+            let mut idx = 0;
+            let mut rank = 1;
+            while rank <= 6 {
+                let square = rank * 8 + file;
+                if lead_count == 1 {
+                    maps.pawns[square] = 47 - available;
+                    maps.pawns[square ^ 7] = 46 - available;
+                    available += 2;
+                }
+                maps.lead_pawn_idx[lead_count][square] = idx;
+                idx += maps.binomial[lead_count - 1][maps.pawns[square] as usize];
+                rank += 1;
+            }
+            maps.lead_pawns_size[lead_count][file] = idx;
+            file += 1;
+        }
+        lead_count += 1;
+    }
+    maps
+}
+
+/// Whether a file holds WDL or DTZ values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TableKind {
+    Wdl,
+    Dtz,
+}
+
+impl TableKind {
+    fn extension(self) -> &'static str {
+        match self {
+            TableKind::Wdl => WDL_EXTENSION,
+            TableKind::Dtz => DTZ_EXTENSION,
+        }
+    }
+}
+
+/// The material of a table, from its name (e.g. `KRPvKR`).
+#[derive(Debug, Clone)]
+struct Material {
+    /// The pieces of the side named first and second.
+    sides: [String; 2],
+    piece_count: usize,
+    has_pawns: bool,
+    /// Whether some side has exactly one piece of some kind other than the king.
+    has_unique_pieces: bool,
+    /// Pawns of the leading color (the side with fewer pawns, if it has any) and of the other one.
+    pawn_count: [usize; 2],
+}
+
+impl Material {
+    fn from_name(name: &str) -> Option<Material> {
+        let (white, black) = name.split_once('v')?;
+        let sides = [white.to_string(), black.to_string()];
+        let count = |side: &str, letter: char| side.chars().filter(|&c| c == letter).count();
+        if sides.iter().any(|side| count(side, 'K') != 1 || !side.starts_with('K') || side.chars().any(|c| !"KQRBNP".contains(c))) {
+            return None;
+        }
+        let piece_count = white.len() + black.len();
+        let (white_pawns, black_pawns) = (count(white, 'P'), count(black, 'P'));
+        let leading_white = black_pawns == 0 || (white_pawns > 0 && black_pawns >= white_pawns);
+        let has_unique_pieces = sides.iter().any(|side| "QRBNP".chars().any(|letter| count(side, letter) == 1));
+        Some(Material {
+            sides,
+            piece_count,
+            has_pawns: white_pawns + black_pawns > 0,
+            has_unique_pieces,
+            pawn_count: if leading_white { [white_pawns, black_pawns] } else { [black_pawns, white_pawns] },
+        })
+        .filter(|material| (3..=TB_PIECES).contains(&material.piece_count))
+    }
+
+    fn is_symmetric(&self) -> bool {
+        self.sides[0] == self.sides[1]
+    }
+}
+
+/// The pieces of one side as in a file name, e.g. `KRP`.
+fn side_name(position: &Position, side: usize) -> String {
+    let mut name = String::new();
+    for (letter, kind) in NAME_ORDER {
+        for _ in 0..position.bb_pieces[side][kind].0.count_ones() {
+            name.push(letter);
+        }
+    }
+    name
+}
+
+/// Orders side names by strength: more pieces first, then by their strongest pieces.
+fn compare_sides(a: &str, b: &str) -> Ordering {
+    let strength = |name: &str| -> Vec<usize> { name.chars().map(|c| 6 - "KQRBNP".find(c).unwrap_or(6)).collect() };
+    a.len().cmp(&b.len()).then_with(|| strength(a).cmp(&strength(b)))
+}
+
+/// Name of the table holding a position, e.g. `KRPvKR` (without extension): the
+/// stronger side first, whatever its color.
+///
+/// # Arguments
+/// * `position` - The board state.
+pub fn table_name(position: &Position) -> String {
+    let white = side_name(position, Sides::WHITE);
+    let black = side_name(position, Sides::BLACK);
+    if compare_sides(&white, &black) == Ordering::Less {
+        format!("{}v{}", black, white)
+    } else {
+        format!("{}v{}", white, black)
+    }
+}
+
+/// A sub-table: the values of one side to move (and one leading pawn file) of a
+/// file. Offsets point into the file's bytes.
+#[derive(Debug, Clone, Default)]
+struct PairsData {
+    flags: u8,
+    /// Piece codes in index order: kind + 1, plus 8 for the second side.
+    pieces: [u8; TB_PIECES],
+    /// Sizes of the piece groups, zero-terminated.
+    group_len: [usize; TB_PIECES + 1],
+    /// Multiplier of each group in the index; the one after the last group is the table size.
+    group_idx: [u64; TB_PIECES + 1],
+    /// The value of every position if the sub-table is [`SINGLE_VALUE`].
+    single_value: u16,
+    block_size: usize,
+    span: u64,
+    blocks: usize,
+    block_length_size: usize,
+    sparse_index_size: usize,
+    min_sym_len: u8,
+    /// Lowest symbol of each code length, little endian u16s.
+    lowest_sym: usize,
+    /// Smallest left-aligned code of each length, longest codes last.
+    base64: Vec<u64>,
+    /// Number of values each symbol expands to, minus one.
+    symlen: Vec<u32>,
+    /// Symbol pairs, 3 bytes each: left and right symbol of 12 bits.
+    btree: usize,
+    sparse_index: usize,
+    block_length: usize,
+    data: usize,
+    /// Start of the DTZ value maps, by result (see [`TableFile::map_score`]).
+    map_idx: [usize; 4],
+}
+
+/// A parsed WDL or DTZ file.
+struct TableFile {
+    bytes: Vec<u8>,
+    kind: TableKind,
+    material: Material,
+    /// Sub-tables by leading pawn file (only the first without pawns) and side to move.
+    pairs: Vec<Vec<PairsData>>,
+    /// Start of the DTZ value maps.
+    map: usize,
+}
+
+fn byte(bytes: &[u8], at: usize) -> Result<u8, String> {
+    bytes.get(at).copied().ok_or_else(|| "file is truncated".to_string())
+}
+
+fn u16_le(bytes: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(bytes.get(at..at + 2)?.try_into().ok()?))
+}
+
+fn u32_le(bytes: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(bytes.get(at..at + 4)?.try_into().ok()?))
+}
+
+fn u32_be(bytes: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(bytes.get(at..at + 4)?.try_into().ok()?))
+}
+
+fn u64_be(bytes: &[u8], at: usize) -> Option<u64> {
+    Some(u64::from_be_bytes(bytes.get(at..at + 8)?.try_into().ok()?))
+}
+
+/*
+File layout (after the 4 magic bytes):
+
+  flags        u8: split (separate sub-tables per side to move), has pawns
+  per file     piece order (1 byte, 2 if both sides have pawns; a nibble per side),
+               then one byte per piece with the piece codes (a nibble per side)
+  sizes        per sub-table: flags, then either the single value or the block
+               size, span, padding, block count, symbol lengths, lowest symbols
+               and the symbol pair tree
+  DTZ maps     DTZ only: value maps of the mapped sub-tables
+  sparse index per sub-table: (block u32, offset u16) every `span` values
+  block sizes  per sub-table: u16 number of values in each block, minus one
+  blocks       per sub-table, 64-byte aligned: canonical Huffman codes of
+               symbols, each expanding to one or more values (recursive pairing)
+*/
+
+impl TableFile {
+    /// Parses a file's header.
+    ///
+    /// # Errors
+    /// Returns `Err(String)` if the file is not a Syzygy table of the given kind
+    /// and material, or is truncated.
+    fn parse(bytes: Vec<u8>, kind: TableKind, material: Material) -> Result<TableFile, String> {
+        let magic = match kind {
+            TableKind::Wdl => WDL_MAGIC,
+            TableKind::Dtz => DTZ_MAGIC,
+        };
+        if bytes.len() % 64 != 16 || bytes.get(..4) != Some(&magic[..]) {
+            return Err("not a Syzygy table".to_string());
+        }
+        let mut at = 4;
+        let flags = byte(&bytes, at)?;
+        at += 1;
+        if (flags & HAS_PAWNS != 0) != material.has_pawns {
+            return Err("pawn flag does not match the file name".to_string());
+        }
+        let sides = if kind == TableKind::Wdl && flags & SPLIT != 0 { 2 } else { 1 };
+        let files = if material.has_pawns { 4 } else { 1 };
+        let both_pawns = material.has_pawns && material.pawn_count[1] > 0;
+
+        let mut pairs = vec![vec![PairsData::default(); sides]; files];
+        for (file, file_pairs) in pairs.iter_mut().enumerate() {
+            let first = byte(&bytes, at)?;
+            let second = if both_pawns { byte(&bytes, at + 1)? } else { 0xFF };
+            let order = [[first & 0xF, second & 0xF], [first >> 4, second >> 4]];
+            at += 1 + both_pawns as usize;
+            for k in 0..material.piece_count {
+                let codes = byte(&bytes, at)?;
+                for (side, data) in file_pairs.iter_mut().enumerate() {
+                    data.pieces[k] = if side == 1 { codes >> 4 } else { codes & 0xF };
+                }
+                at += 1;
+            }
+            for (side, data) in file_pairs.iter_mut().enumerate() {
+                set_groups(data, &material, order[side], file)?;
+            }
+        }
+        at += at & 1;
+
+        for data in pairs.iter_mut().flatten() {
+            at = set_sizes(data, &bytes, at)?;
+        }
+
+        let map = at;
+        if kind == TableKind::Dtz {
+            for file_pairs in pairs.iter_mut() {
+                let data = &mut file_pairs[0];
+                if data.flags & MAPPED == 0 {
+                    continue;
+                }
+                if data.flags & WIDE != 0 {
+                    at += at & 1;
+                    for i in 0..4 {
+                        data.map_idx[i] = (at - map) / 2 + 1;
+                        at += 2 * u16_le(&bytes, at).ok_or("file is truncated")? as usize + 2;
+                    }
+                } else {
+                    for i in 0..4 {
+                        data.map_idx[i] = at - map + 1;
+                        at += byte(&bytes, at)? as usize + 1;
+                    }
+                }
+            }
+            at += at & 1;
+        }
+
+        for data in pairs.iter_mut().flatten() {
+            data.sparse_index = at;
+            at += data.sparse_index_size * 6;
+        }
+        for data in pairs.iter_mut().flatten() {
+            data.block_length = at;
+            at += data.block_length_size * 2;
+        }
+        for data in pairs.iter_mut().flatten() {
+            at = (at + 0x3F) & !0x3F;
+            data.data = at;
+            at += data.blocks * data.block_size;
+        }
+        if at > bytes.len() {
+            return Err("file is truncated".to_string());
+        }
+        Ok(TableFile { bytes, kind, material, pairs, map })
+    }
+
+    /// The sub-table of a side to move and leading pawn file.
+    fn pairs(&self, stm: usize, file: usize) -> &PairsData {
+        let file_pairs = &self.pairs[if self.material.has_pawns { file } else { 0 }];
+        &file_pairs[stm % file_pairs.len()]
+    }
+
+    /// Turns a stored DTZ value into plies, given the position's WDL result.
+    fn map_score(&self, file: usize, value: u16, wdl: Wdl) -> Option<i32> {
+        const WDL_MAP: [usize; 5] = [1, 3, 0, 2, 0];
+        let data = self.pairs(0, file);
+        let mut value = value as i32;
+        if data.flags & MAPPED != 0 {
+            let index = data.map_idx[WDL_MAP[(wdl.value() + 2) as usize]] + value as usize;
+            value = if data.flags & WIDE != 0 {
+                u16_le(&self.bytes, self.map + 2 * index)? as i32
+            } else {
+                *self.bytes.get(self.map + index)? as i32
+            };
+        }
+        // stored in moves unless the plies flag is set; cursed results are always in moves
+        let in_moves = match wdl {
+            Wdl::Win => data.flags & WIN_PLIES == 0,
+            Wdl::Loss => data.flags & LOSS_PLIES == 0,
+            Wdl::CursedWin | Wdl::BlessedLoss => true,
+            Wdl::Draw => false,
+        };
+        Some(if in_moves { value * 2 } else { value } + 1)
+    }
+}
+
+/// Splits the pieces of a sub-table into groups and computes each group's multiplier.
+///
+/// The first group holds the leading pawns, or the kings (plus a third piece if
+/// some piece is unique); pieces of the same kind that follow each other form
+/// the other groups. `order` gives the position of the leading group and of the
+/// remaining pawns in the index.
+fn set_groups(data: &mut PairsData, material: &Material, order: [u8; 2], file: usize) -> Result<(), String> {
+    let maps = &MAPS;
+    let mut n = 0;
+    let mut first_len: i32 = if material.has_pawns {
+        0
+    } else if material.has_unique_pieces {
+        3
+    } else {
+        2
+    };
+    data.group_len[0] = 1;
+    for i in 1..material.piece_count {
+        first_len -= 1;
+        if first_len > 0 || data.pieces[i] == data.pieces[i - 1] {
+            data.group_len[n] += 1;
+        } else {
+            n += 1;
+            data.group_len[n] = 1;
+        }
+    }
+    n += 1;
+    data.group_len[n] = 0;
+    if data.group_len.iter().any(|&len| len >= TB_PIECES) || (material.has_pawns && data.group_len[0] > 5) {
+        return Err("invalid piece groups".to_string());
+    }
+
+    let both_pawns = material.has_pawns && material.pawn_count[1] > 0;
+    let mut next = if both_pawns { 2 } else { 1 };
+    let mut free_squares = 64 - data.group_len[0] - if both_pawns { data.group_len[1] } else { 0 };
+    let mut idx: u64 = 1;
+    let mut k = 0;
+    while next < n || k == order[0] || k == order[1] {
+        if k == order[0] {
+            data.group_idx[0] = idx;
+            idx *= if material.has_pawns {
+                maps.lead_pawns_size[data.group_len[0]][file]
+            } else if material.has_unique_pieces {
+                31332
+            } else {
+                462
+            };
+        } else if k == order[1] {
+            data.group_idx[1] = idx;
+            idx *= maps.binomial[data.group_len[1]][48 - data.group_len[0]];
+        } else {
+            data.group_idx[next] = idx;
+            idx *= maps.binomial[data.group_len[next]][free_squares];
+            free_squares -= data.group_len[next];
+            next += 1;
+        }
+        k += 1;
+    }
+    data.group_idx[n] = idx;
+    Ok(())
+}
+
+/// Reads the sizes and the Huffman code of a sub-table starting at `at`.
+///
+/// # Returns
+/// The offset after the sub-table's entry.
+fn set_sizes(data: &mut PairsData, bytes: &[u8], mut at: usize) -> Result<usize, String> {
+    data.flags = byte(bytes, at)?;
+    at += 1;
+    if data.flags & SINGLE_VALUE != 0 {
+        data.single_value = byte(bytes, at)? as u16;
+        return Ok(at + 1);
+    }
+    let size = data.group_idx[data.group_len.iter().position(|&len| len == 0).unwrap_or(TB_PIECES)];
+    let (block_bits, span_bits) = (byte(bytes, at)?, byte(bytes, at + 1)?);
+    if block_bits >= 32 || span_bits >= 32 {
+        return Err("invalid block size".to_string());
+    }
+    data.block_size = 1 << block_bits;
+    data.span = 1 << span_bits;
+    data.sparse_index_size = size.div_ceil(data.span) as usize;
+    let padding = byte(bytes, at + 2)? as usize;
+    data.blocks = u32_le(bytes, at + 3).ok_or("file is truncated")? as usize;
+    data.block_length_size = data.blocks + padding;
+    let max_sym_len = byte(bytes, at + 7)?;
+    data.min_sym_len = byte(bytes, at + 8)?;
+    at += 9;
+    if max_sym_len < data.min_sym_len || max_sym_len > 64 {
+        return Err("invalid symbol lengths".to_string());
+    }
+
+    // canonical Huffman code: longer codes have lower values, so lowest_sym
+    // decreases with the length and base64 (left-aligned) does too
+    data.lowest_sym = at;
+    let lengths = (max_sym_len - data.min_sym_len + 1) as usize;
+    let lowest = |i: usize| u16_le(bytes, data.lowest_sym + 2 * i).map(u64::from).ok_or("file is truncated");
+    data.base64 = vec![0; lengths];
+    for i in (0..lengths - 1).rev() {
+        data.base64[i] = (data.base64[i + 1].wrapping_add(lowest(i)?).wrapping_sub(lowest(i + 1)?)) / 2;
+    }
+    for (i, base) in data.base64.iter_mut().enumerate() {
+        *base = base.checked_shl(64 - i as u32 - data.min_sym_len as u32).unwrap_or(0);
+    }
+    at += lengths * 2;
+
+    let symbols = u16_le(bytes, at).ok_or("file is truncated")? as usize;
+    at += 2;
+    data.btree = at;
+    if data.btree + 3 * symbols > bytes.len() {
+        return Err("file is truncated".to_string());
+    }
+    data.symlen = vec![0; symbols];
+    let mut visited = vec![false; symbols];
+    for symbol in 0..symbols {
+        if !visited[symbol] {
+            data.symlen[symbol] = set_symlen(data, bytes, symbol, &mut visited)?;
+        }
+    }
+    Ok(at + symbols * 3 + (symbols & 1))
+}
+
+/// Left and right halves of a symbol pair; a right half of `0xFFF` marks a leaf
+/// whose left half is the stored value.
+fn pair(bytes: &[u8], btree: usize, symbol: usize) -> Option<(usize, usize)> {
+    let lr = bytes.get(btree + 3 * symbol..btree + 3 * symbol + 3)?;
+    let left = ((lr[1] as usize & 0xF) << 8) | lr[0] as usize;
+    let right = ((lr[2] as usize) << 4) | (lr[1] as usize >> 4);
+    Some((left, right))
+}
+
+/// Number of values a symbol expands to, minus one.
+fn set_symlen(data: &mut PairsData, bytes: &[u8], symbol: usize, visited: &mut [bool]) -> Result<u32, String> {
+    visited[symbol] = true;
+    let (left, right) = pair(bytes, data.btree, symbol).ok_or("file is truncated")?;
+    if right == 0xFFF {
+        return Ok(0);
+    }
+    if left >= visited.len() || right >= visited.len() {
+        return Err("invalid symbol tree".to_string());
+    }
+    for child in [left, right] {
+        if !visited[child] {
+            data.symlen[child] = set_symlen(data, bytes, child, visited)?;
+        }
+    }
+    Ok(data.symlen[left] + data.symlen[right] + 1)
+}
+
+/// The value stored at `index` of a sub-table.
+fn decompress_pairs(data: &PairsData, bytes: &[u8], index: u64) -> Option<u16> {
+    if data.flags & SINGLE_VALUE != 0 {
+        return Some(data.single_value);
+    }
+    // the sparse index points at the value in the middle of every span; walk
+    // the block lengths from there to the block holding `index`
+    let k = (index / data.span) as usize;
+    if k >= data.sparse_index_size {
+        return None;
+    }
+    let entry = data.sparse_index + 6 * k;
+    let mut block = u32_le(bytes, entry)? as usize;
+    let mut offset = u16_le(bytes, entry + 4)? as i64 + (index % data.span) as i64 - (data.span / 2) as i64;
+    let block_length = |block: usize| -> Option<i64> {
+        if block >= data.block_length_size {
+            return None;
+        }
+        u16_le(bytes, data.block_length + 2 * block).map(i64::from)
+    };
+    while offset < 0 {
+        block = block.checked_sub(1)?;
+        offset += block_length(block)? + 1;
+    }
+    while offset > block_length(block)? {
+        offset -= block_length(block)? + 1;
+        block += 1;
+    }
+    if block >= data.blocks {
+        return None;
+    }
+
+    let mut at = data.data + block * data.block_size;
+    let mut buffer = u64_be(bytes, at)?;
+    at += 8;
+    let mut buffer_bits = 64;
+    let min = data.min_sym_len as u32;
+    let mut symbol;
+    loop {
+        let mut len = 0;
+        while buffer < data.base64[len] {
+            len += 1;
+            if len >= data.base64.len() {
+                return None;
+            }
+        }
+        let code = (buffer - data.base64[len]).checked_shr(64 - len as u32 - min).unwrap_or(0);
+        symbol = code as usize + u16_le(bytes, data.lowest_sym + 2 * len)? as usize;
+        let expands = *data.symlen.get(symbol)? as i64 + 1;
+        if offset < expands {
+            break;
+        }
+        offset -= expands;
+        let bits = len as u32 + min;
+        buffer = buffer.checked_shl(bits).unwrap_or(0);
+        buffer_bits -= bits as i32;
+        if buffer_bits <= 32 {
+            buffer_bits += 32;
+            buffer |= (u32_be(bytes, at)? as u64) << (64 - buffer_bits);
+            at += 4;
+        }
+    }
+
+    // expand the symbol's pairs down to the leaf holding the value
+    while data.symlen[symbol] != 0 {
+        let (left, right) = pair(bytes, data.btree, symbol)?;
+        let left_values = *data.symlen.get(left)? as i64 + 1;
+        if offset < left_values {
+            symbol = left;
+        } else {
+            offset -= left_values;
+            symbol = right;
+        }
+        data.symlen.get(symbol)?;
+    }
+    pair(bytes, data.btree, symbol).map(|(left, _)| left as u16)
+}
+
+/// Outcome of looking a position up in a file.
+enum Lookup {
+    Value(i32),
+    /// The DTZ file only holds the other side to move.
+    OtherSide,
+}
+
+/// Reads the value of a position from a file.
+///
+/// # Arguments
+/// * `wdl` - The position's result; only used to decode DTZ values.
+fn probe_file(file: &TableFile, position: &Position, color: Color, wdl: Wdl) -> Option<Lookup> {
+    let (stm, tb_file, idx) = position_index(file, position, color)?;
+    if file.kind == TableKind::Dtz {
+        // symmetric pawnless tables hold both sides to move
+        let stored = (file.pairs(stm, tb_file).flags & STM) as usize;
+        if stored != stm && (file.material.has_pawns || !file.material.is_symmetric()) {
+            return Some(Lookup::OtherSide);
+        }
+    }
+    let value = decompress_pairs(file.pairs(stm, tb_file), &file.bytes, idx)?;
+    Some(Lookup::Value(match file.kind {
+        TableKind::Wdl => value as i32 - 2,
+        TableKind::Dtz => file.map_score(tb_file, value, wdl)?,
+    }))
+}
+
+/// Computes the index of a position in a file.
+///
+/// # Returns
+/// The side to move and the leading pawn file of the sub-table, and the index in it.
+fn position_index(file: &TableFile, position: &Position, color: Color) -> Option<(usize, usize, u64)> {
+    let maps = &MAPS;
+    let material = &file.material;
+    let white = side_name(position, Sides::WHITE);
+    let flip = if material.is_symmetric() { color == Color::Black } else { white != material.sides[0] };
+    let (flip_color, flip_squares) = if flip { (8u8, 56usize) } else { (0, 0) };
+    let stm = flip as usize ^ color.index();
+
+    let mut squares = [0usize; TB_PIECES];
+    let mut pieces = [0u8; TB_PIECES];
+    let mut size = 0;
+    let mut lead_pawns = 0u64;
+    let mut lead_count = 0;
+    let mut tb_file = 0;
+    if material.has_pawns {
+        // the pawns of the leading color come first; the leading pawn is moved to the front
+        let piece = file.pairs(0, 0).pieces[0] ^ flip_color;
+        if piece & 7 != 1 {
+            return None;
+        }
+        lead_pawns = position.bb_pieces[(piece >> 3) as usize][Pieces::PAWN].0;
+        let mut bb = lead_pawns;
+        while bb != 0 && size < TB_PIECES {
+            squares[size] = bb.trailing_zeros() as usize ^ flip_squares;
+            pieces[size] = piece ^ flip_color;
+            size += 1;
+            bb &= bb - 1;
+        }
+        lead_count = size;
+        let lead = (0..lead_count).max_by_key(|&i| maps.pawns[squares[i]])?;
+        squares.swap(0, lead);
+        let f = squares[0] & 7;
+        tb_file = f.min(7 - f);
+    }
+
+    let occupied = (position.bb_sides[0].0 | position.bb_sides[1].0) ^ lead_pawns;
+    let mut bb = occupied;
+    while bb != 0 {
+        let square = bb.trailing_zeros() as usize;
+        bb &= bb - 1;
+        if size == TB_PIECES {
+            return None;
+        }
+        let side = if position.bb_sides[Sides::WHITE].0 & 1 << square != 0 { 0 } else { 1 };
+        let kind = (0..6).find(|&kind| position.bb_pieces[side][kind].0 & 1 << square != 0)?;
+        squares[size] = square ^ flip_squares;
+        pieces[size] = (kind as u8 + 1 + 8 * side as u8) ^ flip_color;
+        size += 1;
+    }
+    if size != material.piece_count {
+        return None;
+    }
+    let data = file.pairs(stm, tb_file);
+
+    // reorder the pieces as stored in the sub-table
+    for i in lead_count..size.saturating_sub(1) {
+        for j in i + 1..size {
+            if data.pieces[i] == pieces[j] {
+                pieces.swap(i, j);
+                squares.swap(i, j);
+                break;
+            }
+        }
+    }
+
+    // mirror so the leading piece is on files a-d
+    if squares[0] & 7 > 3 {
+        for square in squares.iter_mut().take(size) {
+            *square ^= 7;
+        }
+    }
+
+    let mut idx: u64;
+    if material.has_pawns {
+        idx = maps.lead_pawn_idx[lead_count][squares[0]];
+        squares[1..lead_count].sort_by_key(|&square| maps.pawns[square]);
+        for (i, &square) in squares.iter().enumerate().take(lead_count).skip(1) {
+            idx += maps.binomial[i][maps.pawns[square] as usize];
+        }
+    } else {
+        // mirror so the leading piece is on ranks 1-4, then below the a1-h8 diagonal
+        if squares[0] >> 3 > 3 {
+            for square in squares.iter_mut().take(size) {
+                *square ^= 56;
+            }
+        }
+        for i in 0..data.group_len[0] {
+            let off = off_diagonal(squares[i]);
+            if off == 0 {
+                continue;
+            }
+            if off > 0 {
+                for square in squares.iter_mut().take(size).skip(i) {
+                    *square = ((*square >> 3) | (*square << 3)) & 63;
+                }
+            }
+            break;
+        }
+
+        if material.has_unique_pieces {
+            let s = &squares;
+            let adjust1 = (s[1] > s[0]) as u64;
+            let adjust2 = (s[2] > s[0]) as u64 + (s[2] > s[1]) as u64;
+            let rank = |square: usize| (square >> 3) as u64;
+            idx = if off_diagonal(s[0]) != 0 {
+                (maps.a1d1d4[s[0]] * 63 + (s[1] as u64 - adjust1)) * 62 + s[2] as u64 - adjust2
+            } else if off_diagonal(s[1]) != 0 {
+                (6 * 63 + rank(s[0]) * 28 + maps.b1h1h7[s[1]]) * 62 + s[2] as u64 - adjust2
+            } else if off_diagonal(s[2]) != 0 {
+                6 * 63 * 62 + 4 * 28 * 62 + rank(s[0]) * 7 * 28 + (rank(s[1]) - adjust1) * 28 + maps.b1h1h7[s[2]]
+            } else {
+                6 * 63 * 62 + 4 * 28 * 62 + 4 * 7 * 28 + rank(s[0]) * 7 * 6 + (rank(s[1]) - adjust1) * 6 + (rank(s[2]) - adjust2)
+            };
+        } else {
+            idx = maps.kk[maps.a1d1d4[squares[0]] as usize][squares[1]];
+        }
+    }
+
+    // the other groups, each as a combination of the squares left free by the previous ones
+    idx *= data.group_idx[0];
+    let mut start = data.group_len[0];
+    let mut remaining_pawns = material.has_pawns && material.pawn_count[1] > 0;
+    let mut next = 1;
+    while data.group_len[next] != 0 {
+        let end = start + data.group_len[next];
+        if end > size {
+            return None;
+        }
+        squares[start..end].sort_unstable();
+        let mut n = 0;
+        for i in 0..data.group_len[next] {
+            let square = squares[start + i];
+            let adjust = squares[..start].iter().filter(|&&other| square > other).count();
+            let mapped = square.checked_sub(adjust + 8 * remaining_pawns as usize)?;
+            n += maps.binomial[i + 1][mapped];
+        }
+        remaining_pawns = false;
+        idx += n * data.group_idx[next];
+        start = end;
+        next += 1;
+    }
+    Some((stm, tb_file, idx))
+}
+
+/// Whether a move captures, en passant included.
+fn is_capture(m: &Move, position: &Position) -> bool {
+    let occupied = position.bb_sides[0].0 | position.bb_sides[1].0;
+    occupied & 1u64 << m.to != 0 || (m.piece.index() == Pieces::PAWN && m.from % 8 != m.to % 8)
+}
+
+/// Whether a move resets the fifty-move counter.
+fn is_zeroing(m: &Move, position: &Position) -> bool {
+    m.piece.index() == Pieces::PAWN || m.promoted_from_pawn || is_capture(m, position)
+}
+
+/// DTZ of a position whose best move zeroes the counter.
+fn dtz_before_zeroing(wdl: Wdl) -> i32 {
+    match wdl {
+        Wdl::Win => 1,
+        Wdl::CursedWin => 101,
+        Wdl::Draw => 0,
+        Wdl::BlessedLoss => -101,
+        Wdl::Loss => -1,
+    }
+}
+
+/// Syzygy tablebase files found in one or more directories.
+///
+/// Files are read and parsed the first time a position needs them. A missing or
+/// unreadable file makes the positions it holds unprobeable (`None`) rather than
+/// failing the caller.
+pub struct Syzygy {
+    /// Paths of the files found, by file name (e.g. `KRvK.rtbw`).
+    files: HashMap<String, PathBuf>,
+    /// Files read so far; `None` if reading or parsing failed.
+    loaded: Mutex<HashMap<String, Option<Arc<TableFile>>>>,
+    max_pieces: usize,
+}
+
+impl Syzygy {
+    /// Scans directories for `.rtbw` and `.rtbz` files.
+    ///
+    /// # Arguments
+    /// * `paths` - Directories separated like `PATH` entries (`:` on Unix, `;` on Windows).
+    ///
+    /// # Errors
+    /// Returns `Err(String)` if a directory cannot be read.
+    pub fn new(paths: &str) -> Result<Syzygy, String> {
+        let mut files = HashMap::new();
+        for dir in env::split_paths(paths).filter(|dir| !dir.as_os_str().is_empty()) {
+            let entries = fs::read_dir(&dir).map_err(|e| format!("Cannot read {}: {}", dir.display(), e))?;
+            for entry in entries {
+                let path = entry.map_err(|e| format!("Cannot read {}: {}", dir.display(), e))?.path();
+                let Some((stem, extension)) = path.file_stem().zip(path.extension()) else { continue };
+                let (Some(stem), Some(extension)) = (stem.to_str(), extension.to_str()) else { continue };
+                if (extension == WDL_EXTENSION || extension == DTZ_EXTENSION) && Material::from_name(stem).is_some() {
+                    // the first directory listing a file wins
+                    files.entry(format!("{}.{}", stem, extension)).or_insert(path);
+                }
+            }
+        }
+        let max_pieces = files.keys().map(|name| name.find('.').unwrap_or(0) - 1).max().unwrap_or(0);
+        Ok(Syzygy { files, loaded: Mutex::new(HashMap::new()), max_pieces })
+    }
+
+    /// Number of files found.
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    /// Whether no files were found.
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /// Largest number of pieces of the tables found, `0` if there are none.
+    pub fn max_pieces(&self) -> usize {
+        self.max_pieces
+    }
+
+    /// Whether the directories hold a file, e.g. `KRvK.rtbw`.
+    pub fn has_file(&self, file_name: &str) -> bool {
+        self.files.contains_key(file_name)
+    }
+
+    /// The parsed file of a table, reading it on first use.
+    fn file(&self, name: &str, kind: TableKind) -> Option<Arc<TableFile>> {
+        let file_name = format!("{}.{}", name, kind.extension());
+        let path = self.files.get(&file_name)?;
+        let mut loaded = self.loaded.lock().unwrap_or_else(|e| e.into_inner());
+        loaded
+            .entry(file_name)
+            .or_insert_with(|| {
+                let material = Material::from_name(name)?;
+                let bytes = fs::read(path).ok()?;
+                TableFile::parse(bytes, kind, material).ok().map(Arc::new)
+            })
+            .clone()
+    }
+
+    /// Whether the tables may hold a position: standard chess, few enough pieces and no castling rights.
+    fn covers(&self, position: &Position) -> bool {
+        let pieces = (position.bb_sides[0].0 | position.bb_sides[1].0).count_ones() as usize;
+        position.variant == Variant::Standard && pieces <= self.max_pieces.max(2) && !can_castle(position)
+    }
+
+    /// Looks a position up in its file, ignoring en passant and the best capture.
+    fn probe_table(&self, kind: TableKind, position: &Position, color: Color, wdl: Wdl) -> Option<Lookup> {
+        let pieces = (position.bb_sides[0].0 | position.bb_sides[1].0).count_ones();
+        if pieces == 2 {
+            return Some(Lookup::Value(0));
+        }
+        let file = self.file(&table_name(position), kind)?;
+        probe_file(&file, position, color, wdl)
+    }
+
+    /// WDL of a position, resolving captures first: the tables may store any
+    /// value where capturing is best, and do not know about en passant.
+    ///
+    /// # Arguments
+    /// * `zeroing` - Whether pawn moves are resolved like captures too.
+    ///
+    /// # Returns
+    /// The result and whether the best move is a zeroing one.
+    fn search(&self, position: &Position, color: Color, zeroing: bool) -> Option<(Wdl, bool)> {
+        let moves = legal_moves(color, position);
+        let mut best = Wdl::Loss;
+        let mut searched = 0;
+        for m in &moves {
+            let resolved = if zeroing { is_zeroing(m, position) } else { is_capture(m, position) };
+            if !resolved {
+                continue;
+            }
+            searched += 1;
+            let (value, _) = self.search(&play(*m, position), color.opponent(), false)?;
+            let value = value.negate();
+            if value > best {
+                best = value;
+                if value == Wdl::Win {
+                    return Some((value, true));
+                }
+            }
+        }
+
+        // with every move searched, the stored value is not needed (and may be wrong)
+        let all_searched = searched > 0 && searched == moves.len();
+        let value = if all_searched {
+            best
+        } else {
+            match self.probe_table(TableKind::Wdl, position, color, Wdl::Draw)? {
+                Lookup::Value(value) => Wdl::from_value(value)?,
+                Lookup::OtherSide => return None,
+            }
+        };
+        if best >= value {
+            return Some((best, best > Wdl::Draw || all_searched));
+        }
+        Some((value, false))
+    }
+
+    /// Probes the WDL tables.
+    ///
+    /// # Arguments
+    /// * `position` - The board state.
+    /// * `color` - The side to move.
+    ///
+    /// # Returns
+    /// The result for the side to move, or `None` if castling is still possible or
+    /// a needed file (this position's or one after a capture) is missing or unreadable.
+    pub fn probe_wdl(&self, position: &Position, color: Color) -> Option<Wdl> {
+        if !self.covers(position) {
+            return None;
+        }
+        self.search(position, color, false).map(|(wdl, _)| wdl)
+    }
+
+    /// Probes the DTZ tables (and the WDL tables for the sign).
+    ///
+    /// # Arguments
+    /// * `position` - The board state.
+    /// * `color` - The side to move.
+    ///
+    /// # Returns
+    /// Plies to the next zeroing move (capture or pawn move) under best play: positive
+    /// if the side to move wins, negative if it loses, `0` for draws. Values beyond
+    /// 100 are cursed wins or blessed losses. `None` if the position cannot be
+    /// probed, see [`Syzygy::probe_wdl`].
+    pub fn probe_dtz(&self, position: &Position, color: Color) -> Option<i32> {
+        if !self.covers(position) {
+            return None;
+        }
+        self.dtz(position, color)
+    }
+
+    fn dtz(&self, position: &Position, color: Color) -> Option<i32> {
+        let (wdl, zeroing_best) = self.search(position, color, true)?;
+        if wdl == Wdl::Draw {
+            return Some(0);
+        }
+        // the stored value does not account for a best move that zeroes the counter
+        if zeroing_best {
+            return Some(dtz_before_zeroing(wdl));
+        }
+        let sign = wdl.value().signum();
+        if let Lookup::Value(dtz) = self.probe_table(TableKind::Dtz, position, color, wdl)? {
+            let cursed = matches!(wdl, Wdl::CursedWin | Wdl::BlessedLoss);
+            return Some((dtz + if cursed { 100 } else { 0 }) * sign);
+        }
+
+        // the file holds the other side to move: take the best DTZ after one ply
+        let mut best: Option<i32> = None;
+        for m in legal_moves(color, position) {
+            let child = play(m, position);
+            let mut dtz = if is_zeroing(&m, position) {
+                -dtz_before_zeroing(self.search(&child, color.opponent(), false)?.0)
+            } else {
+                -self.dtz(&child, color.opponent())?
+            };
+            if dtz == 1 && is_checkmated(color.opponent(), &child) {
+                best = Some(1);
+            }
+            if !is_zeroing(&m, position) {
+                dtz += dtz.signum();
+            }
+            if dtz.signum() == sign && best.is_none_or(|best| dtz < best) {
+                best = Some(dtz);
+            }
+        }
+        Some(best.unwrap_or(-1))
+    }
+
+    /// Results of all legal moves of a position, best first: wins with the fewest
+    /// plies to zeroing, then cursed wins, draws, blessed losses and losses with the most.
+    ///
+    /// # Returns
+    /// `None` if the position or a position after a move cannot be probed (both
+    /// WDL and DTZ files are needed), or if there are no legal moves.
+    pub fn root_moves(&self, position: &Position, color: Color) -> Option<Vec<RootMove>> {
+        if !self.covers(position) {
+            return None;
+        }
+        let mut moves = Vec::new();
+        for m in legal_moves(color, position) {
+            let child = play(m, position);
+            let wdl = self.search(&child, color.opponent(), false)?.0.negate();
+            let dtz = if is_zeroing(&m, position) {
+                dtz_before_zeroing(wdl)
+            } else {
+                let dtz = -self.dtz(&child, color.opponent())?;
+                if is_checkmated(color.opponent(), &child) { 1 } else { dtz + dtz.signum() }
+            };
+            moves.push(RootMove { m, wdl, dtz });
+        }
+        // among results of one kind, a smaller (more negative) DTZ is better
+        moves.sort_by_key(|root| (std::cmp::Reverse(root.wdl), root.dtz));
+        (!moves.is_empty()).then_some(moves)
+    }
+
+    /// The legal moves that keep the best result the WDL tables give for a position,
+    /// e.g. to restrict a search to them. Only the WDL files are needed.
+    ///
+    /// # Returns
+    /// `None` if the position or a position after a move cannot be probed.
+    pub fn filter_root_moves(&self, position: &Position, color: Color) -> Option<Vec<Move>> {
+        if !self.covers(position) {
+            return None;
+        }
+        let values = legal_moves(color, position)
+            .into_iter()
+            .map(|m| self.search(&play(m, position), color.opponent(), false).map(|(wdl, _)| (m, wdl.negate())))
+            .collect::<Option<Vec<_>>>()?;
+        let best = values.iter().map(|&(_, wdl)| wdl).max()?;
+        Some(values.into_iter().filter(|&(_, wdl)| wdl == best).map(|(m, _)| m).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fen::parse_fen;
+
+    const RESULTS: [Wdl; 5] = [Wdl::Loss, Wdl::BlessedLoss, Wdl::Draw, Wdl::CursedWin, Wdl::Win];
+
+    #[test]
+    fn results_flip_with_the_side() {
+        for wdl in RESULTS {
+            assert_eq!(wdl.negate().negate(), wdl);
+            assert_eq!(wdl.negate().value(), -wdl.value());
+            assert_eq!(wdl.negate().wdl(), -wdl.wdl());
+            assert_eq!(wdl.negate().to_score(3), -wdl.to_score(3));
+            assert_eq!(dtz_before_zeroing(wdl.negate()), -dtz_before_zeroing(wdl));
+            assert_eq!(Wdl::from_value(wdl.value()), Some(wdl));
+        }
+        assert!(Wdl::Win.to_score(1) > Wdl::Win.to_score(2));
+        assert!(Wdl::CursedWin.to_score(1) > 0 && Wdl::CursedWin.to_score(1) < Wdl::Win.to_score(100));
+    }
+
+    #[test]
+    fn table_names() {
+        let (position, _) = parse_fen("8/8/8/4k3/8/8/1r6/KR6 w - - 0 1").unwrap();
+        assert_eq!(table_name(&position), "KRvKR");
+        let (position, _) = parse_fen("8/8/8/4k3/8/8/1q6/K7 w - - 0 1").unwrap();
+        assert_eq!(table_name(&position), "KQvK");
+    }
+
+    #[test]
+    fn missing_tables() {
+        let syzygy = Syzygy::new("").unwrap();
+        assert!(syzygy.is_empty());
+        assert!(Syzygy::new("/nonexistent/syzygy").is_err());
+        // bare kings need no file
+        let (position, _) = parse_fen("8/8/8/4k3/8/8/8/K7 w - - 0 1").unwrap();
+        for color in [Color::White, Color::Black] {
+            assert_eq!(syzygy.probe_wdl(&position, color), Some(Wdl::Draw));
+            assert_eq!(syzygy.probe_dtz(&position, color), Some(0));
+        }
+        let moves = syzygy.root_moves(&position, Color::White).unwrap();
+        assert!(moves.iter().all(|root| root.wdl == Wdl::Draw && root.dtz == 0));
+        assert_eq!(syzygy.filter_root_moves(&position, Color::White).unwrap().len(), moves.len());
+        // anything else needs a file
+        let (position, _) = parse_fen("8/8/8/4k3/8/8/1q6/K7 w - - 0 1").unwrap();
+        for color in [Color::White, Color::Black] {
+            assert_eq!(syzygy.probe_wdl(&position, color), None);
+            assert_eq!(syzygy.probe_dtz(&position, color), None);
+            assert!(syzygy.root_moves(&position, color).is_none());
+        }
+    }
+
+    #[test]
+    fn captures_are_resolved_before_the_tables() {
+        let dir = env::temp_dir().join(format!("syzygy-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        // found, but unreadable
+        fs::write(dir.join("KQvK.rtbw"), b"not a table").unwrap();
+        let syzygy = Syzygy::new(dir.to_str().unwrap()).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(syzygy.max_pieces(), 3);
+        assert!(syzygy.has_file("KQvK.rtbw"));
+
+        // Kxb2 is White's only move and leaves bare kings
+        let (position, _) = parse_fen("7k/8/8/8/8/8/1q6/K7 w - - 0 1").unwrap();
+        assert_eq!(syzygy.probe_wdl(&position, Color::White), Some(Wdl::Draw));
+        assert_eq!(syzygy.probe_dtz(&position, Color::White), Some(0));
+        // Black has quiet moves, which only the file can judge
+        let (position, color) = parse_fen("7k/8/8/8/8/8/2q5/K7 b - - 0 1").unwrap();
+        assert_eq!(syzygy.probe_wdl(&position, color), None);
+        assert_eq!(syzygy.probe_dtz(&position, color), None);
+        // castling rights keep positions out of the tables
+        let (position, color) = parse_fen("4k3/8/8/8/8/8/8/4K2R w K - 0 1").unwrap();
+        assert_eq!(syzygy.probe_wdl(&position, color), None);
+    }
+}
@@ -0,0 +1,1047 @@
+use std::cmp::{Ordering, Reverse};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::thread;
+
+use crate::make_move::legal_moves;
+use crate::moves::Move;
+use crate::piece::Color;
+use crate::position::{Pieces, Position, Sides};
+use crate::search::{MATE_SCORE, play};
+use crate::variant::Variant;
+
+// see: https://www.chessprogramming.org/Retrograde_Analysis
+// and: https://www.chessprogramming.org/Endgame_Tablebases
+
+/// Largest number of pieces (kings included) a table can hold.
+pub const MAX_PIECES: usize = 4;
+/// Magic bytes at the start of a table file.
+pub const MAGIC: &[u8; 4] = b"RCTB";
+/// File extension of table files, e.g. `KRKP.rtb`.
+pub const EXTENSION: &str = "rtb";
+
+/*
+Table file format (all integers little endian):
+
+  magic        4 bytes, "RCTB"
+  name length  u8
+  name         ASCII, e.g. "KRKP": the stronger side's pieces (White), then the other side's (Black)
+  entries      u32, number of entries
+  entry        u8 x entries
+
+Entries are indexed by
+
+  ((side to move * K + king slot) * 64 + square 1) * 64 + square 2 ...
+
+where White is 0, the king slot is the white king's square after the symmetry
+reduction below, and squares 1.. are those of the other pieces in name order
+(black king first after the white pieces: K, white pieces, K, black pieces).
+
+Symmetry reduction: without pawns the board is rotated/mirrored so that the white
+king stands in the a1-d1-d4 triangle (K = 10); with pawns it is only mirrored
+left-right, so that the white king stands on files a-d (K = 32). If several
+symmetries qualify, the one giving the smallest index is used and the entries
+of the others are invalid.
+
+Entry values:
+
+  0        draw
+  1..=253  distance to mate in plies + 1; odd distances are wins for the side
+           to move, even ones losses (0 = checkmated)
+  255      invalid or unused index
+
+Castling and en passant rights are not part of the tables (see `Tablebase::probe`).
+*/
+
+const DRAW: u8 = 0;
+const INVALID: u8 = 255;
+/// Marks stalemates during generation, so they are never turned into losses; stored as a draw.
+const STALEMATE: u8 = 254;
+/// Longest distance to mate (in plies) an entry can hold.
+const MAX_DISTANCE: u32 = 252;
+
+/// White king squares of pawnless tables: the a1-d1-d4 triangle.
+const TRIANGLE: [u8; 10] = [0, 1, 2, 3, 9, 10, 11, 18, 19, 27];
+
+const KNIGHT_STEPS: [(i8, i8); 8] = [(1, 2), (2, 1), (2, -1), (1, -2), (-1, -2), (-2, -1), (-2, 1), (-1, 2)];
+const KING_STEPS: [(i8, i8); 8] = [(1, 0), (1, 1), (0, 1), (-1, 1), (-1, 0), (-1, -1), (0, -1), (1, -1)];
+const ROOK_DIRECTIONS: [(i8, i8); 4] = [(1, 0), (0, 1), (-1, 0), (0, -1)];
+const BISHOP_DIRECTIONS: [(i8, i8); 4] = [(1, 1), (-1, 1), (-1, -1), (1, -1)];
+const PROMOTIONS: [usize; 4] = [Pieces::QUEEN, Pieces::ROOK, Pieces::BISHOP, Pieces::KNIGHT];
+const PIECE_LETTERS: [char; 6] = ['P', 'N', 'B', 'R', 'Q', 'K'];
+
+/// Result of a position under perfect play, from the point of view of the side to move.
+///
+/// Distances count plies until checkmate. The fifty-move rule is ignored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TbValue {
+    /// The side to move mates in this many plies (always odd).
+    Win(u32),
+    Draw,
+    /// The side to move is mated in this many plies (always even; `0` = checkmated).
+    Loss(u32),
+}
+
+impl TbValue {
+    fn from_entry(entry: u8) -> Option<TbValue> {
+        match entry {
+            INVALID => None,
+            DRAW | STALEMATE => Some(TbValue::Draw),
+            entry => {
+                let distance = entry as u32 - 1;
+                Some(if distance % 2 == 1 { TbValue::Win(distance) } else { TbValue::Loss(distance) })
+            }
+        }
+    }
+
+    /// Win, draw or loss as `1`, `0` or `-1`, e.g. for [`Adjudication::tablebase`](crate::match_runner::Adjudication::tablebase).
+    pub fn wdl(self) -> i32 {
+        match self {
+            TbValue::Win(_) => 1,
+            TbValue::Draw => 0,
+            TbValue::Loss(_) => -1,
+        }
+    }
+
+    /// The value as a search score at `ply`, using the same mate scores as [`crate::search`].
+    pub fn to_score(self, ply: i32) -> i32 {
+        match self {
+            TbValue::Win(distance) => MATE_SCORE - ply - distance as i32,
+            TbValue::Draw => 0,
+            TbValue::Loss(distance) => -(MATE_SCORE - ply - distance as i32),
+        }
+    }
+
+    /// The value of the position before a move, given the value after it (for the opponent).
+    pub fn parent(self) -> TbValue {
+        match self {
+            TbValue::Win(distance) => TbValue::Loss(distance + 1),
+            TbValue::Draw => TbValue::Draw,
+            TbValue::Loss(distance) => TbValue::Win(distance + 1),
+        }
+    }
+}
+
+/// The pieces of a position in some order, with the side to move.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Setup {
+    len: usize,
+    kinds: [usize; MAX_PIECES],
+    colors: [Color; MAX_PIECES],
+    squares: [u8; MAX_PIECES],
+    color: Color,
+}
+
+impl Setup {
+    /// Reads the pieces of a position, or `None` if there are more than [`MAX_PIECES`].
+    fn from_position(position: &Position, color: Color) -> Option<Setup> {
+        if (position.bb_sides[Sides::WHITE].0 | position.bb_sides[Sides::BLACK].0).count_ones() as usize > MAX_PIECES {
+            return None;
+        }
+        let mut setup = Setup { len: 0, kinds: [0; MAX_PIECES], colors: [Color::White; MAX_PIECES], squares: [0; MAX_PIECES], color };
+        for (side, piece_color) in [(Sides::WHITE, Color::White), (Sides::BLACK, Color::Black)] {
+            for kind in 0..6 {
+                let mut bits = position.bb_pieces[side][kind].0;
+                while bits != 0 {
+                    setup.push(kind, piece_color, bits.trailing_zeros() as u8);
+                    bits &= bits - 1;
+                }
+            }
+        }
+        Some(setup)
+    }
+
+    fn push(&mut self, kind: usize, color: Color, square: u8) {
+        self.kinds[self.len] = kind;
+        self.colors[self.len] = color;
+        self.squares[self.len] = square;
+        self.len += 1;
+    }
+
+    fn remove(&mut self, i: usize) {
+        for j in i..self.len - 1 {
+            self.kinds[j] = self.kinds[j + 1];
+            self.colors[j] = self.colors[j + 1];
+            self.squares[j] = self.squares[j + 1];
+        }
+        self.len -= 1;
+    }
+
+    fn occupied(&self) -> u64 {
+        self.squares[..self.len].iter().fold(0, |bits, &square| bits | 1u64 << square)
+    }
+
+    fn occupied_by(&self, color: Color) -> u64 {
+        (0..self.len).filter(|&i| self.colors[i] == color).fold(0, |bits, i| bits | 1u64 << self.squares[i])
+    }
+
+    fn piece_at(&self, square: u8) -> Option<usize> {
+        (0..self.len).find(|&i| self.squares[i] == square)
+    }
+
+    /// Whether `color`'s king is attacked.
+    fn in_check(&self, color: Color) -> bool {
+        let Some(king) = (0..self.len).find(|&i| self.kinds[i] == Pieces::KING && self.colors[i] == color) else {
+            return false;
+        };
+        let occupied = self.occupied();
+        (0..self.len).any(|i| {
+            self.colors[i] != color && attacks(self.kinds[i], self.colors[i], self.squares[i], occupied) & 1u64 << self.squares[king] != 0
+        })
+    }
+
+    /// Whether the setup is a position that can occur with this side to move: no two
+    /// pieces on a square, no pawns on the first or last rank, and the side that just
+    /// moved not in check.
+    fn is_valid(&self) -> bool {
+        let occupied = self.occupied();
+        occupied.count_ones() as usize == self.len
+            && (0..self.len).all(|i| self.kinds[i] != Pieces::PAWN || (1..7).contains(&(self.squares[i] / 8)))
+            && !self.in_check(self.color.opponent())
+    }
+
+    /// Calls `visit` with the position after every legal move.
+    ///
+    /// Pieces keep their order, except that a captured piece is removed.
+    fn for_each_child(&self, mut visit: impl FnMut(&Setup)) {
+        let us = self.color;
+        let occupied = self.occupied();
+        let own = self.occupied_by(us);
+        let enemy = occupied & !own;
+        for i in (0..self.len).filter(|&i| self.colors[i] == us) {
+            let (kind, from) = (self.kinds[i], self.squares[i]);
+            let mut targets = if kind == Pieces::PAWN {
+                let forward: i8 = if us == Color::White { 8 } else { -8 };
+                let one = (from as i8 + forward) as u8;
+                let mut pushes = 0;
+                if occupied & 1u64 << one == 0 {
+                    pushes |= 1u64 << one;
+                    let start_rank = if us == Color::White { 1 } else { 6 };
+                    let two = (one as i8 + forward) as u8;
+                    if from / 8 == start_rank && occupied & 1u64 << two == 0 {
+                        pushes |= 1u64 << two;
+                    }
+                }
+                pushes | attacks(kind, us, from, occupied) & enemy
+            } else {
+                attacks(kind, us, from, occupied) & !own
+            };
+            while targets != 0 {
+                let to = targets.trailing_zeros() as u8;
+                targets &= targets - 1;
+                let mut child = *self;
+                child.squares[i] = to;
+                child.color = us.opponent();
+                let mut moved = i;
+                if let Some(j) = self.piece_at(to) {
+                    if self.kinds[j] == Pieces::KING {
+                        continue;
+                    }
+                    child.remove(j);
+                    if j < i {
+                        moved -= 1;
+                    }
+                }
+                if child.in_check(us) {
+                    continue;
+                }
+                if kind == Pieces::PAWN && (to / 8 == 0 || to / 8 == 7) {
+                    for promotion in PROMOTIONS {
+                        child.kinds[moved] = promotion;
+                        visit(&child);
+                    }
+                } else {
+                    visit(&child);
+                }
+            }
+        }
+    }
+
+    /// Calls `visit` with every position from which a non-capturing, non-promoting
+    /// move of the side that just moved leads to this one.
+    ///
+    /// The predecessors are not checked for validity.
+    fn for_each_parent(&self, mut visit: impl FnMut(&Setup)) {
+        let mover = self.color.opponent();
+        let occupied = self.occupied();
+        for i in (0..self.len).filter(|&i| self.colors[i] == mover) {
+            let (kind, to) = (self.kinds[i], self.squares[i]);
+            let mut origins = if kind == Pieces::PAWN {
+                let back: i8 = if mover == Color::White { -8 } else { 8 };
+                let (start_rank, double_rank) = if mover == Color::White { (1, 3) } else { (6, 4) };
+                let one = (to as i8 + back) as u8;
+                let mut origins = 0;
+                if (1..7).contains(&(one / 8)) && occupied & 1u64 << one == 0 {
+                    origins |= 1u64 << one;
+                    let two = (one as i8 + back) as u8;
+                    if to / 8 == double_rank && two / 8 == start_rank && occupied & 1u64 << two == 0 {
+                        origins |= 1u64 << two;
+                    }
+                }
+                origins
+            } else {
+                attacks(kind, mover, to, occupied) & !occupied
+            };
+            while origins != 0 {
+                let from = origins.trailing_zeros() as u8;
+                origins &= origins - 1;
+                let mut parent = *self;
+                parent.squares[i] = from;
+                parent.color = mover;
+                visit(&parent);
+            }
+        }
+    }
+}
+
+/// The square `(file_step, rank_step)` away from `square`, if it is on the board.
+fn step(square: u8, file_step: i8, rank_step: i8) -> Option<u8> {
+    let file = (square % 8) as i8 + file_step;
+    let rank = (square / 8) as i8 + rank_step;
+    ((0..8).contains(&file) && (0..8).contains(&rank)).then_some((rank * 8 + file) as u8)
+}
+
+/// Squares reached from each square by one of `steps`.
+const fn step_table<const N: usize>(steps: [(i8, i8); N]) -> [u64; 64] {
+    let mut table = [0; 64];
+    let mut square = 0;
+    while square < 64 {
+        let mut i = 0;
+        while i < N {
+            let file = (square % 8) as i8 + steps[i].0;
+            let rank = (square / 8) as i8 + steps[i].1;
+            if file >= 0 && file < 8 && rank >= 0 && rank < 8 {
+                table[square] |= 1u64 << (rank * 8 + file);
+            }
+            i += 1;
+        }
+        square += 1;
+    }
+    table
+}
+
+const KNIGHT_ATTACKS: [u64; 64] = step_table(KNIGHT_STEPS);
+const KING_ATTACKS: [u64; 64] = step_table(KING_STEPS);
+const PAWN_ATTACKS: [[u64; 64]; 2] = [step_table([(-1, 1), (1, 1)]), step_table([(-1, -1), (1, -1)])];
+
+/// Squares attacked by a piece of `kind` and `color` on `from`, with sliding pieces
+/// blocked by `occupied`.
+fn attacks(kind: usize, color: Color, from: u8, occupied: u64) -> u64 {
+    let slides = |directions: &[(i8, i8)]| {
+        let mut bits = 0;
+        for &(f, r) in directions {
+            let mut square = from;
+            while let Some(to) = step(square, f, r) {
+                bits |= 1u64 << to;
+                if occupied & 1u64 << to != 0 {
+                    break;
+                }
+                square = to;
+            }
+        }
+        bits
+    };
+    match kind {
+        Pieces::PAWN => PAWN_ATTACKS[color.index()][from as usize],
+        Pieces::KNIGHT => KNIGHT_ATTACKS[from as usize],
+        Pieces::BISHOP => slides(&BISHOP_DIRECTIONS),
+        Pieces::ROOK => slides(&ROOK_DIRECTIONS),
+        Pieces::QUEEN => slides(&ROOK_DIRECTIONS) | slides(&BISHOP_DIRECTIONS),
+        _ => KING_ATTACKS[from as usize],
+    }
+}
+
+/// Applies one of the 8 board symmetries: bit 2 swaps files and ranks, bit 0
+/// mirrors the files, bit 1 the ranks.
+fn transform(square: u8, symmetry: usize) -> u8 {
+    let (mut file, mut rank) = (square % 8, square / 8);
+    if symmetry & 4 != 0 {
+        std::mem::swap(&mut file, &mut rank);
+    }
+    if symmetry & 1 != 0 {
+        file = 7 - file;
+    }
+    if symmetry & 2 != 0 {
+        rank = 7 - rank;
+    }
+    rank * 8 + file
+}
+
+/// Orders the non-king material of two sides: more pieces first, then stronger pieces.
+fn compare_material(a: &[usize], b: &[usize]) -> Ordering {
+    a.len().cmp(&b.len()).then_with(|| a.cmp(b))
+}
+
+/// Name of the ending with `strong` (white) and `weak` (black) non-king pieces,
+/// each sorted strongest first.
+fn ending_name(strong: &[usize], weak: &[usize]) -> String {
+    let letters = |kinds: &[usize]| kinds.iter().map(|&kind| PIECE_LETTERS[kind]).collect::<String>();
+    format!("K{}K{}", letters(strong), letters(weak))
+}
+
+/// Numeric key of the ending with `strong` (white) and `weak` (black) non-king
+/// pieces, each sorted strongest first; cheaper to look up than the name.
+fn material_key(strong: &[usize], weak: &[usize]) -> u32 {
+    let key = strong.iter().fold(0, |key, &kind| key * 7 + kind as u32 + 1);
+    weak.iter().fold(key * 7, |key, &kind| key * 7 + kind as u32 + 1)
+}
+
+/// Splits a name like `KRKP` into the non-king pieces of both sides, strongest first.
+fn parse_name(name: &str) -> Result<(Vec<usize>, Vec<usize>), String> {
+    let invalid = || format!("invalid ending '{}'", name);
+    let upper = name.to_ascii_uppercase();
+    let rest = upper.strip_prefix('K').ok_or_else(invalid)?;
+    let (white, black) = rest.split_once('K').ok_or_else(invalid)?;
+    let kinds = |letters: &str| -> Result<Vec<usize>, String> {
+        let mut kinds = letters
+            .chars()
+            .map(|c| PIECE_LETTERS[..5].iter().position(|&letter| letter == c).ok_or_else(invalid))
+            .collect::<Result<Vec<_>, _>>()?;
+        kinds.sort_by(|a, b| b.cmp(a));
+        Ok(kinds)
+    };
+    Ok((kinds(white)?, kinds(black)?))
+}
+
+/// All endings with `pieces` pieces (kings included), by name.
+pub fn endings(pieces: usize) -> Vec<String> {
+    let mut names = Vec::new();
+    let others = pieces.saturating_sub(2);
+    // non-increasing sequences of piece kinds
+    fn sets(count: usize, max_kind: usize) -> Vec<Vec<usize>> {
+        if count == 0 {
+            return vec![Vec::new()];
+        }
+        (0..=max_kind)
+            .rev()
+            .flat_map(|kind| {
+                sets(count - 1, kind).into_iter().map(move |mut rest| {
+                    rest.insert(0, kind);
+                    rest
+                })
+            })
+            .collect()
+    }
+    for weak_count in 0..=others / 2 {
+        for strong in sets(others - weak_count, Pieces::QUEEN) {
+            for weak in sets(weak_count, Pieces::QUEEN) {
+                if compare_material(&strong, &weak) != Ordering::Less {
+                    let name = ending_name(&strong, &weak);
+                    if !names.contains(&name) {
+                        names.push(name);
+                    }
+                }
+            }
+        }
+    }
+    names
+}
+
+/// One table: the values of all positions of an ending.
+#[derive(Debug, Clone)]
+pub struct Table {
+    name: String,
+    /// Piece kinds in index order: white king, white pieces, black king, black pieces.
+    kinds: Vec<usize>,
+    colors: Vec<Color>,
+    pawns: bool,
+    data: Vec<u8>,
+}
+
+impl Table {
+    fn empty(strong: &[usize], weak: &[usize]) -> Table {
+        let mut kinds = vec![Pieces::KING];
+        kinds.extend_from_slice(strong);
+        kinds.push(Pieces::KING);
+        kinds.extend_from_slice(weak);
+        let colors = (0..kinds.len()).map(|i| if i <= strong.len() { Color::White } else { Color::Black }).collect();
+        let pawns = kinds.contains(&Pieces::PAWN);
+        let king_slots = if pawns { 32 } else { TRIANGLE.len() };
+        let size = 2 * king_slots * 64usize.pow(kinds.len() as u32 - 1);
+        Table { name: ending_name(strong, weak), kinds, colors, pawns, data: vec![DRAW; size] }
+    }
+
+    /// Name of the ending, e.g. `KRKP`.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Number of entries, invalid ones included.
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    fn king_slots(&self) -> usize {
+        if self.pawns { 32 } else { TRIANGLE.len() }
+    }
+
+    fn symmetries(&self) -> usize {
+        if self.pawns { 2 } else { 8 }
+    }
+
+    /// Index of a setup (pieces in table order) under one symmetry, if that symmetry
+    /// brings the white king into the reduced area.
+    fn index_with(&self, setup: &Setup, symmetry: usize) -> Option<usize> {
+        let king = transform(setup.squares[0], symmetry);
+        let slot = if self.pawns {
+            (king % 8 < 4).then_some((king / 8 * 4 + king % 8) as usize)?
+        } else {
+            TRIANGLE.iter().position(|&square| square == king)?
+        };
+        let mut index = setup.color.index() * self.king_slots() + slot;
+        for &square in &setup.squares[1..setup.len] {
+            index = index * 64 + transform(square, symmetry) as usize;
+        }
+        Some(index)
+    }
+
+    /// Index of a setup whose pieces are in table order.
+    fn index(&self, setup: &Setup) -> usize {
+        (0..self.symmetries()).filter_map(|symmetry| self.index_with(setup, symmetry)).min().expect("a symmetry fits")
+    }
+
+    fn decode(&self, mut index: usize) -> Setup {
+        let mut setup = Setup {
+            len: self.kinds.len(),
+            kinds: [0; MAX_PIECES],
+            colors: [Color::White; MAX_PIECES],
+            squares: [0; MAX_PIECES],
+            color: Color::White,
+        };
+        setup.kinds[..self.kinds.len()].copy_from_slice(&self.kinds);
+        setup.colors[..self.colors.len()].copy_from_slice(&self.colors);
+        for i in (1..setup.len).rev() {
+            setup.squares[i] = (index % 64) as u8;
+            index /= 64;
+        }
+        let slot = index % self.king_slots();
+        setup.squares[0] = if self.pawns { (slot / 4 * 8 + slot % 4) as u8 } else { TRIANGLE[slot] };
+        setup.color = if index / self.king_slots() == 0 { Color::White } else { Color::Black };
+        setup
+    }
+
+    /// Serializes the table in the format documented at the top of `tablebase.rs`.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.data.len() + 16);
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(self.name.len() as u8);
+        bytes.extend_from_slice(self.name.as_bytes());
+        bytes.extend_from_slice(&(self.data.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&self.data);
+        bytes
+    }
+
+    /// Reads a table written by [`Table::to_bytes`].
+    ///
+    /// # Errors
+    /// Returns `Err(String)` if the data is not a valid table.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Table, String> {
+        if bytes.len() < 5 || &bytes[0..4] != MAGIC {
+            return Err("not a tablebase file (bad magic)".to_string());
+        }
+        let name_end = 5 + bytes[4] as usize;
+        let name = bytes.get(5..name_end).and_then(|name| std::str::from_utf8(name).ok()).ok_or("truncated table name")?;
+        let (strong, weak) = parse_name(name)?;
+        let table = Table::empty(&strong, &weak);
+        if table.name != name || compare_material(&strong, &weak) == Ordering::Less {
+            return Err(format!("'{}' is not a canonical ending name", name));
+        }
+        let count = bytes.get(name_end..name_end + 4).ok_or("truncated table header")?;
+        let count = u32::from_le_bytes(count.try_into().expect("4 bytes")) as usize;
+        let data = &bytes[name_end + 4..];
+        if count != table.data.len() || data.len() != count {
+            return Err(format!("table {} has {} entries, expected {}", name, data.len(), table.data.len()));
+        }
+        Ok(Table { data: data.to_vec(), ..table })
+    }
+}
+
+/// A set of tables, generated by retrograde analysis or loaded from disk.
+///
+/// Covers endings of up to [`MAX_PIECES`] pieces, e.g. KQK, KRK, KPK, KBNK or KRKP.
+/// Positions with only the two kings are always drawn and need no table.
+#[derive(Debug, Clone, Default)]
+pub struct Tablebase {
+    /// Tables by [`material_key`].
+    tables: HashMap<u32, Table>,
+}
+
+impl Tablebase {
+    pub fn new() -> Self {
+        Tablebase::default()
+    }
+
+    /// Names of the loaded tables, sorted.
+    pub fn names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.tables.values().map(|table| table.name.as_str()).collect();
+        names.sort();
+        names
+    }
+
+    /// Returns the table of an ending, if loaded.
+    pub fn table(&self, name: &str) -> Option<&Table> {
+        let (strong, weak) = parse_name(name).ok()?;
+        let (strong, weak) = if compare_material(&strong, &weak) == Ordering::Less { (weak, strong) } else { (strong, weak) };
+        self.tables.get(&material_key(&strong, &weak))
+    }
+
+    /// Adds a table, replacing one of the same ending.
+    pub fn insert(&mut self, table: Table) {
+        let (strong, weak) = parse_name(&table.name).expect("tables have valid names");
+        self.tables.insert(material_key(&strong, &weak), table);
+    }
+
+    /// Generates the table of an ending, e.g. `KRKP`, and first the tables of all
+    /// endings it can turn into by captures and promotions. Tables that are already
+    /// present are kept.
+    ///
+    /// The sides may be given in either order; tables are always stored with the
+    /// stronger side as White.
+    ///
+    /// # Errors
+    /// Returns `Err(String)` if the name is invalid or has more than [`MAX_PIECES`] pieces.
+    pub fn generate(&mut self, name: &str) -> Result<(), String> {
+        let (strong, weak) = parse_name(name)?;
+        if strong.len() + weak.len() + 2 > MAX_PIECES {
+            return Err(format!("{} has more than {} pieces", name, MAX_PIECES));
+        }
+        self.generate_material(strong, weak);
+        Ok(())
+    }
+
+    fn generate_material(&mut self, strong: Vec<usize>, weak: Vec<usize>) {
+        let (strong, weak) = if compare_material(&strong, &weak) == Ordering::Less { (weak, strong) } else { (strong, weak) };
+        if strong.is_empty() || self.tables.contains_key(&material_key(&strong, &weak)) {
+            return;
+        }
+        let sorted = |mut kinds: Vec<usize>| {
+            kinds.sort_by(|a, b| b.cmp(a));
+            kinds
+        };
+        // captures
+        for i in 0..strong.len() {
+            let mut rest = strong.clone();
+            rest.remove(i);
+            self.generate_material(rest, weak.clone());
+        }
+        for i in 0..weak.len() {
+            let mut rest = weak.clone();
+            rest.remove(i);
+            self.generate_material(strong.clone(), rest);
+        }
+        // promotions
+        for promotion in PROMOTIONS {
+            if let Some(i) = strong.iter().position(|&kind| kind == Pieces::PAWN) {
+                let mut promoted = strong.clone();
+                promoted[i] = promotion;
+                self.generate_material(sorted(promoted), weak.clone());
+            }
+            if let Some(i) = weak.iter().position(|&kind| kind == Pieces::PAWN) {
+                let mut promoted = weak.clone();
+                promoted[i] = promotion;
+                self.generate_material(strong.clone(), sorted(promoted));
+            }
+        }
+        let table = self.build(&strong, &weak);
+        self.insert(table);
+    }
+
+    /// Value of a child position of a table being generated: a position of the same
+    /// table (`Ok(index)`) or of another, already generated one (`Err(value)`).
+    fn classify(&self, table: &Table, parent: &Setup, child: &Setup) -> Result<usize, TbValue> {
+        if child.len == parent.len && child.kinds == parent.kinds {
+            Ok(table.index(child))
+        } else {
+            Err(self.probe_setup(child).expect("tables of smaller endings are generated first"))
+        }
+    }
+
+    /// Retrograde analysis of one ending.
+    ///
+    /// Every position is first checked for mate, stalemate and moves leaving the
+    /// table (captures and promotions, looked up in the smaller tables); this pass
+    /// runs on all available threads. Results are then finalized in order of
+    /// distance: a position with a move to a lost position is won one ply later, and
+    /// a position whose moves all lead to won positions is lost one ply after the
+    /// longest of them. The predecessors of each newly finalized position are found
+    /// by un-making moves. Everything left over is a draw.
+    fn build(&self, strong: &[usize], weak: &[usize]) -> Table {
+        let mut table = Table::empty(strong, weak);
+        let size = table.data.len();
+        let mut data = std::mem::take(&mut table.data);
+        // per position: distinct successors in the table not yet known to be won,
+        // the longest loss through moves leaving the table, and whether such a move
+        // draws or wins
+        let mut successors = vec![0u8; size];
+        let mut exit_loss = vec![0u8; size];
+        let mut cannot_lose = vec![false; size];
+
+        let threads = thread::available_parallelism().map_or(1, |n| n.get());
+        let chunk = size.div_ceil(threads);
+        let table_ref = &table;
+        let scheduled: Vec<(u32, u32)> = thread::scope(|scope| {
+            let handles: Vec<_> = data
+                .chunks_mut(chunk)
+                .zip(successors.chunks_mut(chunk))
+                .zip(exit_loss.chunks_mut(chunk))
+                .zip(cannot_lose.chunks_mut(chunk))
+                .enumerate()
+                .map(|(n, (((data, successors), exit_loss), cannot_lose))| {
+                    scope.spawn(move || self.first_pass(table_ref, n * chunk, data, successors, exit_loss, cannot_lose))
+                })
+                .collect();
+            handles.into_iter().flat_map(|handle| handle.join().expect("generator thread panicked")).collect()
+        });
+
+        // positions to finalize, by distance; the distance's parity tells win from loss
+        let mut pending: Vec<Vec<u32>> = Vec::new();
+        let schedule = |pending: &mut Vec<Vec<u32>>, distance: u32, index: u32| {
+            let distance = distance.min(MAX_DISTANCE) as usize;
+            if pending.len() <= distance {
+                pending.resize(distance + 1, Vec::new());
+            }
+            pending[distance].push(index);
+        };
+        for (distance, index) in scheduled {
+            schedule(&mut pending, distance, index);
+        }
+
+        let mut parents = Vec::new();
+        let mut distance = 0;
+        while distance < pending.len() {
+            let mut finalized = Vec::new();
+            for index in std::mem::take(&mut pending[distance]) {
+                if data[index as usize] == DRAW {
+                    data[index as usize] = distance as u8 + 1;
+                    finalized.push(index);
+                }
+            }
+            for index in finalized {
+                parents.clear();
+                table.decode(index as usize).for_each_parent(|parent| parents.push(table.index(parent) as u32));
+                parents.sort_unstable();
+                parents.dedup();
+                for &parent in &parents {
+                    let p = parent as usize;
+                    if data[p] != DRAW {
+                        continue;
+                    }
+                    if distance % 2 == 0 {
+                        // a move to a lost position wins
+                        schedule(&mut pending, distance as u32 + 1, parent);
+                    } else {
+                        successors[p] -= 1;
+                        if successors[p] == 0 && !cannot_lose[p] {
+                            schedule(&mut pending, (distance as u32 + 1).max(exit_loss[p] as u32), parent);
+                        }
+                    }
+                }
+            }
+            distance += 1;
+        }
+        for entry in &mut data {
+            if *entry == STALEMATE {
+                *entry = DRAW;
+            }
+        }
+        table.data = data;
+        table
+    }
+
+    /// First pass of [`Tablebase::build`] over the positions from `start` on.
+    ///
+    /// # Returns
+    /// The positions already known to be won or lost, with their distances.
+    fn first_pass(
+        &self,
+        table: &Table,
+        start: usize,
+        data: &mut [u8],
+        successors: &mut [u8],
+        exit_loss: &mut [u8],
+        cannot_lose: &mut [bool],
+    ) -> Vec<(u32, u32)> {
+        let mut scheduled = Vec::new();
+        let mut distinct = Vec::new();
+        for (i, entry) in data.iter_mut().enumerate() {
+            let index = start + i;
+            let setup = table.decode(index);
+            if !setup.is_valid() || table.index(&setup) != index {
+                *entry = INVALID;
+                continue;
+            }
+            distinct.clear();
+            let (mut moves, mut best_win, mut worst_loss) = (0, None, 0);
+            setup.for_each_child(|child| {
+                moves += 1;
+                match self.classify(table, &setup, child) {
+                    Ok(child_index) => {
+                        if !distinct.contains(&child_index) {
+                            distinct.push(child_index);
+                        }
+                    }
+                    Err(value) => match value.parent() {
+                        TbValue::Win(d) => {
+                            best_win = Some(best_win.map_or(d, |best: u32| best.min(d)));
+                            cannot_lose[i] = true;
+                        }
+                        TbValue::Draw => cannot_lose[i] = true,
+                        TbValue::Loss(d) => worst_loss = worst_loss.max(d),
+                    },
+                }
+            });
+            successors[i] = distinct.len() as u8;
+            exit_loss[i] = worst_loss.min(MAX_DISTANCE) as u8;
+            if moves == 0 {
+                if setup.in_check(setup.color) {
+                    scheduled.push((0, index as u32));
+                } else {
+                    *entry = STALEMATE;
+                }
+            } else if let Some(distance) = best_win {
+                scheduled.push((distance, index as u32));
+            } else if distinct.is_empty() && !cannot_lose[i] {
+                scheduled.push((worst_loss, index as u32));
+            }
+        }
+        scheduled
+    }
+
+    /// Generates all endings of up to `pieces` pieces (at most [`MAX_PIECES`]).
+    pub fn generate_all(&mut self, pieces: usize) {
+        for count in 3..=pieces.min(MAX_PIECES) {
+            for name in endings(count) {
+                self.generate(&name).expect("generated names are valid");
+            }
+        }
+    }
+
+    /// Looks up a setup with pieces in any order.
+    fn probe_setup(&self, setup: &Setup) -> Option<TbValue> {
+        if setup.len == 2 {
+            return Some(TbValue::Draw);
+        }
+        // pieces of each side, king first, then strongest first
+        let mut sides = [[(0, 0); MAX_PIECES]; 2];
+        let mut counts = [0; 2];
+        for i in 0..setup.len {
+            let side = setup.colors[i].index();
+            sides[side][counts[side]] = (setup.kinds[i], setup.squares[i]);
+            counts[side] += 1;
+        }
+        let [white, black] = &mut sides;
+        let (white, black) = (&mut white[..counts[0]], &mut black[..counts[1]]);
+        white.sort_unstable_by_key(|&(kind, _)| Reverse(kind));
+        black.sort_unstable_by_key(|&(kind, _)| Reverse(kind));
+        let material = |pieces: &[(usize, u8)]| {
+            let mut kinds = [0; MAX_PIECES];
+            for (kind, &(piece, _)) in kinds.iter_mut().zip(&pieces[1..]) {
+                *kind = piece;
+            }
+            (kinds, pieces.len() - 1)
+        };
+        let ((white_kinds, white_count), (black_kinds, black_count)) = (material(white), material(black));
+        let flip = compare_material(&white_kinds[..white_count], &black_kinds[..black_count]) == Ordering::Less;
+        let key = if flip {
+            material_key(&black_kinds[..black_count], &white_kinds[..white_count])
+        } else {
+            material_key(&white_kinds[..white_count], &black_kinds[..black_count])
+        };
+        let table = self.tables.get(&key)?;
+        let (strong, weak) = if flip { (&*black, &*white) } else { (&*white, &*black) };
+
+        let mut canonical = *setup;
+        canonical.len = 0;
+        for (pieces, color) in [(strong, Color::White), (weak, Color::Black)] {
+            for &(kind, square) in pieces {
+                canonical.push(kind, color, if flip { square ^ 56 } else { square });
+            }
+        }
+        canonical.color = if flip { setup.color.opponent() } else { setup.color };
+        TbValue::from_entry(table.data[table.index(&canonical)])
+    }
+
+    /// Probes a position.
+    ///
+    /// En passant captures are not stored in the tables; if one is possible, the
+    /// position is resolved by probing all its moves.
+    ///
+    /// # Arguments
+    /// * `position` - The board state.
+    /// * `color` - The side to move.
+    ///
+    /// # Returns
+    /// The value for the side to move, or `None` if the position has more than
+    /// [`MAX_PIECES`] pieces, its table is not loaded, castling is still possible
+    /// or it is not a standard chess position.
+    pub fn probe(&self, position: &Position, color: Color) -> Option<TbValue> {
+        let setup = Setup::from_position(position, color)?;
+        if can_castle(position) || position.variant != Variant::Standard {
+            return None;
+        }
+        if let Some(ep) = position.en_passant {
+            let moves = legal_moves(color, position);
+            let en_passant = |m: &Move| m.to == ep && m.piece.index() == Pieces::PAWN && m.from % 8 != m.to % 8;
+            if moves.iter().any(en_passant) {
+                return moves
+                    .iter()
+                    .map(|&m| self.probe(&play(m, position), color.opponent()).map(TbValue::parent))
+                    .collect::<Option<Vec<_>>>()?
+                    .into_iter()
+                    .max_by_key(|value| value.to_score(0));
+            }
+        }
+        self.probe_setup(&setup)
+    }
+
+    /// Values of all legal moves of a position, best first: the fastest wins, then
+    /// draws, then the slowest losses.
+    ///
+    /// # Returns
+    /// `None` if the position or any position after a move cannot be probed
+    /// (see [`Tablebase::probe`]), or if there are no legal moves.
+    pub fn root_moves(&self, position: &Position, color: Color) -> Option<Vec<(Move, TbValue)>> {
+        self.probe(position, color)?;
+        let mut values = legal_moves(color, position)
+            .into_iter()
+            .map(|m| self.probe(&play(m, position), color.opponent()).map(|value| (m, value.parent())))
+            .collect::<Option<Vec<_>>>()?;
+        values.sort_by_key(|(_, value)| -value.to_score(0));
+        (!values.is_empty()).then_some(values)
+    }
+
+    /// The best move of a position according to the tables, see [`Tablebase::root_moves`].
+    pub fn best_move(&self, position: &Position, color: Color) -> Option<(Move, TbValue)> {
+        self.root_moves(position, color).map(|moves| moves[0])
+    }
+
+    /// Writes every table to `<dir>/<name>.rtb`.
+    ///
+    /// # Errors
+    /// Returns `Err(String)` if a file cannot be written.
+    pub fn save_dir(&self, dir: &str) -> Result<(), String> {
+        fs::create_dir_all(dir).map_err(|e| format!("Cannot create {}: {}", dir, e))?;
+        for table in self.tables.values() {
+            let path = Path::new(dir).join(format!("{}.{}", table.name, EXTENSION));
+            fs::write(&path, table.to_bytes()).map_err(|e| format!("Cannot write {}: {}", path.display(), e))?;
+        }
+        Ok(())
+    }
+
+    /// Loads all `.rtb` files of a directory.
+    ///
+    /// # Errors
+    /// Returns `Err(String)` if the directory or a table cannot be read.
+    pub fn load_dir(dir: &str) -> Result<Tablebase, String> {
+        let mut tablebase = Tablebase::new();
+        let entries = fs::read_dir(dir).map_err(|e| format!("Cannot read {}: {}", dir, e))?;
+        for entry in entries {
+            let path = entry.map_err(|e| format!("Cannot read {}: {}", dir, e))?.path();
+            if path.extension().is_some_and(|extension| extension == EXTENSION) {
+                let bytes = fs::read(&path).map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+                let table = Table::from_bytes(&bytes).map_err(|e| format!("{}: {}", path.display(), e))?;
+                tablebase.insert(table);
+            }
+        }
+        Ok(tablebase)
+    }
+}
+
+/// Whether either side can still castle, i.e. has its king on the back rank and
+/// a rook on its starting square with the rights to castle.
+pub fn can_castle(position: &Position) -> bool {
+    let rights = &position.castling_rights;
+    [Color::White, Color::Black].into_iter().any(|color| {
+        let pieces = &position.bb_pieces[color.index()];
+        let back_rank = if color == Color::White { 0xFFu64 } else { 0xFFu64 << 56 };
+        pieces[Pieces::KING].0 & back_rank != 0
+            && [true, false].into_iter().any(|kingside| {
+                rights.has_right(color, kingside) && pieces[Pieces::ROOK].0 & 1u64 << rights.rook_square(color, kingside) != 0
+            })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fen::parse_fen;
+
+    /// Longest win in a table, in plies.
+    fn longest_mate(table: &Table) -> u32 {
+        table
+            .data
+            .iter()
+            .filter_map(|&entry| match TbValue::from_entry(entry)? {
+                TbValue::Win(distance) => Some(distance),
+                _ => None,
+            })
+            .max()
+            .unwrap_or(0)
+    }
+
+    fn probe_fen(tablebase: &Tablebase, fen: &str) -> Option<TbValue> {
+        let (position, color) = parse_fen(fen).unwrap();
+        tablebase.probe(&position, color)
+    }
+
+    #[test]
+    fn longest_mates() {
+        let mut tablebase = Tablebase::new();
+        tablebase.generate("KQK").unwrap();
+        tablebase.generate("KRK").unwrap();
+        // mate in 10 and mate in 16
+        assert_eq!(longest_mate(tablebase.table("KQK").unwrap()), 19);
+        assert_eq!(longest_mate(tablebase.table("KRK").unwrap()), 31);
+    }
+
+    #[test]
+    fn probe_values() {
+        let mut tablebase = Tablebase::new();
+        tablebase.generate("KQK").unwrap();
+        assert_eq!(probe_fen(&tablebase, "k7/8/1K6/8/8/8/7Q/8 w - - 0 1"), Some(TbValue::Win(1)));
+        assert_eq!(probe_fen(&tablebase, "k6Q/8/1K6/8/8/8/8/8 b - - 0 1"), Some(TbValue::Loss(0)));
+        // stalemate, and the queen hanging to the king
+        assert_eq!(probe_fen(&tablebase, "k7/2Q5/1K6/8/8/8/8/8 b - - 0 1"), Some(TbValue::Draw));
+        assert_eq!(probe_fen(&tablebase, "k7/1Q6/8/8/8/8/8/7K b - - 0 1"), Some(TbValue::Draw));
+        // the same ending with colors swapped
+        assert_eq!(probe_fen(&tablebase, "K7/8/1k6/8/8/8/7q/8 b - - 0 1"), Some(TbValue::Win(1)));
+        // the side not to move is in check
+        assert_eq!(probe_fen(&tablebase, "k7/8/1K6/8/8/8/8/7Q w - - 0 1"), None);
+        // KRK is not loaded
+        assert_eq!(probe_fen(&tablebase, "k7/8/1K6/8/8/8/7R/8 w - - 0 1"), None);
+
+        let (position, color) = parse_fen("k7/8/1K6/8/8/8/7Q/8 w - - 0 1").unwrap();
+        let (m, value) = tablebase.best_move(&position, color).unwrap();
+        assert_eq!(value, TbValue::Win(1));
+        assert_eq!(tablebase.probe(&play(m, &position), color.opponent()), Some(TbValue::Loss(0)));
+    }
+
+    #[test]
+    fn file_round_trip() {
+        let mut tablebase = Tablebase::new();
+        tablebase.generate("KRK").unwrap();
+        let table = tablebase.table("KRK").unwrap();
+        let read = Table::from_bytes(&table.to_bytes()).unwrap();
+        assert_eq!(read.name(), "KRK");
+        assert_eq!(read.data, table.data);
+
+        let dir = std::env::temp_dir().join(format!("rtb-test-{}", std::process::id()));
+        let dir = dir.to_str().unwrap();
+        tablebase.save_dir(dir).unwrap();
+        let loaded = Tablebase::load_dir(dir).unwrap();
+        fs::remove_dir_all(dir).unwrap();
+        assert_eq!(loaded.names(), vec!["KRK"]);
+        assert_eq!(loaded.table("KRK").unwrap().data, table.data);
+
+        let mut bytes = table.to_bytes();
+        bytes[0] = b'X';
+        assert!(Table::from_bytes(&bytes).is_err());
+        let bytes = table.to_bytes();
+        assert!(Table::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+    }
+}
@@ -0,0 +1,66 @@
+use crate::piece::Color;
+use crate::position::Position;
+
+// see: https://lichess.org/variant/threeCheck
+
+/// Number of checks that wins a three-check game.
+pub const CHECKS_TO_WIN: u8 = 3;
+
+/// Evaluation bonus in centipawns for having given 0, 1 or 2 checks.
+///
+/// Grows quickly, as every check brings the win closer.
+pub const CHECK_BONUS: [i32; 3] = [0, 150, 450];
+
+/// Returns how many checks `color` has given so far.
+pub fn checks_given(color: Color, position: &Position) -> u8 {
+    position.checks[color.index()]
+}
+
+/// Returns `true` if `color` has given enough checks to win.
+pub fn has_won(color: Color, position: &Position) -> bool {
+    checks_given(color, position) >= CHECKS_TO_WIN
+}
+
+/// Evaluation term for the checks given, from the point of view of `color`.
+pub fn evaluate(color: Color, position: &Position) -> i32 {
+    let bonus = |side: Color| CHECK_BONUS[checks_given(side, position).min(CHECKS_TO_WIN - 1) as usize];
+    bonus(color) - bonus(color.opponent())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fen::{parse_fen, to_fen};
+    use crate::game::GameResult;
+    use crate::variant::Variant;
+    use crate::variant::testing::{play, variant_game};
+
+    #[test]
+    fn check_counters_round_trip() {
+        let fen = "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 1 +1+2";
+        let (position, color) = parse_fen(fen).unwrap();
+        assert_eq!(position.variant, Variant::ThreeCheck);
+        assert_eq!(checks_given(Color::White, &position), 1);
+        assert_eq!(checks_given(Color::Black, &position), 2);
+        assert_eq!(to_fen(&position, color), fen);
+        assert!(parse_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 1 +4+0").is_err());
+    }
+
+    #[test]
+    fn checks_are_counted() {
+        let (mut game, color) = variant_game("4k3/8/8/8/8/8/8/R3K3 w - - 0 1 +0+0", Variant::ThreeCheck);
+        play("a1a8", color, &mut game);
+        assert_eq!(game.position.checks, [1, 0]);
+        assert_eq!(game.result, GameResult::Ongoing);
+        assert!(to_fen(&game.position, Color::Black).ends_with(" +1+0"));
+    }
+
+    #[test]
+    fn third_check_wins() {
+        let (mut game, color) = variant_game("4k3/8/8/8/8/8/8/R3K3 w - - 0 1 +2+0", Variant::ThreeCheck);
+        play("a1a8", color, &mut game);
+        assert!(has_won(Color::White, &game.position));
+        assert_eq!(game.result, GameResult::ThirdCheck(Color::Black));
+        assert_eq!(game.result.winner(), Some(Color::White));
+    }
+}
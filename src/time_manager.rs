@@ -0,0 +1,213 @@
+use std::time::{Duration, Instant};
+
+use crate::piece::Color;
+
+// see: https://www.chessprogramming.org/Time_Management
+
+/// Default time reserved per move for communication and GUI lag, in milliseconds.
+pub const DEFAULT_MOVE_OVERHEAD_MS: u64 = 30;
+/// Number of moves the remaining time is spread over when `movestogo` is not given.
+pub const DEFAULT_MOVES_TO_GO: u64 = 30;
+
+/// Clock information for one search, as given by the UCI `go` command.
+///
+/// All times are in milliseconds. Fields that are `None` were not given.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TimeControl {
+    /// White's remaining time.
+    pub wtime: Option<u64>,
+    /// Black's remaining time.
+    pub btime: Option<u64>,
+    /// White's increment per move.
+    pub winc: Option<u64>,
+    /// Black's increment per move.
+    pub binc: Option<u64>,
+    /// Moves until the next time control.
+    pub movestogo: Option<u64>,
+    /// Exact time to search, overriding the clock.
+    pub movetime: Option<u64>,
+}
+
+/// Decides how long a search may run.
+///
+/// Two limits are computed when the search starts:
+/// - the **soft limit**: no new iteration is started after it has passed.
+///   It grows when the search looks unstable (see [`TimeManager::update`]).
+/// - the **hard limit**: the search is stopped immediately once it has passed.
+pub struct TimeManager {
+    start: Instant,
+    base_soft_limit: Duration,
+    soft_limit: Duration,
+    hard_limit: Duration,
+}
+
+impl TimeManager {
+    /// Allocates time for one move.
+    ///
+    /// The remaining time (minus `move_overhead_ms`) is split evenly over the moves
+    /// to go, plus most of the increment. The hard limit allows up to four times
+    /// that, but never more than half of the remaining time.
+    ///
+    /// # Arguments
+    /// * `control` - The clock state.
+    /// * `color` - The side to move, whose clock is used.
+    /// * `move_overhead_ms` - Time reserved per move for communication lag.
+    ///
+    /// # Returns
+    /// A [`TimeManager`], or `None` if `control` contains no time for `color` (infinite search).
+    pub fn new(control: &TimeControl, color: Color, move_overhead_ms: u64) -> Option<Self> {
+        let start = Instant::now();
+
+        if let Some(movetime) = control.movetime {
+            let limit = Duration::from_millis(movetime.saturating_sub(move_overhead_ms).max(1));
+            return Some(TimeManager { start, base_soft_limit: limit, soft_limit: limit, hard_limit: limit });
+        }
+
+        let (time, inc) = match color {
+            Color::White => (control.wtime?, control.winc.unwrap_or(0)),
+            Color::Black => (control.btime?, control.binc.unwrap_or(0)),
+        };
+        let available = time.saturating_sub(move_overhead_ms).max(1);
+        let moves_to_go = control.movestogo.unwrap_or(DEFAULT_MOVES_TO_GO).clamp(1, DEFAULT_MOVES_TO_GO);
+
+        let soft = (available / moves_to_go + inc * 3 / 4).min(available);
+        let hard = (soft * 4).min(available / 2).max(soft.min(available));
+
+        Some(TimeManager {
+            start,
+            base_soft_limit: Duration::from_millis(soft),
+            soft_limit: Duration::from_millis(soft),
+            hard_limit: Duration::from_millis(hard),
+        })
+    }
+
+    /// Restarts the clock, e.g. on `ponderhit`: time spent pondering was the opponent's.
+    pub fn restart(&mut self) {
+        self.start = Instant::now();
+    }
+
+    /// Time passed since the search started.
+    pub fn elapsed(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    pub fn soft_limit(&self) -> Duration {
+        self.soft_limit
+    }
+
+    pub fn hard_limit(&self) -> Duration {
+        self.hard_limit
+    }
+
+    /// Returns `true` if no new iteration should be started.
+    pub fn soft_limit_reached(&self) -> bool {
+        self.elapsed() >= self.soft_limit
+    }
+
+    /// Returns `true` if the search must stop right away.
+    pub fn hard_limit_reached(&self) -> bool {
+        self.elapsed() >= self.hard_limit
+    }
+
+    /// Adjusts the soft limit after a completed iteration.
+    ///
+    /// - If the best move changed, the position is unclear: think 30% longer.
+    /// - If the score dropped (a *fail low* against the previous iteration), the
+    ///   expected move is in trouble: think 50% longer.
+    /// - Otherwise the soft limit returns to its base value.
+    ///
+    /// The soft limit never exceeds the hard limit.
+    ///
+    /// # Arguments
+    /// * `best_move_changed` - Whether this iteration's best move differs from the previous one.
+    /// * `score_drop` - How many centipawns the score fell compared to the previous iteration.
+    pub fn update(&mut self, best_move_changed: bool, score_drop: i32) {
+        let mut factor = 1.0;
+        if best_move_changed {
+            factor *= 1.3;
+        }
+        if score_drop > 30 {
+            factor *= 1.5;
+        }
+        self.soft_limit = self.base_soft_limit.mul_f64(factor).min(self.hard_limit);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limits(control: TimeControl, color: Color) -> (u64, u64) {
+        let time = TimeManager::new(&control, color, DEFAULT_MOVE_OVERHEAD_MS).unwrap();
+        (time.soft_limit().as_millis() as u64, time.hard_limit().as_millis() as u64)
+    }
+
+    #[test]
+    fn movetime_is_used_as_is() {
+        let control = TimeControl { movetime: Some(1000), wtime: Some(5000), ..Default::default() };
+        assert_eq!(limits(control, Color::White), (970, 970));
+        let control = TimeControl { movetime: Some(10), ..Default::default() };
+        assert_eq!(limits(control, Color::Black), (1, 1));
+    }
+
+    #[test]
+    fn no_clock_means_infinite() {
+        assert!(TimeManager::new(&TimeControl::default(), Color::White, 0).is_none());
+        let control = TimeControl { btime: Some(60_000), ..Default::default() };
+        assert!(TimeManager::new(&control, Color::White, 0).is_none());
+    }
+
+    #[test]
+    fn clock_allocation() {
+        // 59970 ms after the overhead, spread over 30 moves
+        let control = TimeControl { wtime: Some(60_000), btime: Some(30_000), ..Default::default() };
+        assert_eq!(limits(control, Color::White), (1999, 7996));
+        assert_eq!(limits(control, Color::Black), (999, 3996));
+
+        // three quarters of the increment are added
+        let control = TimeControl { wtime: Some(60_000), winc: Some(2000), binc: Some(0), ..Default::default() };
+        assert_eq!(limits(control, Color::White), (3499, 13996));
+
+        // the hard limit never takes more than half of the clock
+        let control = TimeControl { wtime: Some(60_000), movestogo: Some(5), ..Default::default() };
+        assert_eq!(limits(control, Color::White), (11994, 29985));
+        let control = TimeControl { wtime: Some(60_000), movestogo: Some(1), ..Default::default() };
+        assert_eq!(limits(control, Color::White), (59970, 59970));
+        // ...and no more than the clock holds, whatever the increment
+        let control = TimeControl { wtime: Some(1000), winc: Some(5000), ..Default::default() };
+        assert_eq!(limits(control, Color::White), (970, 970));
+    }
+
+    #[test]
+    fn move_overhead_is_reserved() {
+        let control = TimeControl { wtime: Some(3030), ..Default::default() };
+        let time = TimeManager::new(&control, Color::White, 30).unwrap();
+        assert_eq!(time.soft_limit(), Duration::from_millis(100));
+        let time = TimeManager::new(&control, Color::White, 1530).unwrap();
+        assert_eq!(time.soft_limit(), Duration::from_millis(50));
+        // an overhead larger than the clock leaves (almost) nothing
+        let time = TimeManager::new(&control, Color::White, 5000).unwrap();
+        assert!(time.hard_limit() <= Duration::from_millis(1));
+    }
+
+    #[test]
+    fn unstable_searches_get_more_time() {
+        let control = TimeControl { wtime: Some(60_030), ..Default::default() };
+        let mut time = TimeManager::new(&control, Color::White, 30).unwrap();
+        assert_eq!(time.soft_limit(), Duration::from_millis(2000));
+        time.update(true, 0);
+        assert_eq!(time.soft_limit(), Duration::from_millis(2600));
+        time.update(false, 50);
+        assert_eq!(time.soft_limit(), Duration::from_millis(3000));
+        time.update(true, 50);
+        assert_eq!(time.soft_limit(), Duration::from_millis(3900));
+        time.update(false, 10);
+        assert_eq!(time.soft_limit(), Duration::from_millis(2000));
+
+        // capped by the hard limit
+        let control = TimeControl { wtime: Some(60_030), movestogo: Some(2), ..Default::default() };
+        let mut time = TimeManager::new(&control, Color::White, 30).unwrap();
+        time.update(true, 100);
+        assert_eq!(time.soft_limit(), time.hard_limit());
+    }
+}
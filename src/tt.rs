@@ -0,0 +1,214 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::moves::Move;
+use crate::piece::{Color, Piece};
+use crate::search::is_mate_score;
+
+// see: https://www.chessprogramming.org/Transposition_Table
+
+/// How a stored score relates to the true value of the position.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bound {
+    /// The score is exact (it fell inside the search window).
+    Exact,
+    /// The search failed high: the true score is at least this score.
+    Lower,
+    /// The search failed low: the true score is at most this score.
+    Upper,
+}
+
+/// One stored search result.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TtEntry {
+    /// Full Zobrist key, to detect index collisions.
+    pub key: u64,
+    /// Best (or refuting) move found for the position, tried first on the next visit.
+    pub best_move: Option<Move>,
+    /// Score from the point of view of the side to move. Mate scores are stored
+    /// relative to this node, not the root; see [`TranspositionTable::probe`].
+    pub score: i32,
+    /// Remaining depth the score was searched with.
+    pub depth: u32,
+    pub bound: Bound,
+}
+
+/// A fixed-size hash table of search results, indexed by Zobrist key.
+///
+/// Each slot holds one entry; a new entry replaces the old one unless the old
+/// one belongs to the same position and was searched deeper.
+///
+/// The table can be shared between search threads (e.g. behind an `Arc`): every
+/// entry is packed into one 64-bit word and stored next to `key ^ data`, so a
+/// slot torn by two threads writing at once simply fails the key check on the
+/// next probe instead of returning a corrupted entry.
+///
+/// see: <https://www.chessprogramming.org/Shared_Hash_Table#Lockless>
+pub struct TranspositionTable {
+    slots: Vec<[AtomicU64; 2]>,
+}
+
+impl TranspositionTable {
+    /// Creates a table using roughly `megabytes` MB of memory (at least one entry).
+    pub fn new(megabytes: usize) -> Self {
+        let slot_size = std::mem::size_of::<[AtomicU64; 2]>();
+        let count = (megabytes * 1024 * 1024 / slot_size).max(1);
+        TranspositionTable {
+            slots: (0..count).map(|_| [AtomicU64::new(0), AtomicU64::new(0)]).collect(),
+        }
+    }
+
+    /// Removes all entries.
+    pub fn clear(&self) {
+        for slot in &self.slots {
+            slot[0].store(0, Ordering::Relaxed);
+            slot[1].store(0, Ordering::Relaxed);
+        }
+    }
+
+    fn index(&self, key: u64) -> usize {
+        (key % self.slots.len() as u64) as usize
+    }
+
+    /// Reads the raw entry in the slot for `key`, whichever position it belongs to.
+    fn load(&self, key: u64) -> Option<TtEntry> {
+        let slot = &self.slots[self.index(key)];
+        let checked_key = slot[0].load(Ordering::Relaxed);
+        let data = slot[1].load(Ordering::Relaxed);
+        unpack(checked_key ^ data, data)
+    }
+
+    /// Looks up the entry for `key`.
+    ///
+    /// # Arguments
+    /// * `key` - Zobrist key of the position.
+    /// * `ply` - Distance of the position from the root, used to turn stored
+    ///   mate scores back into root-relative ones.
+    ///
+    /// # Returns
+    /// The stored entry, or `None` if the slot is empty or holds another position.
+    pub fn probe(&self, key: u64, ply: i32) -> Option<TtEntry> {
+        let entry = self.load(key)?;
+        if entry.key != key {
+            return None;
+        }
+        Some(TtEntry { score: score_from_tt(entry.score, ply), ..entry })
+    }
+
+    /// Stores a search result for `key`.
+    ///
+    /// # Arguments
+    /// * `key` - Zobrist key of the position.
+    /// * `best_move` - Best move found, if any.
+    /// * `score` - Root-relative score of the position.
+    /// * `depth` - Remaining search depth.
+    /// * `bound` - Whether `score` is exact or a bound.
+    /// * `ply` - Distance of the position from the root.
+    pub fn store(&self, key: u64, best_move: Option<Move>, score: i32, depth: u32, bound: Bound, ply: i32) {
+        let old = self.load(key).filter(|old| old.key == key);
+        if let Some(old) = old
+            && old.depth > depth
+            && bound != Bound::Exact
+        {
+            return;
+        }
+        // keep an older best move rather than losing it when this search found none
+        let best_move = best_move.or_else(|| old.and_then(|old| old.best_move));
+        let data = pack(&TtEntry { key, best_move, score: score_to_tt(score, ply), depth, bound });
+
+        let slot = &self.slots[self.index(key)];
+        slot[0].store(key ^ data, Ordering::Relaxed);
+        slot[1].store(data, Ordering::Relaxed);
+    }
+}
+
+/*
+Packed entry layout (bits):
+ 0..16  score (i16)
+16..24  depth (saturated to 255)
+24..26  bound: 1 = exact, 2 = lower, 3 = upper (0 = empty slot)
+26..27  has move
+27..33  move from
+33..39  move to
+39..42  piece type
+42..43  piece color (1 = black)
+43..44  promoted from pawn
+44..45  drop
+45..46  has duck square
+46..52  duck square
+*/
+
+fn pack(entry: &TtEntry) -> u64 {
+    let mut data = (entry.score as i16 as u16) as u64;
+    data |= (entry.depth.min(255) as u64) << 16;
+    let bound = match entry.bound {
+        Bound::Exact => 1,
+        Bound::Lower => 2,
+        Bound::Upper => 3,
+    };
+    data |= bound << 24;
+    if let Some(m) = entry.best_move {
+        data |= 1 << 26;
+        data |= (m.from as u64) << 27;
+        data |= (m.to as u64) << 33;
+        data |= (m.piece.index() as u64) << 39;
+        data |= (m.piece.color().index() as u64) << 42;
+        data |= (m.promoted_from_pawn as u64) << 43;
+        data |= (m.drop as u64) << 44;
+        if let Some(duck) = m.duck {
+            data |= 1 << 45;
+            data |= (duck as u64) << 46;
+        }
+    }
+    data
+}
+
+fn unpack(key: u64, data: u64) -> Option<TtEntry> {
+    let bound = match (data >> 24) & 3 {
+        1 => Bound::Exact,
+        2 => Bound::Lower,
+        3 => Bound::Upper,
+        _ => return None,
+    };
+    let best_move = if (data >> 26) & 1 == 1 {
+        let color = if (data >> 42) & 1 == 1 { Color::Black } else { Color::White };
+        Some(Move {
+            from: ((data >> 27) & 63) as u8,
+            to: ((data >> 33) & 63) as u8,
+            piece: Piece::from_index(((data >> 39) & 7) as usize, color),
+            promoted_from_pawn: (data >> 43) & 1 == 1,
+            drop: (data >> 44) & 1 == 1,
+            duck: ((data >> 45) & 1 == 1).then_some(((data >> 46) & 63) as u8),
+        })
+    } else {
+        None
+    };
+    Some(TtEntry {
+        key,
+        best_move,
+        score: (data & 0xFFFF) as u16 as i16 as i32,
+        depth: ((data >> 16) & 0xFF) as u32,
+        bound,
+    })
+}
+
+/// Converts a root-relative mate score into one relative to the current node.
+fn score_to_tt(score: i32, ply: i32) -> i32 {
+    if !is_mate_score(score) {
+        score
+    } else if score > 0 {
+        score + ply
+    } else {
+        score - ply
+    }
+}
+
+/// Converts a node-relative mate score back into a root-relative one.
+fn score_from_tt(score: i32, ply: i32) -> i32 {
+    if !is_mate_score(score) {
+        score
+    } else if score > 0 {
+        score - ply
+    } else {
+        score + ply
+    }
+}
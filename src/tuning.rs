@@ -0,0 +1,313 @@
+use std::fs;
+
+use crate::datagen::{TrainingRecord, read_records};
+use crate::eval::{EvalParams, evaluate_with, pst_index};
+use crate::fen::parse_fen;
+use crate::piece::Color;
+use crate::position::{Position, Sides};
+
+// see: https://www.chessprogramming.org/Texel%27s_Tuning_Method
+
+/// A quiet position together with the result of the game it was taken from.
+#[derive(Debug, Clone, Copy)]
+pub struct TuningPosition {
+    pub position: Position,
+    pub color: Color,
+    /// Game result from White's point of view: `1.0` win, `0.5` draw, `0.0` loss.
+    pub result: f64,
+}
+
+/// Parses a game result token: `1-0`, `0-1`, `1/2-1/2`, or a number like `0.5`.
+///
+/// Quotes, brackets and semicolons around the token are ignored.
+fn parse_result(token: &str) -> Option<f64> {
+    let token = token.trim_matches(|c| matches!(c, '"' | '[' | ']' | ';' | '(' | ')'));
+    match token {
+        "1-0" => Some(1.0),
+        "0-1" => Some(0.0),
+        "1/2-1/2" | "1/2" => Some(0.5),
+        _ => token.parse::<f64>().ok().filter(|r| (0.0..=1.0).contains(r)),
+    }
+}
+
+/// Parses one line of a tuning file: a FEN followed by the game result as the last token.
+///
+/// Accepted layouts include `<fen> 1-0`, `<fen> [0.5]` and `<fen> c9 "1/2-1/2";`.
+///
+/// # Returns
+/// `Ok(None)` for blank lines and `#` comments.
+///
+/// # Errors
+/// Returns `Err(String)` if the FEN is invalid or no result is found.
+pub fn parse_tuning_line(line: &str) -> Result<Option<TuningPosition>, String> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return Ok(None);
+    }
+    let tokens: Vec<&str> = line.split_whitespace().collect();
+    let (last, rest) = tokens.split_last().expect("line is not empty");
+    let result = parse_result(last).ok_or_else(|| format!("No game result at the end of '{}'", line))?;
+    let fen: Vec<&str> = rest.iter().copied().filter(|t| *t != "c9").collect();
+    let (position, color) = parse_fen(&fen.join(" "))?;
+    Ok(Some(TuningPosition { position, color, result }))
+}
+
+/// Reads a tuning file, one position per line (see [`parse_tuning_line`]).
+///
+/// # Errors
+/// Returns `Err(String)` naming the first line that cannot be parsed.
+pub fn load_positions(path: &str) -> Result<Vec<TuningPosition>, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("Cannot read {}: {}", path, e))?;
+    let mut positions = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        let parsed = parse_tuning_line(line).map_err(|e| format!("Line {}: {}", number + 1, e))?;
+        positions.extend(parsed);
+    }
+    Ok(positions)
+}
+
+impl From<&TrainingRecord> for TuningPosition {
+    fn from(record: &TrainingRecord) -> Self {
+        TuningPosition { position: record.position, color: record.color, result: record.white_result() }
+    }
+}
+
+/// Reads tuning positions from a text file (see [`parse_tuning_line`]), or from
+/// a self-play record file (see [`crate::datagen`]) if the path ends in `.bin`.
+pub fn load_any(path: &str) -> Result<Vec<TuningPosition>, String> {
+    if path.ends_with(".bin") {
+        Ok(read_records(path)?.iter().map(TuningPosition::from).collect())
+    } else {
+        load_positions(path)
+    }
+}
+
+/// Maps a centipawn score (White's point of view) to an expected game result.
+///
+/// # Arguments
+/// * `score` - The evaluation in centipawns.
+/// * `k` - Scaling constant, see [`find_k`].
+pub fn sigmoid(score: f64, k: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-k * score / 400.0))
+}
+
+/// Mean squared error between game results and the results predicted by `params`.
+pub fn mean_error(params: &EvalParams, positions: &[TuningPosition], k: f64) -> f64 {
+    if positions.is_empty() {
+        return 0.0;
+    }
+    let total: f64 = positions
+        .iter()
+        .map(|p| {
+            let score = evaluate_with(params, Color::White, &p.position) as f64;
+            (p.result - sigmoid(score, k)).powi(2)
+        })
+        .sum();
+    total / positions.len() as f64
+}
+
+/// Finds the scaling constant `k` that minimizes [`mean_error`] for the given parameters.
+///
+/// `k` is fitted once, before tuning, so the error measures the parameters and not
+/// the scale of the evaluation.
+pub fn find_k(params: &EvalParams, positions: &[TuningPosition]) -> f64 {
+    let (mut low, mut high) = (0.0f64, 3.0f64);
+    // ternary search: the error is unimodal in k
+    for _ in 0..60 {
+        let a = low + (high - low) / 3.0;
+        let b = high - (high - low) / 3.0;
+        if mean_error(params, positions, a) < mean_error(params, positions, b) {
+            high = b;
+        } else {
+            low = a;
+        }
+    }
+    (low + high) / 2.0
+}
+
+/// The evaluation of a position as a sparse linear function of the parameter vector
+/// (see [`EvalParams::to_vector`]): pairs of parameter index and coefficient, from
+/// White's point of view.
+fn features(position: &Position) -> Vec<(usize, f64)> {
+    let mut features = Vec::with_capacity(64);
+    for side in [Sides::WHITE, Sides::BLACK] {
+        let (color, sign) = if side == Sides::WHITE { (Color::White, 1.0) } else { (Color::Black, -1.0) };
+        for piece_type in 0..6 {
+            let mut bb = position.bb_pieces[side][piece_type].0;
+            while bb != 0 {
+                let square = bb.trailing_zeros() as u8;
+                bb &= bb - 1;
+                features.push((piece_type, sign));
+                features.push((6 + piece_type * 64 + pst_index(square, color), sign));
+            }
+        }
+    }
+    features
+}
+
+/// Settings for [`tune`].
+#[derive(Debug, Clone, Copy)]
+pub struct TuningOptions {
+    /// Number of gradient descent steps over the whole data set.
+    pub iterations: usize,
+    /// Step size of the Adam optimizer, in centipawns.
+    pub learning_rate: f64,
+    /// Whether the king's material value is tuned. It cancels out whenever both
+    /// kings are on the board, so it is kept fixed by default.
+    pub tune_king_value: bool,
+}
+
+impl Default for TuningOptions {
+    fn default() -> Self {
+        TuningOptions { iterations: 2000, learning_rate: 1.0, tune_king_value: false }
+    }
+}
+
+/// Tunes evaluation parameters on a set of quiet positions.
+///
+/// The error is the [`mean_error`] of the logistic prediction [`sigmoid`], with
+/// `k` fixed. As the evaluation is linear in its parameters, the gradient of
+/// the error is computed exactly, and the parameters are optimized with Adam.
+/// The result is rounded to whole centipawns.
+///
+/// # Arguments
+/// * `start` - Parameters to start from, usually the current ones.
+/// * `positions` - Quiet positions with game results.
+/// * `k` - Scaling constant, see [`find_k`].
+/// * `options` - Iterations and step size.
+/// * `progress` - Called every 100 iterations with the iteration number and the current error.
+pub fn tune<F>(start: &EvalParams, positions: &[TuningPosition], k: f64, options: &TuningOptions, mut progress: F) -> EvalParams
+where
+    F: FnMut(usize, f64),
+{
+    let data: Vec<(Vec<(usize, f64)>, f64)> = positions.iter().map(|p| (features(&p.position), p.result)).collect();
+    let mut params: Vec<f64> = start.to_vector().into_iter().map(f64::from).collect();
+    let (beta1, beta2, epsilon) = (0.9, 0.999, 1e-8);
+    let mut m = vec![0.0; params.len()];
+    let mut v = vec![0.0; params.len()];
+    let scale = k * 10f64.ln() / 400.0;
+
+    for iteration in 1..=options.iterations {
+        let mut gradient = vec![0.0; params.len()];
+        let mut error = 0.0;
+        for (features, result) in &data {
+            let score: f64 = features.iter().map(|&(i, c)| params[i] * c).sum();
+            let predicted = sigmoid(score, k);
+            error += (result - predicted).powi(2);
+            // d/dscore (result - s)^2 = -2 (result - s) * s (1 - s) * ln(10) k / 400
+            let factor = -2.0 * (result - predicted) * predicted * (1.0 - predicted) * scale;
+            for &(i, c) in features {
+                gradient[i] += factor * c;
+            }
+        }
+        if !options.tune_king_value {
+            gradient[5] = 0.0;
+        }
+
+        let n = data.len().max(1) as f64;
+        for i in 0..params.len() {
+            let g = gradient[i] / n;
+            m[i] = beta1 * m[i] + (1.0 - beta1) * g;
+            v[i] = beta2 * v[i] + (1.0 - beta2) * g * g;
+            let m_hat = m[i] / (1.0 - beta1.powi(iteration as i32));
+            let v_hat = v[i] / (1.0 - beta2.powi(iteration as i32));
+            params[i] -= options.learning_rate * m_hat / (v_hat.sqrt() + epsilon);
+        }
+        if iteration % 100 == 0 {
+            progress(iteration, error / n);
+        }
+    }
+
+    let rounded: Vec<i32> = params.iter().map(|p| p.round() as i32).collect();
+    EvalParams::from_vector(&rounded).expect("parameter count is unchanged")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::eval::DEFAULT_PARAMS;
+
+    const FEN: &str = "4k3/8/8/8/8/8/4P3/4K3 w - - 0 1";
+
+    fn positions(lines: &[&str]) -> Vec<TuningPosition> {
+        lines.iter().map(|line| parse_tuning_line(line).unwrap().unwrap()).collect()
+    }
+
+    #[test]
+    fn result_tokens() {
+        assert_eq!(parse_result("1-0"), Some(1.0));
+        assert_eq!(parse_result("0-1"), Some(0.0));
+        assert_eq!(parse_result("1/2-1/2"), Some(0.5));
+        assert_eq!(parse_result("\"1/2-1/2\";"), Some(0.5));
+        assert_eq!(parse_result("[0.5]"), Some(0.5));
+        assert_eq!(parse_result("(1/2)"), Some(0.5));
+        assert_eq!(parse_result("1.5"), None);
+        assert_eq!(parse_result("w"), None);
+    }
+
+    #[test]
+    fn tuning_line_layouts() {
+        let layouts = [
+            (format!("{} 1-0", FEN), 1.0),
+            (format!("{} [0.5]", FEN), 0.5),
+            (format!("{} [0.0]", FEN), 0.0),
+            (format!("{} c9 \"1/2-1/2\";", FEN), 0.5),
+            (format!("  {} 0-1  ", FEN), 0.0),
+        ];
+        let (position, color) = parse_fen(FEN).unwrap();
+        for (line, result) in layouts {
+            let parsed = parse_tuning_line(&line).unwrap().unwrap();
+            assert_eq!(parsed.result, result, "{}", line);
+            assert_eq!(parsed.color, color);
+            assert_eq!(parsed.position.bb_pieces, position.bb_pieces);
+        }
+        assert!(parse_tuning_line("").unwrap().is_none());
+        assert!(parse_tuning_line("# comment").unwrap().is_none());
+        assert!(parse_tuning_line("4k3/8/8/8/8/8/4P3/4K3 w - -").is_err());
+        assert!(parse_tuning_line("not/a/fen w - - 0 1 1-0").is_err());
+    }
+
+    #[test]
+    fn params_text_round_trip() {
+        let mut params = DEFAULT_PARAMS.clone();
+        params.piece_values[1] = 333;
+        params.piece_square_tables[3][10] = -17;
+        let text = params.to_text();
+        assert_eq!(EvalParams::parse(&text).unwrap(), params);
+        assert!(EvalParams::parse("1 2 3").is_err());
+        assert!(EvalParams::parse(&text.replace("333", "x")).is_err());
+    }
+
+    #[test]
+    fn features_match_the_evaluation() {
+        let data = positions(&[
+            "r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4 1/2-1/2",
+            "4k3/8/8/8/8/8/4P3/4K3 b - - 0 1 1-0",
+        ]);
+        for p in &data {
+            let linear: f64 = features(&p.position).iter().map(|&(i, c)| DEFAULT_PARAMS.to_vector()[i] as f64 * c).sum();
+            assert_eq!(linear.round() as i32, evaluate_with(&DEFAULT_PARAMS, Color::White, &p.position));
+        }
+    }
+
+    #[test]
+    fn tuning_lowers_the_error() {
+        // an extra pawn that never wins, and an extra queen that always does
+        let data = positions(&[
+            "4k3/8/8/8/8/8/4P3/4K3 w - - 0 1 1/2-1/2",
+            "4k3/4p3/8/8/8/8/8/4K3 w - - 0 1 1/2-1/2",
+            "4k3/8/8/8/8/8/8/3QK3 w - - 0 1 1-0",
+            "3qk3/8/8/8/8/8/8/4K3 w - - 0 1 0-1",
+        ]);
+        let k = find_k(&DEFAULT_PARAMS, &data);
+        assert!(mean_error(&DEFAULT_PARAMS, &data, k) <= mean_error(&DEFAULT_PARAMS, &data, 1.0));
+        let before = mean_error(&DEFAULT_PARAMS, &data, 1.0);
+        let options = TuningOptions { iterations: 200, ..Default::default() };
+        let mut reports = 0;
+        let tuned = tune(&DEFAULT_PARAMS, &data, 1.0, &options, |_, _| reports += 1);
+        assert_eq!(reports, 2);
+        assert!(mean_error(&tuned, &data, 1.0) < before);
+        assert!(tuned.piece_values[0] < DEFAULT_PARAMS.piece_values[0]);
+        assert_eq!(tuned.piece_values[5], DEFAULT_PARAMS.piece_values[5]);
+    }
+}
@@ -0,0 +1,701 @@
+use std::io::{self, BufRead, Write};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::{self, JoinHandle};
+
+use crate::duck::{placement_suffix, split_placement};
+use crate::eval::EvalParams;
+use crate::engine::{Engine, SearchLimits};
+use crate::fen::{START_FEN, char_to_piece, parse_fen, piece_to_char, to_fen};
+use crate::helper::{index_to_square, square_to_index};
+use crate::make_move::legal_moves;
+use crate::moves::Move;
+use crate::piece::{Color, Piece};
+use crate::mcts::MctsSearcher;
+use crate::nnue::Network;
+use crate::polyglot::Book;
+use crate::position::Position;
+use crate::rng::Rng;
+use crate::skill::{MAX_ELO, MAX_SKILL_LEVEL, MIN_ELO, Skill};
+use crate::special_moves::castling_squares;
+use crate::search::{IterationCallback, IterationInfo, MATE_SCORE, Searcher, is_mate_score, play};
+use crate::syzygy::Syzygy;
+use crate::tablebase::Tablebase;
+use crate::time_manager::TimeControl;
+use crate::tt::TranspositionTable;
+use crate::variant::Variant;
+
+// see: https://www.wbec-ridderkerk.nl/html/UCIProtocol.html
+
+/// Engine name reported to the GUI.
+pub const ENGINE_NAME: &str = "rusty chess";
+
+/// Writes a move in UCI long algebraic notation, e.g. `e2e4`, `e7e8q`, the drop `N@f3`
+/// or the duck chess move `e2e4,d5`.
+pub fn move_to_uci(m: &Move) -> String {
+    if m.drop {
+        let letter = piece_to_char(m.piece).to_ascii_uppercase();
+        return format!("{}@{}", letter, index_to_square(m.to).to_ascii_lowercase());
+    }
+    let mut s = format!(
+        "{}{}",
+        index_to_square(m.from).to_ascii_lowercase(),
+        index_to_square(m.to).to_ascii_lowercase()
+    );
+    if m.promoted_from_pawn {
+        s.push(match m.piece {
+            Piece::Queen(_) => 'q',
+            Piece::Rook(_) => 'r',
+            Piece::Bishop(_) => 'b',
+            Piece::King(_) => 'k',
+            _ => 'n',
+        });
+    }
+    s.push_str(&placement_suffix(m));
+    s
+}
+
+/// Finds the legal move described by a UCI move string.
+///
+/// # Arguments
+/// * `text` - e.g. `e2e4`, `e7e8q`, `N@f3` or `e2e4,d5`.
+/// * `color` - The side to move.
+/// * `position` - The board state.
+///
+/// Castling is accepted both as the king's two-square move (`e1g1`) and as the
+/// king capturing its own rook (`e1h1`), whichever the position's castling mode
+/// does not use itself; GUIs send the latter in Chess960 games.
+///
+/// # Returns
+/// The matching legal move, or `None` if the string is malformed or the move is illegal.
+pub fn move_from_uci(text: &str, color: Color, position: &Position) -> Option<Move> {
+    if text.len() < 4 || !text.is_ascii() {
+        return None;
+    }
+    let (text, duck) = split_placement(text)?;
+    if let Some((letter, square)) = text.split_once('@') {
+        let piece = char_to_piece(letter.chars().next()?.to_ascii_uppercase())?;
+        let to = square_to_index(square)?;
+        return legal_moves(color, position)
+            .into_iter()
+            .find(|m| m.drop && m.to == to && m.piece.index() == piece.index() && m.duck == duck);
+    }
+    let from = square_to_index(text.get(0..2)?)?;
+    let to = square_to_index(text.get(2..4)?)?;
+    let promotion = text[4..].chars().next();
+
+    let moves = legal_moves(color, position);
+    let found = moves.iter().find(|m| {
+        m.from == from
+            && m.to == to
+            && m.duck == duck
+            && match promotion {
+                None => !m.promoted_from_pawn,
+                Some(c) => m.promoted_from_pawn && move_to_uci(m).ends_with(c.to_ascii_lowercase()),
+            }
+    });
+    found.or_else(|| {
+        moves.iter().find(|m| {
+            m.from == from
+                && m.duck == duck
+                && promotion.is_none()
+                && castling_squares(m, position).is_some_and(|castling| castling.rook_from == to || castling.king_to == to)
+        })
+    })
+    .copied()
+}
+
+/// Formats a score for an `info` line: `cp <centipawns>` or `mate <moves>`
+/// (negative if the side to move is getting mated).
+pub fn format_score(score: i32) -> String {
+    if is_mate_score(score) {
+        let plies = MATE_SCORE - score.abs();
+        let moves = (plies + 1) / 2;
+        format!("mate {}", if score > 0 { moves } else { -moves })
+    } else {
+        format!("cp {}", score)
+    }
+}
+
+/// Formats the `info` lines of one completed iteration, one per MultiPV line.
+pub fn format_info(info: &IterationInfo) -> Vec<String> {
+    let millis = info.elapsed.as_millis().max(1) as u64;
+    info.lines
+        .iter()
+        .enumerate()
+        .map(|(i, line)| {
+            let pv: Vec<String> = line.pv.iter().map(move_to_uci).collect();
+            format!(
+                "info depth {} multipv {} score {} nodes {} nps {} time {} pv {}",
+                info.depth,
+                i + 1,
+                format_score(line.score),
+                info.nodes,
+                info.nodes * 1000 / millis,
+                millis,
+                pv.join(" ")
+            )
+        })
+        .collect()
+}
+
+/// Writes one line to stdout and flushes it, as the GUI reads line by line.
+fn send(line: &str) {
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    let _ = writeln!(out, "{}", line);
+    let _ = out.flush();
+}
+
+/// The searchers of a UCI session, lent to the search thread together.
+struct Engines {
+    searcher: Searcher,
+    /// Shares the alpha-beta searcher's stop flag.
+    mcts: MctsSearcher,
+    /// Whether the `UseMCTS` option is on.
+    use_mcts: bool,
+}
+
+impl Engines {
+    fn new() -> Self {
+        let searcher = Searcher::new();
+        let mut mcts = MctsSearcher::new();
+        mcts.stop = Arc::clone(&searcher.stop);
+        Engines { searcher, mcts, use_mcts: false }
+    }
+}
+
+/// State of a UCI session: the current position, engine options and the running search.
+pub struct UciEngine {
+    position: Position,
+    color: Color,
+    /// The searchers, or `None` while they are lent to the search thread.
+    engines: Option<Engines>,
+    search_thread: Option<JoinHandle<Engines>>,
+    /// The searchers' stop flag, kept here so `stop` works while the searchers are lent out.
+    stop: Arc<AtomicBool>,
+    /// The searcher's pondering flag, cleared on `ponderhit`.
+    pondering: Arc<AtomicBool>,
+    /// Whether the `UseNNUE` option is on.
+    use_nnue: bool,
+    /// Network loaded through `EvalFile`, used instead of the bundled one.
+    eval_file: Option<Arc<Network>>,
+    /// The `Skill Level` option.
+    skill_level: u8,
+    /// Whether `UCI_LimitStrength` is on, in which case `UCI_Elo` sets the skill.
+    limit_strength: bool,
+    /// The `UCI_Elo` option.
+    elo: u32,
+    /// Whether the running search is an MCTS search started by `go ponder`.
+    /// MCTS cannot switch to a timed search, so `ponderhit` stops it instead.
+    mcts_pondering: bool,
+    /// Whether the `OwnBook` option is on.
+    own_book: bool,
+    /// Book loaded through `BookFile`.
+    book: Option<Book>,
+    book_rng: Rng,
+    /// Whether `UCI_Chess960` is on: castling moves are sent and received as
+    /// the king capturing its own rook.
+    chess960: bool,
+    /// The `UCI_Variant` option.
+    variant: Variant,
+}
+
+impl Default for UciEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl UciEngine {
+    pub fn new() -> Self {
+        let engines = Engines::new();
+        let stop = Arc::clone(&engines.searcher.stop);
+        let pondering = Arc::clone(&engines.searcher.pondering);
+        let (position, color) = parse_fen(START_FEN).expect("start FEN is valid");
+        UciEngine {
+            position,
+            color,
+            engines: Some(engines),
+            search_thread: None,
+            stop,
+            pondering,
+            use_nnue: false,
+            eval_file: None,
+            skill_level: MAX_SKILL_LEVEL,
+            limit_strength: false,
+            elo: MAX_ELO,
+            mcts_pondering: false,
+            own_book: false,
+            book: None,
+            book_rng: Rng::from_time(),
+            chess960: false,
+            variant: Variant::Standard,
+        }
+    }
+
+    /// Waits for a running search to finish and takes the searchers back.
+    fn join_search(&mut self) {
+        if let Some(handle) = self.search_thread.take() {
+            if let Ok(engines) = handle.join() {
+                self.engines = Some(engines);
+            } else {
+                // the search thread panicked; start over with fresh searchers
+                let engines = Engines::new();
+                self.stop = Arc::clone(&engines.searcher.stop);
+                self.pondering = Arc::clone(&engines.searcher.pondering);
+                self.engines = Some(engines);
+            }
+        }
+        self.mcts_pondering = false;
+        self.stop.store(false, Ordering::Relaxed);
+    }
+
+    fn engines(&mut self) -> &mut Engines {
+        self.join_search();
+        self.engines.as_mut().expect("searchers are home after join")
+    }
+
+    fn searcher(&mut self) -> &mut Searcher {
+        &mut self.engines().searcher
+    }
+
+    /// Handles one line of input.
+    ///
+    /// # Returns
+    /// `false` once the GUI sent `quit`.
+    pub fn handle_command(&mut self, line: &str) -> bool {
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        match tokens.first().copied() {
+            Some("uci") => {
+                send(&format!("id name {}", ENGINE_NAME));
+                send("id author nhg");
+                send("option name Hash type spin default 16 min 1 max 4096");
+                send("option name Threads type spin default 1 min 1 max 256");
+                send("option name MultiPV type spin default 1 min 1 max 256");
+                send("option name Move Overhead type spin default 30 min 0 max 5000");
+                send("option name Ponder type check default false");
+                send("option name UseMCTS type check default false");
+                send("option name UseNNUE type check default false");
+                send("option name EvalFile type string default <bundled>");
+                send("option name EvalParams type string default <builtin>");
+                send(&format!("option name Skill Level type spin default {0} min 0 max {0}", MAX_SKILL_LEVEL));
+                send("option name UCI_LimitStrength type check default false");
+                send(&format!("option name UCI_Elo type spin default {0} min {1} max {0}", MAX_ELO, MIN_ELO));
+                send("option name OwnBook type check default false");
+                send("option name BookFile type string default <empty>");
+                send("option name TablebasePath type string default <empty>");
+                send("option name SyzygyPath type string default <empty>");
+                send("option name UCI_Chess960 type check default false");
+                let variants: Vec<String> = Variant::ALL.iter().map(|v| format!("var {}", v.uci_name())).collect();
+                send(&format!("option name UCI_Variant type combo default chess {}", variants.join(" ")));
+                send("uciok");
+            }
+            Some("isready") => send("readyok"),
+            Some("ucinewgame") => {
+                let engines = self.engines();
+                engines.searcher.new_game();
+                engines.mcts.new_game();
+            }
+            Some("setoption") => self.set_option(&tokens),
+            Some("position") => {
+                self.join_search();
+                if let Err(e) = self.set_position(&tokens) {
+                    send(&format!("info string {}", e));
+                }
+            }
+            Some("go") => self.go(&tokens),
+            Some("ponderhit") => {
+                self.pondering.store(false, Ordering::Relaxed);
+                if self.mcts_pondering {
+                    self.stop.store(true, Ordering::Relaxed);
+                }
+            }
+            Some("stop") => {
+                self.stop.store(true, Ordering::Relaxed);
+                self.join_search();
+            }
+            Some("d") => send(&to_fen(&self.position, self.color)),
+            Some("quit") => {
+                self.stop.store(true, Ordering::Relaxed);
+                self.join_search();
+                return false;
+            }
+            _ => {}
+        }
+        true
+    }
+
+    /// Handles `setoption name <name> value <value>`.
+    fn set_option(&mut self, tokens: &[&str]) {
+        let name_start = tokens.iter().position(|&t| t == "name").map(|i| i + 1);
+        let value_start = tokens.iter().position(|&t| t == "value");
+        let (Some(name_start), Some(value_start)) = (name_start, value_start) else {
+            return;
+        };
+        let name = tokens[name_start..value_start].join(" ").to_ascii_lowercase();
+        let value = tokens[value_start + 1..].join(" ");
+        match name.as_str() {
+            // pondering is driven by `go ponder`; nothing to configure
+            "ponder" => return,
+            "usennue" => {
+                self.use_nnue = value.eq_ignore_ascii_case("true");
+                self.update_network();
+                return;
+            }
+            "evalfile" => {
+                self.eval_file = None;
+                if !value.is_empty() && value != "<bundled>" {
+                    match Network::load(&value) {
+                        Ok(network) => self.eval_file = Some(Arc::new(network)),
+                        Err(e) => send(&format!("info string {}", e)),
+                    }
+                }
+                self.update_network();
+                return;
+            }
+            "ownbook" => {
+                self.own_book = value.eq_ignore_ascii_case("true");
+                return;
+            }
+            "uci_chess960" => {
+                self.chess960 = value.eq_ignore_ascii_case("true");
+                return;
+            }
+            "uci_variant" => {
+                match Variant::from_name(&value) {
+                    Some(variant) => self.variant = variant,
+                    None => send(&format!("info string unknown variant '{}'", value)),
+                }
+                return;
+            }
+            "bookfile" => {
+                self.book = None;
+                if !value.is_empty() && value != "<empty>" {
+                    match Book::load(&value) {
+                        Ok(book) => self.book = Some(book),
+                        Err(e) => send(&format!("info string {}", e)),
+                    }
+                }
+                return;
+            }
+            "tablebasepath" => {
+                let tablebase = if value.is_empty() || value == "<empty>" {
+                    None
+                } else {
+                    match Tablebase::load_dir(&value) {
+                        Ok(tablebase) => {
+                            send(&format!("info string loaded {} tables", tablebase.names().len()));
+                            Some(Arc::new(tablebase))
+                        }
+                        Err(e) => {
+                            send(&format!("info string {}", e));
+                            None
+                        }
+                    }
+                };
+                self.searcher().tablebase = tablebase;
+                return;
+            }
+            "syzygypath" => {
+                let syzygy = if value.is_empty() || value == "<empty>" {
+                    None
+                } else {
+                    match Syzygy::new(&value) {
+                        Ok(syzygy) => {
+                            send(&format!("info string found {} Syzygy files", syzygy.len()));
+                            Some(Arc::new(syzygy))
+                        }
+                        Err(e) => {
+                            send(&format!("info string {}", e));
+                            None
+                        }
+                    }
+                };
+                self.searcher().syzygy = syzygy;
+                return;
+            }
+            "usemcts" => {
+                self.engines().use_mcts = value.eq_ignore_ascii_case("true");
+                return;
+            }
+            "uci_limitstrength" => {
+                self.limit_strength = value.eq_ignore_ascii_case("true");
+                self.update_skill();
+                return;
+            }
+            "evalparams" => {
+                let params = if value.is_empty() || value == "<builtin>" {
+                    None
+                } else {
+                    match EvalParams::load(&value) {
+                        Ok(params) => Some(Arc::new(params)),
+                        Err(e) => {
+                            send(&format!("info string {}", e));
+                            None
+                        }
+                    }
+                };
+                self.searcher().eval_params = params;
+                return;
+            }
+            _ => {}
+        }
+        let Ok(number) = value.parse::<usize>() else {
+            send(&format!("info string invalid value '{}'", value));
+            return;
+        };
+
+        let engines = self.engines();
+        let searcher = &mut engines.searcher;
+        match name.as_str() {
+            "hash" => searcher.tt = Arc::new(TranspositionTable::new(number.max(1))),
+            "threads" => searcher.threads = number.max(1),
+            "multipv" => {
+                searcher.multi_pv = number.max(1);
+                engines.mcts.multi_pv = number.max(1);
+            }
+            "move overhead" => {
+                searcher.move_overhead_ms = number as u64;
+                engines.mcts.move_overhead_ms = number as u64;
+            }
+            "skill level" => {
+                self.skill_level = number.min(MAX_SKILL_LEVEL as usize) as u8;
+                self.update_skill();
+            }
+            "uci_elo" => {
+                self.elo = number.min(u32::MAX as usize) as u32;
+                self.update_skill();
+            }
+            _ => send(&format!("info string unknown option '{}'", name)),
+        }
+    }
+
+    /// Hands the network selected by `UseNNUE` and `EvalFile` to the searcher.
+    fn update_network(&mut self) {
+        let network = match (self.use_nnue, &self.eval_file) {
+            (false, _) => None,
+            (true, Some(network)) => Some(Arc::clone(network)),
+            (true, None) => Some(Arc::new(Network::bundled())),
+        };
+        self.searcher().network = network;
+    }
+
+    /// Hands the skill selected by `Skill Level` or `UCI_LimitStrength`/`UCI_Elo` to the searcher.
+    fn update_skill(&mut self) {
+        let skill = if self.limit_strength { Skill::from_elo(self.elo) } else { Skill::from_level(self.skill_level) };
+        self.searcher().skill = skill;
+    }
+
+    /// Handles `position (startpos | fen <fen>) [moves <move>...]`.
+    fn set_position(&mut self, tokens: &[&str]) -> Result<(), String> {
+        let moves_start = tokens.iter().position(|&t| t == "moves");
+        let setup_end = moves_start.unwrap_or(tokens.len());
+
+        let (mut position, mut color) = match tokens.get(1).copied() {
+            Some("startpos") => parse_fen(self.variant.start_fen())?,
+            Some("fen") => parse_fen(&tokens[2..setup_end].join(" "))?,
+            _ => return Err("expected 'startpos' or 'fen'".to_string()),
+        };
+        position.castling_rights.chess960 |= self.chess960;
+        // a FEN with a pocket or check counters has set its variant already
+        if self.variant != Variant::Standard {
+            position.variant = self.variant;
+        }
+
+        if let Some(start) = moves_start {
+            for text in &tokens[start + 1..] {
+                let m = move_from_uci(text, color, &position).ok_or_else(|| format!("illegal move '{}'", text))?;
+                position = play(m, &position);
+                color = color.opponent();
+            }
+        }
+        self.position = position;
+        self.color = color;
+        Ok(())
+    }
+
+    /// Handles `go` by starting a search on a background thread.
+    ///
+    /// Supports `depth`, `nodes`, `movetime`, `wtime`/`btime`/`winc`/`binc`/`movestogo`,
+    /// `infinite` and `ponder`. Without limits (or with `infinite`) the search runs until `stop`.
+    /// A `ponder` search runs until `ponderhit` (then continues as a timed search)
+    /// or `stop` (the opponent played another move). With `UseMCTS` the MCTS searcher
+    /// is used instead; its ponder search answers right away on `ponderhit`.
+    ///
+    /// With `OwnBook` on, a book move is played without searching (except for
+    /// `infinite` and `ponder`, which must wait for `stop`/`ponderhit`, and for
+    /// variants, which Polyglot books do not cover).
+    fn go(&mut self, tokens: &[&str]) {
+        self.join_search();
+        let infinite = tokens.contains(&"infinite");
+        let ponder = tokens.contains(&"ponder");
+        if self.own_book
+            && self.position.variant == Variant::Standard
+            && !infinite
+            && !ponder
+            && let Some(m) = self.book.as_ref().and_then(|book| book.pick_weighted(&self.position, self.color, &mut self.book_rng))
+        {
+            send(&format!("bestmove {}", move_to_uci(&m)));
+            return;
+        }
+        let mut engines = self.engines.take().expect("searchers are home after join");
+
+        let value = |name: &str| -> Option<u64> {
+            let i = tokens.iter().position(|&t| t == name)?;
+            tokens.get(i + 1)?.parse().ok()
+        };
+        let control = TimeControl {
+            wtime: value("wtime"),
+            btime: value("btime"),
+            winc: value("winc"),
+            binc: value("binc"),
+            movestogo: value("movestogo"),
+            movetime: value("movetime"),
+        };
+        let limits = if infinite {
+            SearchLimits::default()
+        } else {
+            SearchLimits { depth: value("depth").map(|d| d as u32), nodes: value("nodes"), time: control }
+        };
+        self.mcts_pondering = ponder && engines.use_mcts;
+
+        let position = self.position;
+        let color = self.color;
+        let report = || -> IterationCallback {
+            Box::new(|info: &IterationInfo| {
+                for line in format_info(info) {
+                    send(&line);
+                }
+            })
+        };
+        engines.searcher.on_iteration = Some(report());
+        engines.mcts.on_iteration = Some(report());
+
+        self.search_thread = Some(thread::spawn(move || {
+            let result = if engines.use_mcts {
+                let limits = if ponder { SearchLimits::default() } else { limits };
+                engines.mcts.go(&position, color, &limits)
+            } else if ponder {
+                engines.searcher.search_ponder(&position, color, &control)
+            } else {
+                engines.searcher.go(&position, color, &limits)
+            };
+            engines.searcher.on_iteration = None;
+            engines.mcts.on_iteration = None;
+
+            let engine: &dyn Engine = if engines.use_mcts { &engines.mcts } else { &engines.searcher };
+            match (result.best_move, engine.ponder_move()) {
+                (Some(m), Some(reply)) => send(&format!("bestmove {} ponder {}", move_to_uci(&m), move_to_uci(&reply))),
+                (Some(m), None) => send(&format!("bestmove {}", move_to_uci(&m))),
+                (None, _) => send("bestmove 0000"),
+            }
+            engines
+        }));
+    }
+}
+
+/// Runs the UCI protocol on stdin/stdout until `quit` or end of input.
+pub fn run() {
+    let mut engine = UciEngine::new();
+    for line in io::stdin().lock().lines() {
+        let Ok(line) = line else { break };
+        if !engine.handle_command(line.trim()) {
+            return;
+        }
+    }
+    engine.handle_command("quit");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+    use crate::make_move::is_legal;
+
+    #[test]
+    fn castling_accepts_both_notations() {
+        let (position, color) = parse_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        let two_square = move_from_uci("e1g1", color, &position).unwrap();
+        let king_takes_rook = move_from_uci("e1h1", color, &position).unwrap();
+        assert!(two_square.is_same(&king_takes_rook));
+        assert_eq!(move_to_uci(&king_takes_rook), "e1g1");
+        assert_eq!(castling_squares(&king_takes_rook, &position).map(|c| c.rook_from), Some(7));
+    }
+
+    #[test]
+    fn chess960_king_takes_rook() {
+        let (position, color) = parse_fen("rk5r/pppppppp/8/8/8/8/PPPPPPPP/RK5R w KQkq - 0 1").unwrap();
+        let kingside = move_from_uci("b1h1", color, &position).unwrap();
+        assert_eq!(move_to_uci(&kingside), "b1h1");
+        let castling = castling_squares(&kingside, &position).unwrap();
+        assert!(castling.kingside);
+        assert_eq!((castling.king_to, castling.rook_to), (6, 5));
+        // the king's destination names the same move
+        assert!(move_from_uci("b1g1", color, &position).unwrap().is_same(&kingside));
+
+        let queenside = move_from_uci("b1a1", color, &position).unwrap();
+        let castling = castling_squares(&queenside, &position).unwrap();
+        assert!(!castling.kingside);
+        assert_eq!((castling.king_to, castling.rook_to), (2, 3));
+    }
+
+    #[test]
+    fn multi_pv_option_sets_the_line_count() {
+        let mut engine = UciEngine::new();
+        engine.handle_command("setoption name MultiPV value 3");
+        engine.handle_command("position startpos");
+        engine.handle_command("go depth 3");
+        // joins the search
+        assert_eq!(engine.searcher().lines().len(), 3);
+        engine.handle_command("setoption name MultiPV value 0");
+        assert_eq!(engine.searcher().multi_pv, 1);
+    }
+
+    /// Waits up to `limit` for the search thread to finish on its own.
+    fn finishes_within(engine: &UciEngine, limit: Duration) -> bool {
+        let start = Instant::now();
+        while start.elapsed() < limit {
+            if engine.search_thread.as_ref().is_none_or(|handle| handle.is_finished()) {
+                return true;
+            }
+            thread::sleep(Duration::from_millis(5));
+        }
+        false
+    }
+
+    #[test]
+    fn ponderhit_turns_into_a_timed_search() {
+        let mut engine = UciEngine::new();
+        engine.handle_command("position startpos moves e2e4");
+        engine.handle_command("go ponder wtime 10000 btime 10000 movestogo 40");
+        // the clock does not run while pondering
+        assert!(!finishes_within(&engine, Duration::from_millis(300)));
+        engine.handle_command("ponderhit");
+        assert!(finishes_within(&engine, Duration::from_secs(2)));
+        let (position, color) = (engine.position, engine.color);
+        let best = engine.searcher().lines()[0].best_move;
+        assert!(is_legal(best, color, &position));
+    }
+
+    #[test]
+    fn ponder_miss_stops_and_searches_the_new_position() {
+        let mut engine = UciEngine::new();
+        engine.handle_command("position startpos moves e2e4");
+        engine.handle_command("go ponder wtime 10000 btime 10000");
+        thread::sleep(Duration::from_millis(50));
+        let start = Instant::now();
+        engine.handle_command("stop");
+        assert!(start.elapsed() < Duration::from_secs(1));
+        assert!(engine.search_thread.is_none());
+        assert!(!engine.stop.load(Ordering::Relaxed));
+
+        engine.handle_command("position startpos moves e2e4 d7d5");
+        engine.handle_command("go depth 3");
+        assert!(finishes_within(&engine, Duration::from_secs(5)));
+        let (position, color) = (engine.position, engine.color);
+        assert_eq!(color, Color::White);
+        let best = engine.searcher().lines()[0].best_move;
+        assert!(is_legal(best, color, &position));
+    }
+}
@@ -0,0 +1,134 @@
+use crate::fen::START_FEN;
+use crate::horde;
+use crate::racing_kings;
+
+// see: https://www.chessprogramming.org/Chess_Variants
+
+/// The rule set a [`Position`](crate::position::Position) is played under.
+///
+/// Chess960 is not a variant here: it only changes castling and the start
+/// position, see [`CastlingRights::chess960`](crate::piece::CastlingRights::chess960).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Variant {
+    #[default]
+    Standard,
+    /// Captured pieces go to the capturer's pocket and can be dropped back on
+    /// the board instead of moving (see [`crazyhouse`](crate::crazyhouse)).
+    Crazyhouse,
+    /// Captures explode, removing the capturer and all pieces but pawns next to
+    /// the capture square; blowing up the enemy king wins (see [`atomic`](crate::atomic)).
+    Atomic,
+    /// Losing chess: captures are compulsory, the king is an ordinary piece and a
+    /// side wins by losing all its pieces or being stalemated (see [`antichess`](crate::antichess)).
+    Antichess,
+    /// Giving the third check wins (see [`three_check`](crate::three_check)).
+    ThreeCheck,
+    /// Bringing the king to d4, e4, d5 or e5 wins (see [`king_of_the_hill`](crate::king_of_the_hill)).
+    KingOfTheHill,
+    /// White's 36 pawns and no king against the usual black army; Black wins by
+    /// capturing them all (see [`horde`](crate::horde)).
+    Horde,
+    /// Checks are forbidden and the first king to reach the eighth rank wins
+    /// (see [`racing_kings`](crate::racing_kings)).
+    RacingKings,
+    /// After every move the mover places the duck, a blocker nobody can capture,
+    /// on an empty square; there is no check and capturing the king wins (see [`duck`](crate::duck)).
+    Duck,
+}
+
+impl Variant {
+    /// All variants, in the order of the `UCI_Variant` option.
+    pub const ALL: [Variant; 9] = [
+        Variant::Standard,
+        Variant::Crazyhouse,
+        Variant::Atomic,
+        Variant::Antichess,
+        Variant::ThreeCheck,
+        Variant::KingOfTheHill,
+        Variant::Horde,
+        Variant::RacingKings,
+        Variant::Duck,
+    ];
+
+    /// The name used by the `UCI_Variant` option, e.g. `chess` or `crazyhouse`.
+    pub fn uci_name(self) -> &'static str {
+        match self {
+            Variant::Standard => "chess",
+            Variant::Crazyhouse => "crazyhouse",
+            Variant::Atomic => "atomic",
+            Variant::Antichess => "antichess",
+            Variant::ThreeCheck => "3check",
+            Variant::KingOfTheHill => "kingofthehill",
+            Variant::Horde => "horde",
+            Variant::RacingKings => "racingkings",
+            Variant::Duck => "duck",
+        }
+    }
+
+    /// The name used by the PGN `Variant` tag, e.g. `Standard` or `Crazyhouse`.
+    pub fn pgn_name(self) -> &'static str {
+        match self {
+            Variant::Standard => "Standard",
+            Variant::Crazyhouse => "Crazyhouse",
+            Variant::Atomic => "Atomic",
+            Variant::Antichess => "Antichess",
+            Variant::ThreeCheck => "Three-check",
+            Variant::KingOfTheHill => "King of the Hill",
+            Variant::Horde => "Horde",
+            Variant::RacingKings => "Racing Kings",
+            Variant::Duck => "Duck",
+        }
+    }
+
+    /// The FEN of the variant's start position.
+    pub fn start_fen(self) -> &'static str {
+        match self {
+            Variant::Horde => horde::START_FEN,
+            Variant::RacingKings => racing_kings::START_FEN,
+            _ => START_FEN,
+        }
+    }
+
+    /// Parses a UCI or PGN variant name, ignoring case.
+    ///
+    /// # Returns
+    /// The variant, or `None` if the name is unknown.
+    pub fn from_name(name: &str) -> Option<Variant> {
+        let name = name.trim().to_ascii_lowercase();
+        if name == "normal" {
+            return Some(Variant::Standard);
+        }
+        if name == "giveaway" || name == "losing" {
+            return Some(Variant::Antichess);
+        }
+        if name == "threecheck" {
+            return Some(Variant::ThreeCheck);
+        }
+        Variant::ALL
+            .into_iter()
+            .find(|variant| name == variant.uci_name() || name == variant.pgn_name().to_ascii_lowercase())
+    }
+}
+
+/// Helpers shared by the variant rule tests.
+#[cfg(test)]
+pub(crate) mod testing {
+    use super::Variant;
+    use crate::fen::parse_fen;
+    use crate::game::Game;
+    use crate::make_move::make_move_quiet;
+    use crate::piece::Color;
+    use crate::uci::move_from_uci;
+
+    /// Starts a game of `variant` from `fen`, returned with the side to move.
+    pub(crate) fn variant_game(fen: &str, variant: Variant) -> (Game, Color) {
+        let (position, color) = parse_fen(fen).unwrap();
+        (Game::with_variant(position, variant), color)
+    }
+
+    /// Plays the UCI move `text` for `color`, panicking if it is illegal.
+    pub(crate) fn play(text: &str, color: Color, game: &mut Game) {
+        let m = move_from_uci(text, color, &game.position).unwrap_or_else(|| panic!("{} is illegal", text));
+        make_move_quiet(m, game).unwrap();
+    }
+}
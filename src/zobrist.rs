@@ -0,0 +1,219 @@
+use crate::piece::Color;
+use crate::position::{Position, Sides};
+
+// see: https://www.chessprogramming.org/Zobrist_Hashing
+
+/// Random keys for Zobrist hashing.
+///
+/// Generated at compile time from a fixed seed, so hashes are identical across runs.
+pub struct ZobristKeys {
+    /// One key per `[side][piece_type][square]`.
+    pub pieces: [[[u64; 64]; 6]; 2],
+    /// XOR-ed in when Black is to move.
+    pub black_to_move: u64,
+    /// One key per castling flag, in the field order of [`CastlingRights`](crate::piece::CastlingRights).
+    pub castling: [u64; 6],
+    /// One key per file of the en passant square.
+    pub en_passant: [u64; 8],
+    /// One key per `[side][piece_type][count]` of a Crazyhouse pocket (pawn to queen,
+    /// counts of 1 to 16); empty pockets add nothing.
+    pub pockets: [[[u64; 17]; 5]; 2],
+    /// One key per square of a Crazyhouse piece promoted from a pawn.
+    pub promoted: [u64; 64],
+    /// One key per `[side][count]` of three-check checks given (1 to 3); no checks add nothing.
+    pub checks: [[u64; 4]; 2],
+    /// One key per square of the duck in duck chess.
+    pub duck: [u64; 64],
+    /// One key per [`Variant`](crate::variant::Variant), by discriminant; standard chess adds nothing.
+    pub variants: [u64; 16],
+}
+
+/// SplitMix64 step: returns the next state and its output.
+///
+/// see: <https://prng.di.unimi.it/splitmix64.c>
+pub const fn splitmix64(state: u64) -> (u64, u64) {
+    let state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    (state, z ^ (z >> 31))
+}
+
+const fn generate_keys() -> ZobristKeys {
+    let mut keys = ZobristKeys {
+        pieces: [[[0; 64]; 6]; 2],
+        black_to_move: 0,
+        castling: [0; 6],
+        en_passant: [0; 8],
+        pockets: [[[0; 17]; 5]; 2],
+        promoted: [0; 64],
+        checks: [[0; 4]; 2],
+        duck: [0; 64],
+        variants: [0; 16],
+    };
+    let mut state = 0x5EED_C0FF_EE15_600D;
+    let mut value;
+
+    let mut side = 0;
+    while side < 2 {
+        let mut piece_type = 0;
+        while piece_type < 6 {
+            let mut square = 0;
+            while square < 64 {
+                (state, value) = splitmix64(state);
+                keys.pieces[side][piece_type][square] = value;
+                square += 1;
+            }
+            piece_type += 1;
+        }
+        side += 1;
+    }
+    (state, value) = splitmix64(state);
+    keys.black_to_move = value;
+    let mut i = 0;
+    while i < 6 {
+        (state, value) = splitmix64(state);
+        keys.castling[i] = value;
+        i += 1;
+    }
+    let mut file = 0;
+    while file < 8 {
+        (state, value) = splitmix64(state);
+        keys.en_passant[file] = value;
+        file += 1;
+    }
+    let mut side = 0;
+    while side < 2 {
+        let mut piece_type = 0;
+        while piece_type < 5 {
+            let mut count = 1;
+            while count < 17 {
+                (state, value) = splitmix64(state);
+                keys.pockets[side][piece_type][count] = value;
+                count += 1;
+            }
+            piece_type += 1;
+        }
+        side += 1;
+    }
+    let mut square = 0;
+    while square < 64 {
+        (state, value) = splitmix64(state);
+        keys.promoted[square] = value;
+        square += 1;
+    }
+    let mut side = 0;
+    while side < 2 {
+        let mut count = 1;
+        while count < 4 {
+            (state, value) = splitmix64(state);
+            keys.checks[side][count] = value;
+            count += 1;
+        }
+        side += 1;
+    }
+    let mut square = 0;
+    while square < 64 {
+        (state, value) = splitmix64(state);
+        keys.duck[square] = value;
+        square += 1;
+    }
+    let mut variant = 1;
+    while variant < 16 {
+        (state, value) = splitmix64(state);
+        keys.variants[variant] = value;
+        variant += 1;
+    }
+    keys
+}
+
+/// The Zobrist keys used by [`zobrist_key`].
+pub static ZOBRIST: ZobristKeys = generate_keys();
+
+/// Computes the Zobrist hash of a position with `color` to move.
+///
+/// Two positions with the same pieces, side to move, castling rights, en passant
+/// square, Crazyhouse pockets and promoted pieces, three-check counters, duck and
+/// variant hash to the same key. Used to index the transposition table.
+///
+/// # Arguments
+/// * `position` - The board state.
+/// * `color` - The side to move.
+///
+/// # Returns
+/// A 64-bit hash key.
+pub fn zobrist_key(position: &Position, color: Color) -> u64 {
+    let mut key = 0u64;
+
+    for side in [Sides::WHITE, Sides::BLACK] {
+        for piece_type in 0..6 {
+            let mut bb = position.bb_pieces[side][piece_type].0;
+            while bb != 0 {
+                let square = bb.trailing_zeros() as usize;
+                bb &= bb - 1;
+                key ^= ZOBRIST.pieces[side][piece_type][square];
+            }
+        }
+    }
+
+    if color == Color::Black {
+        key ^= ZOBRIST.black_to_move;
+    }
+
+    let cr = &position.castling_rights;
+    let flags = [
+        cr.white_king_moved,
+        cr.white_kingside_rook_moved,
+        cr.white_queenside_rook_moved,
+        cr.black_king_moved,
+        cr.black_kingside_rook_moved,
+        cr.black_queenside_rook_moved,
+    ];
+    for (i, &flag) in flags.iter().enumerate() {
+        if flag {
+            key ^= ZOBRIST.castling[i];
+        }
+    }
+
+    if let Some(ep) = position.en_passant {
+        key ^= ZOBRIST.en_passant[(ep % 8) as usize];
+    }
+
+    for side in [Sides::WHITE, Sides::BLACK] {
+        for (piece_type, &count) in position.pockets[side].iter().enumerate() {
+            key ^= ZOBRIST.pockets[side][piece_type][count.min(16) as usize];
+        }
+        key ^= ZOBRIST.checks[side][position.checks[side].min(3) as usize];
+    }
+    let mut promoted = position.promoted.0;
+    while promoted != 0 {
+        let square = promoted.trailing_zeros() as usize;
+        promoted &= promoted - 1;
+        key ^= ZOBRIST.promoted[square];
+    }
+    if position.duck.0 != 0 {
+        key ^= ZOBRIST.duck[position.duck.0.trailing_zeros() as usize];
+    }
+    key ^= ZOBRIST.variants[position.variant as usize];
+    key
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fen::{START_FEN, parse_fen};
+    use crate::variant::Variant;
+
+    #[test]
+    fn variant_is_hashed() {
+        let (position, color) = parse_fen(START_FEN).unwrap();
+        let standard = zobrist_key(&position, color);
+        let mut keys = vec![standard];
+        for variant in Variant::ALL.into_iter().filter(|&variant| variant != Variant::Standard) {
+            let key = zobrist_key(&Position { variant, ..position }, color);
+            assert!(!keys.contains(&key), "{:?}", variant);
+            keys.push(key);
+        }
+        assert_eq!(ZOBRIST.variants[Variant::Standard as usize], 0);
+    }
+}